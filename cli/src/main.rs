@@ -2,13 +2,30 @@
 use clap::{Args, Parser, Subcommand};
 use hashassin_client::handle_crack::handle_crack;
 use hashassin_client::handle_upload::handle_upload;
+use hashassin_core::HashassinError;
+use hashassin_core::benchmark;
+use hashassin_core::charset::{Charset, DEFAULT_CHARSET};
 use hashassin_core::crack::{crack_passwords, load_hashes, load_rainbow_table};
+use hashassin_core::dictionary;
 use hashassin_core::dump_hashes;
 use hashassin_core::dump_rainbow_table;
 use hashassin_core::generate_hashes;
-use hashassin_core::generate_passwords;
+use hashassin_core::generate_passwords::{self, PasswordSource};
 use hashassin_core::generate_rainbow_table;
+use hashassin_core::hash::{self, HashAlgorithm};
+use hashassin_core::identify;
+use hashassin_core::import::{self, HashFormat};
+use hashassin_core::lengths::LengthDistribution;
+use hashassin_core::markov::{self, MarkovModel};
+use hashassin_core::mask::Mask;
+use hashassin_core::output::OutputFormat;
+use hashassin_core::passphrase::{Capitalization, Passphrase};
+use hashassin_core::policy::{AMBIGUOUS_CHARS, Policy};
+use hashassin_core::rules::{self, Rule, RuleSet};
+use hashassin_core::salt::{SaltPlacement, Salting};
+use hashassin_core::strength;
 use hashassin_server::server;
+use std::sync::Arc;
 
 #[derive(Debug, Parser)]
 struct MyArgs {
@@ -20,54 +37,129 @@ fn main() {
     let args = MyArgs::parse();
     match args.command {
         Commands::GenPasswords(args) => {
-            if let Err(e) = generate_passwords::generate_passwords(
-                args.chars,
-                args.out_file,
-                args.threads,
-                args.num,
-            ) {
+            let result = if args.exhaustive {
+                match &args.markov {
+                    Some(path) => MarkovModel::load(path).and_then(|model| {
+                        generate_passwords::enumerate_markov_passwords(
+                            model,
+                            args.out_file,
+                            args.skip,
+                            args.limit,
+                        )
+                    }),
+                    None => generate_passwords::enumerate_passwords(
+                        args.mask
+                            .unwrap_or_else(|| Mask::uniform(args.chars, &args.charset)),
+                        args.out_file,
+                        args.threads,
+                        args.skip,
+                        args.limit,
+                    ),
+                }
+            } else {
+                let policy = password_policy(&args);
+                password_source(&args).and_then(|source| {
+                    generate_passwords::generate_passwords(
+                        source,
+                        args.out_file,
+                        args.threads,
+                        args.num,
+                        args.seed,
+                        policy,
+                    )
+                })
+            };
+            if let Err(e) = result {
                 eprintln!("Error: {}", e);
                 std::process::exit(1);
             }
         }
         Commands::GenHashes(args) => {
-            if let Err(e) = generate_hashes::generate_hashes(
-                args.in_file,
-                args.out_file,
-                args.threads,
-                args.algorithm,
-            ) {
+            // Expressions such as md5(p+salt) place the salt themselves
+            let placement = args.salt_placement.unwrap_or(
+                if args.algorithm.salted() && args.algorithm.digest_len().is_some() {
+                    SaltPlacement::Expression
+                } else {
+                    SaltPlacement::Suffix
+                },
+            );
+            let salting = args.salt_len.map(|salt_len| Salting {
+                salt_len,
+                placement,
+            });
+            let result = args.rules.as_deref().map(RuleSet::load).transpose();
+            if let Err(e) = result.and_then(|rules| {
+                generate_hashes::generate_hashes(
+                    args.in_file,
+                    args.out_file,
+                    args.threads,
+                    args.algorithm,
+                    rules,
+                    salting,
+                )
+            }) {
                 eprintln!("Error: {}", e);
                 std::process::exit(1);
             }
         }
         Commands::DumpHashes(args) => {
-            if let Err(e) = dump_hashes::dump_hashes(&args.in_file) {
+            if let Err(e) = dump_hashes::dump_hashes(&args.in_file, args.format) {
                 eprintln!("Error: {}", e);
                 std::process::exit(1);
             }
         }
         Commands::GenRainbowTable(args) => {
-            if let Err(e) = generate_rainbow_table::generate_rainbow_table(
-                args.num_links,
-                args.threads,
-                args.out_file,
-                args.algorithm,
-                args.in_file,
-            ) {
+            let result = args.rules.as_deref().map(RuleSet::load).transpose();
+            if let Err(e) = result.and_then(|rules| {
+                generate_rainbow_table::generate_rainbow_table(
+                    args.num_links,
+                    args.threads,
+                    args.out_file,
+                    args.algorithm,
+                    args.in_file,
+                    rules,
+                )
+            }) {
                 eprintln!("Error generating rainbow table: {}", e);
                 std::process::exit(1);
             }
         }
+        Commands::Mangle(args) => {
+            let result = match args.rules.as_deref() {
+                Some(path) => RuleSet::load(path).map(|mut rules| {
+                    rules.extend(args.rule);
+                    rules
+                }),
+                None => Ok(RuleSet::new(args.rule)),
+            };
+            if let Err(e) = result
+                .and_then(|rules| rules::mangle_wordlist(&args.in_file, &args.out_file, &rules))
+            {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Commands::AuditPasswords(args) => {
+            if let Err(e) = strength::audit_passwords(&args.in_file, args.dictionary.as_deref()) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Commands::TrainMarkov(args) => {
+            if let Err(e) = markov::train_markov(&args.in_file, &args.out_file) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
         Commands::DumpRainbowTable(args) => {
-            if let Err(e) = dump_rainbow_table::dump_rainbow_table(&args.in_file) {
+            if let Err(e) = dump_rainbow_table::dump_rainbow_table(&args.in_file, args.format) {
                 eprintln!("Error dumping rainbow table: {}", e);
                 std::process::exit(1);
             }
         }
         Commands::Crack(args) => {
             match load_rainbow_table(&args.in_file) {
-                Ok(table) => match load_hashes(&args.hashes, &table.algorithm) {
+                Ok(table) => match load_hashes(&args.hashes, table.header.algorithm.as_ref()) {
                     Ok(hashes) => {
                         if let Err(e) = crack_passwords(
                             table,
@@ -87,6 +179,56 @@ fn main() {
                 }
             }
         }
+        Commands::CrackWordlist(args) => {
+            let result = args.rules.as_deref().map(RuleSet::load).transpose();
+            if let Err(e) = result.and_then(|rules| {
+                dictionary::crack_with_wordlist(
+                    &args.hashes,
+                    &args.wordlist,
+                    rules,
+                    args.threads,
+                    args.out_file.as_deref(),
+                )
+            }) {
+                eprintln!("Error cracking passwords: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Commands::ImportShadow(args) => {
+            if let Err(e) = import::import_shadow(&args.in_file, &args.out_file, args.algorithm) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Commands::ImportHashes(args) => {
+            if let Err(e) = import::import_hashes(
+                &args.in_file,
+                &args.out_file,
+                args.algorithm,
+                args.format,
+                args.salt_placement,
+            ) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Commands::IdentifyHash(args) => {
+            if let Err(e) = identify::identify_hashes(&args.hashes, args.in_file.as_deref()) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Commands::BenchmarkHashes(args) => {
+            if let Err(e) = benchmark::benchmark_hashes(
+                &args.in_file,
+                &args.algorithm,
+                args.threads,
+                args.limit,
+            ) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
         Commands::Server(args) => {
             let async_threads = match args.async_threads {
                 Some(n) if n > 0 => n,
@@ -144,12 +286,28 @@ fn main() {
 
 #[derive(Debug, Subcommand)]
 enum Commands {
-    GenPasswords(GenPasswordsArgs),
+    GenPasswords(Box<GenPasswordsArgs>),
     GenHashes(GenHashesArgs),
     DumpHashes(DumpHashesArgs),
     GenRainbowTable(GenRainbowTableArgs),
+    /// Expand every word of a wordlist with mangling rules
+    Mangle(MangleArgs),
+    /// Train a Markov model on a password list for gen-passwords --markov
+    TrainMarkov(TrainMarkovArgs),
+    /// Score the strength of every password in a file and summarize the results
+    AuditPasswords(AuditPasswordsArgs),
     DumpRainbowTable(DumpRainbowTableArgs),
     Crack(CrackArgs),
+    /// Crack plain or salted hashes by hashing every word of a wordlist
+    CrackWordlist(CrackWordlistArgs),
+    /// Read the crypt(3) hashes of a shadow file (user:hash:...) into a hash file
+    ImportShadow(ImportShadowArgs),
+    /// Read a text list of hashes (hex, user:hash, hashcat or John) into a hash file
+    ImportHashes(ImportHashesArgs),
+    /// List the algorithms that may have produced hashes and whether they can be cracked
+    IdentifyHash(IdentifyHashArgs),
+    /// Compare how fast algorithms hash the same passwords
+    BenchmarkHashes(BenchmarkHashesArgs),
     Server(ServerArgs),
     Client(ClientArgs),
 }
@@ -164,6 +322,129 @@ struct GenPasswordsArgs {
     threads: usize,
     #[arg(long, default_value_t = 1)]
     num: usize,
+    /// Comma separated character classes (lower, upper, digit, symbol, space, hex) or literal characters
+    #[arg(long, default_value = DEFAULT_CHARSET)]
+    charset: Charset,
+    /// Seed for reproducible output; the same seed, thread count and num always produce the same passwords
+    #[arg(long)]
+    seed: Option<u64>,
+    /// Per-position template such as ?u?l?l?l?d?d or Pass?d?d?d; overrides --chars and --charset
+    #[arg(long, conflicts_with_all = ["chars", "charset"])]
+    mask: Option<Mask>,
+    /// Enumerate every password of the keyspace in lexicographic order instead of sampling --num passwords
+    #[arg(long, conflicts_with_all = ["num", "seed", "min_chars", "lengths"])]
+    exhaustive: bool,
+    /// Number of passwords at the start of the keyspace to skip
+    #[arg(long, default_value_t = 0, requires = "exhaustive")]
+    skip: u128,
+    /// Maximum number of passwords to enumerate
+    #[arg(long, requires = "exhaustive")]
+    limit: Option<u128>,
+    /// Shortest password length; lengths between --min-chars and --max-chars are equally likely
+    #[arg(long, requires = "max_chars", conflicts_with_all = ["chars", "mask"])]
+    min_chars: Option<u8>,
+    /// Longest password length
+    #[arg(long, requires = "min_chars")]
+    max_chars: Option<u8>,
+    /// Length distribution as length:weight pairs, e.g. 8:50,10:30,12:20
+    #[arg(long, conflicts_with_all = ["chars", "mask", "min_chars"])]
+    lengths: Option<LengthDistribution>,
+    /// Markov model from train-markov; samples candidates, or with --exhaustive lists them by decreasing probability
+    #[arg(long, value_name = "MODEL", conflicts_with_all = ["chars", "charset", "mask", "min_chars", "lengths"])]
+    markov: Option<String>,
+    /// Wordlist for diceware-style passphrases made of --words words
+    #[arg(long, value_name = "WORDLIST", conflicts_with_all = ["chars", "charset", "mask", "exhaustive", "min_chars", "lengths", "markov"])]
+    passphrase: Option<String>,
+    /// Number of words in every passphrase
+    #[arg(long, default_value_t = 6, requires = "passphrase")]
+    words: usize,
+    /// String placed between passphrase words
+    #[arg(long, default_value = "-", requires = "passphrase")]
+    separator: String,
+    /// Capitalization of passphrase words: none, first, all or random
+    #[arg(long, default_value = "none", requires = "passphrase")]
+    capitalize: Capitalization,
+    /// Number of random digits inserted at a random position of every passphrase
+    #[arg(long, default_value_t = 0, requires = "passphrase")]
+    digits: usize,
+    /// Minimum number of uppercase letters in every password
+    #[arg(long, default_value_t = 0, conflicts_with = "exhaustive")]
+    min_upper: usize,
+    /// Minimum number of lowercase letters in every password
+    #[arg(long, default_value_t = 0, conflicts_with = "exhaustive")]
+    min_lower: usize,
+    /// Minimum number of digits in every password
+    #[arg(long, default_value_t = 0, conflicts_with = "exhaustive")]
+    min_digit: usize,
+    /// Minimum number of symbols in every password
+    #[arg(long, default_value_t = 0, conflicts_with = "exhaustive")]
+    min_symbol: usize,
+    /// Characters that must not appear in any password
+    #[arg(long, default_value = "", conflicts_with = "exhaustive")]
+    exclude: String,
+    /// Exclude easily confused characters (0O1lI|)
+    #[arg(long, conflicts_with = "exhaustive")]
+    no_ambiguous: bool,
+    /// Longest allowed run of one repeated character
+    #[arg(long, conflicts_with = "exhaustive")]
+    max_run: Option<usize>,
+}
+
+/// Collects the policy options of gen-passwords.
+fn password_policy(args: &GenPasswordsArgs) -> Policy {
+    let mut excluded = args.exclude.as_bytes().to_vec();
+    if args.no_ambiguous {
+        excluded.extend_from_slice(AMBIGUOUS_CHARS.as_bytes());
+    }
+    Policy {
+        min_upper: args.min_upper,
+        min_lower: args.min_lower,
+        min_digit: args.min_digit,
+        min_symbol: args.min_symbol,
+        excluded,
+        max_run: args.max_run,
+    }
+}
+
+/// Picks how gen-passwords builds every password from the mutually exclusive length options.
+fn password_source(args: &GenPasswordsArgs) -> Result<PasswordSource, HashassinError> {
+    if let Some(wordlist) = &args.passphrase {
+        let passphrase = Passphrase::new(
+            wordlist,
+            args.words,
+            args.separator.clone(),
+            args.capitalize,
+            args.digits,
+        )?;
+        // Passphrases go to stdout, so the report goes to stderr
+        eprintln!(
+            "Passphrase entropy: {:.1} bits ({} words from a list of {})",
+            passphrase.entropy_bits(),
+            args.words,
+            passphrase.wordlist_len()
+        );
+        return Ok(PasswordSource::Passphrase(Arc::new(passphrase)));
+    }
+    if let Some(path) = &args.markov {
+        return Ok(PasswordSource::Markov(Arc::new(MarkovModel::load(path)?)));
+    }
+    if let Some(mask) = &args.mask {
+        return Ok(PasswordSource::Mask(mask.clone()));
+    }
+    let lengths = match (&args.lengths, args.min_chars.zip(args.max_chars)) {
+        (Some(lengths), _) => lengths.clone(),
+        (None, Some((min, max))) => LengthDistribution::uniform(min, max)?,
+        (None, None) => {
+            return Ok(PasswordSource::Mask(Mask::uniform(
+                args.chars,
+                &args.charset,
+            )));
+        }
+    };
+    Ok(PasswordSource::Lengths {
+        lengths,
+        charset: args.charset.clone(),
+    })
 }
 
 #[derive(Debug, Args)]
@@ -174,14 +455,28 @@ struct GenHashesArgs {
     out_file: String,
     #[arg(long, default_value_t = 1)]
     threads: usize,
-    #[arg(long, default_value = "sha256")]
-    algorithm: String,
+    /// Hash algorithm by name or alias, or an expression such as md5(sha1(p)), sha256^1000(p) or md5(p+salt)
+    #[arg(long, default_value = "sha256", value_parser = hash::lookup)]
+    algorithm: Arc<dyn HashAlgorithm>,
+    /// Rules file (hashcat syntax) expanding every input line into one password per rule
+    #[arg(long, value_name = "FILE")]
+    rules: Option<String>,
+    /// Hash every password with its own random salt of this many bytes and write a salted hash file
+    #[arg(long)]
+    salt_len: Option<u8>,
+    /// Where the salt goes: prefix, suffix, hmac (the salt is the HMAC key) or expression (where the
+    /// expression puts `salt`); defaults to expression for expressions using `salt`, suffix otherwise
+    #[arg(long, requires = "salt_len")]
+    salt_placement: Option<SaltPlacement>,
 }
 
 #[derive(Debug, Args)]
 struct DumpHashesArgs {
     #[arg(long)]
     in_file: String,
+    /// Output format: text, json or csv
+    #[arg(long, default_value = "text")]
+    format: OutputFormat,
 }
 
 #[derive(Debug, Args)]
@@ -193,17 +488,59 @@ struct GenRainbowTableArgs {
 
     #[arg(long, required = true)]
     out_file: String,
-    #[arg(long, default_value = "md5")]
-    algorithm: String,
+    /// Hash algorithm by name or alias; must be unsalted
+    #[arg(long, default_value = "md5", value_parser = hash::lookup)]
+    algorithm: Arc<dyn HashAlgorithm>,
 
     #[arg(long, required = true)]
     in_file: String,
+
+    /// Rules file (hashcat syntax) expanding every input line into one chain start per rule
+    #[arg(long, value_name = "FILE")]
+    rules: Option<String>,
+}
+
+#[derive(Debug, Args)]
+struct MangleArgs {
+    #[arg(long, required = true)]
+    in_file: String,
+    #[arg(long, default_value = "std")]
+    out_file: String,
+    /// Rules file with one hashcat-style rule per line
+    #[arg(long, value_name = "FILE", required_unless_present = "rule")]
+    rules: Option<String>,
+    /// A single rule such as c$1, may be repeated; applied after the rules file
+    #[arg(long)]
+    rule: Vec<Rule>,
+}
+
+#[derive(Debug, Args)]
+struct TrainMarkovArgs {
+    /// Training passwords, one per line
+    #[arg(long, required = true)]
+    in_file: String,
+    /// Where the trained model is written
+    #[arg(long, required = true)]
+    out_file: String,
+}
+
+#[derive(Debug, Args)]
+struct AuditPasswordsArgs {
+    /// Passwords to audit, one per line
+    #[arg(long, required = true)]
+    in_file: String,
+    /// Wordlist of additional dictionary words, one per line
+    #[arg(long)]
+    dictionary: Option<String>,
 }
 
 #[derive(Debug, Args)]
 struct DumpRainbowTableArgs {
     #[arg(long, required = true)]
     in_file: String,
+    /// Output format: text, json or csv
+    #[arg(long, default_value = "text")]
+    format: OutputFormat,
 }
 
 #[derive(Debug, Args)]
@@ -221,6 +558,82 @@ struct CrackArgs {
     threads: usize,
 }
 
+#[derive(Debug, Args)]
+struct CrackWordlistArgs {
+    /// Plain or salted hash file from gen-hashes
+    #[arg(long)]
+    hashes: String,
+    /// Candidate passwords, one per line
+    #[arg(long)]
+    wordlist: String,
+    /// Rules file (hashcat syntax) expanding every word into one candidate per rule
+    #[arg(long, value_name = "FILE")]
+    rules: Option<String>,
+    #[arg(long)]
+    out_file: Option<String>,
+    #[arg(long, default_value_t = 1)]
+    threads: usize,
+}
+
+#[derive(Debug, Args)]
+struct ImportShadowArgs {
+    /// Shadow-style file with one user:hash:... line per account
+    #[arg(long)]
+    in_file: String,
+    #[arg(long)]
+    out_file: String,
+    /// Only import hashes of this algorithm; required if the file mixes several
+    #[arg(long, value_parser = hash::lookup)]
+    algorithm: Option<Arc<dyn HashAlgorithm>>,
+}
+
+#[derive(Debug, Args)]
+struct ImportHashesArgs {
+    /// Hash list, one hash per line
+    #[arg(long)]
+    in_file: String,
+    #[arg(long)]
+    out_file: String,
+    /// Algorithm of the hashes; identify-hash lists candidates
+    #[arg(long, value_parser = hash::lookup)]
+    algorithm: Arc<dyn HashAlgorithm>,
+    /// Format of the hash list: hex, user, hashcat or john
+    #[arg(long, default_value = "hex")]
+    format: HashFormat,
+    /// Read hashcat hash:salt lines into a salted hash file with this salt placement
+    #[arg(long)]
+    salt_placement: Option<SaltPlacement>,
+}
+
+#[derive(Debug, Args)]
+struct IdentifyHashArgs {
+    /// Hex digests or encoded hashes such as $6$... crypt strings; quote them in the shell
+    #[arg(required_unless_present = "in_file")]
+    hashes: Vec<String>,
+    /// File of hashes, one per line
+    #[arg(long)]
+    in_file: Option<String>,
+}
+
+#[derive(Debug, Args)]
+struct BenchmarkHashesArgs {
+    /// Passwords to hash, one per line
+    #[arg(long)]
+    in_file: String,
+    /// Algorithm to benchmark, may be repeated; cost parameters such as bcrypt:cost=10 are accepted
+    #[arg(
+        long,
+        value_parser = hash::lookup,
+        default_values = ["md5", "sha256", "sha3_512", "pbkdf2-sha256", "bcrypt", "scrypt", "argon2id"]
+    )]
+    algorithm: Vec<Arc<dyn HashAlgorithm>>,
+    #[arg(long, default_value_t = 1)]
+    threads: usize,
+    /// Only hash the first N passwords of the file
+    #[arg(long)]
+    limit: Option<usize>,
+}
+
 #[derive(Debug, Args)]
 struct ServerArgs {
     #[arg(long, default_value_t = String::from("127.0.0.1"))]
@@ -1,37 +1,219 @@
-use crate::radix_type::Radix;
+use crate::{HashassinError, radix_type::Radix};
+use des::{
+    Des,
+    cipher::{BlockEncrypt, KeyInit, generic_array::GenericArray},
+};
 use ethereum_types::{U256, U512};
+use md4::Md4;
 use scrypt::{
-    Scrypt,
-    password_hash::{PasswordHasher, SaltString, rand_core::OsRng},
+    Params, Scrypt,
+    password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString, rand_core::OsRng},
 };
 use sha2::Sha256;
 use sha3::{Digest, Sha3_512};
 
 /// Generates an MD5 hash from the provided password string.
-pub(crate) fn generate_md5_hash(password: String) -> Vec<u8> {
-    let hash = md5::compute(&password);
+pub(crate) fn generate_md5_hash(password: &[u8]) -> Vec<u8> {
+    let hash = md5::compute(password);
     hash.to_vec()
 }
 
-pub(crate) fn generate_sha256_hash(password: String) -> Vec<u8> {
+pub(crate) fn generate_sha256_hash(password: &[u8]) -> Vec<u8> {
     let mut hasher = Sha256::new();
     hasher.update(password);
     hasher.finalize().to_vec()
 }
 
-pub(crate) fn generate_sha3_512_hash(password: String) -> Vec<u8> {
+pub(crate) fn generate_sha3_512_hash(password: &[u8]) -> Vec<u8> {
     let mut hasher = Sha3_512::new();
-    hasher.update(password.as_bytes());
+    hasher.update(password);
+    hasher.finalize().to_vec()
+}
+
+/// Generates a hash with any RustCrypto digest, such as `sha1::Sha1` or `sha2::Sha512`.
+pub(crate) fn generate_digest<D: Digest>(password: &[u8]) -> Vec<u8> {
+    D::digest(password).to_vec()
+}
+
+/// Generates a BLAKE3 hash of `len` bytes using its extendable output.
+pub(crate) fn generate_blake3_hash(password: &[u8], len: usize) -> Vec<u8> {
+    let mut output = vec![0u8; len];
+    blake3::Hasher::new()
+        .update(password)
+        .finalize_xof()
+        .fill(&mut output);
+    output
+}
+
+/// Generates an NTLM hash: MD4 over the UTF-16LE encoding of the password.
+pub(crate) fn generate_ntlm_hash(password: &[u8]) -> Vec<u8> {
+    let utf16: Vec<u8> = String::from_utf8_lossy(password)
+        .encode_utf16()
+        .flat_map(u16::to_le_bytes)
+        .collect();
+    let mut hasher = Md4::new();
+    hasher.update(utf16);
     hasher.finalize().to_vec()
 }
 
-pub(crate) fn generate_scrypt_hash(password: String) -> Vec<u8> {
+/// Generates a LAN Manager hash.
+///
+/// The password is uppercased, padded with zeros or truncated to 14 bytes, and each 7-byte half is used as a
+/// DES key to encrypt the constant `KGS!@#$%`.
+pub(crate) fn generate_lm_hash(password: &[u8]) -> Vec<u8> {
+    const MAGIC: &[u8; 8] = b"KGS!@#$%";
+    let mut padded = [0u8; 14];
+    for (dst, src) in padded.iter_mut().zip(password) {
+        *dst = src.to_ascii_uppercase();
+    }
+
+    padded
+        .chunks_exact(7)
+        .flat_map(|half| {
+            let cipher = Des::new(GenericArray::from_slice(&lm_des_key(half)));
+            let mut block = GenericArray::clone_from_slice(MAGIC);
+            cipher.encrypt_block(&mut block);
+            block
+        })
+        .collect()
+}
+
+/// Spreads 56 key bits over 8 bytes, leaving the lowest bit of every byte for DES parity.
+fn lm_des_key(half: &[u8]) -> [u8; 8] {
+    let mut key = [0u8; 8];
+    key[0] = half[0] >> 1;
+    for i in 1..7 {
+        key[i] = ((half[i - 1] & ((1 << i) - 1)) << (7 - i)) | (half[i] >> (i + 1));
+    }
+    key[7] = half[6] & 0x7F;
+    key.map(|k| k << 1)
+}
+
+/// Hashes a password with scrypt and a random salt, returning the PHC string.
+pub(crate) fn generate_scrypt_hash(
+    password: &[u8],
+    params: Params,
+) -> Result<Vec<u8>, HashassinError> {
     let salt = SaltString::generate(&mut OsRng);
-    let password_hash = match Scrypt.hash_password(password.as_bytes(), &salt) {
-        Ok(hash) => hash,
-        Err(e) => return format!("Error generating scrypt hash: {e}").into_bytes(),
+    Scrypt
+        .hash_password_customized(password, None, None, params, &salt)
+        .map(|hash| hash.to_string().into_bytes())
+        .map_err(|e| hash_error("scrypt", e))
+}
+
+/// Hashes a password with Argon2 and a random salt, returning the PHC string.
+pub(crate) fn generate_argon2_hash(
+    password: &[u8],
+    algorithm: argon2::Algorithm,
+    params: argon2::Params,
+) -> Result<Vec<u8>, HashassinError> {
+    let salt = SaltString::generate(&mut OsRng);
+    let argon2 = argon2::Argon2::new(algorithm, argon2::Version::V0x13, params);
+    argon2
+        .hash_password(password, &salt)
+        .map(|hash| hash.to_string().into_bytes())
+        .map_err(|e| hash_error("argon2", e))
+}
+
+/// Hashes a password with PBKDF2 and a random salt, returning the PHC string.
+pub(crate) fn generate_pbkdf2_hash(
+    password: &[u8],
+    algorithm: pbkdf2::Algorithm,
+    params: pbkdf2::Params,
+) -> Result<Vec<u8>, HashassinError> {
+    let salt = SaltString::generate(&mut OsRng);
+    pbkdf2::Pbkdf2
+        .hash_password_customized(password, Some(algorithm.ident()), None, params, &salt)
+        .map(|hash| hash.to_string().into_bytes())
+        .map_err(|e| hash_error("pbkdf2", e))
+}
+
+/// Hashes a password with bcrypt and a random salt, returning the `$2b$` modular crypt string.
+pub(crate) fn generate_bcrypt_hash(password: &[u8], cost: u32) -> Result<Vec<u8>, HashassinError> {
+    bcrypt::hash(password, cost)
+        .map(String::into_bytes)
+        .map_err(|e| hash_error("bcrypt", e))
+}
+
+/// Hashes a password with md5crypt and a random salt, returning the `$1$` crypt string.
+// Legacy schemes are generated to test audits, not to store passwords
+#[allow(deprecated)]
+pub(crate) fn generate_md5_crypt_hash(password: &[u8]) -> Result<Vec<u8>, HashassinError> {
+    pwhash::md5_crypt::hash(password)
+        .map(String::into_bytes)
+        .map_err(|e| hash_error("md5crypt", e))
+}
+
+/// Hashes a password with sha256crypt or sha512crypt and a random salt, returning the `$5$` or `$6$` crypt
+/// string. Without `rounds` the default of 5000 is used and left out of the string.
+#[allow(deprecated)]
+pub(crate) fn generate_sha_crypt_hash(
+    password: &[u8],
+    bits: u16,
+    rounds: Option<u32>,
+) -> Result<Vec<u8>, HashassinError> {
+    let setup = pwhash::HashSetup { salt: None, rounds };
+    let hash = match bits {
+        256 => pwhash::sha256_crypt::hash_with(setup, password),
+        _ => pwhash::sha512_crypt::hash_with(setup, password),
+    };
+    hash.map(String::into_bytes)
+        .map_err(|e| hash_error(&format!("sha{bits}crypt"), e))
+}
+
+/// Describes a failure of a hash function.
+fn hash_error(algorithm: &str, e: impl std::fmt::Display) -> HashassinError {
+    HashassinError::HashError(format!("{algorithm}: {e}"))
+}
+
+/// Checks a password against a `$1$`, `$5$` or `$6$` crypt string, using the salt and rounds it records.
+pub(crate) fn verify_crypt_hash(password: &[u8], hash: &[u8]) -> bool {
+    let Ok(hash) = std::str::from_utf8(hash) else {
+        return false;
     };
-    password_hash.to_string().into_bytes()
+    if hash.starts_with("$1$") {
+        pwhash::md5_crypt::verify(password, hash)
+    } else if hash.starts_with("$5$") {
+        pwhash::sha256_crypt::verify(password, hash)
+    } else if hash.starts_with("$6$") {
+        pwhash::sha512_crypt::verify(password, hash)
+    } else {
+        false
+    }
+}
+
+/// Checks a password against a PHC string, using the algorithm, salt and parameters it records.
+fn verify_phc_hash(verifier: &impl PasswordVerifier, password: &[u8], phc: &[u8]) -> bool {
+    let Ok(phc) = std::str::from_utf8(phc) else {
+        return false;
+    };
+    match PasswordHash::new(phc) {
+        Ok(hash) => verifier.verify_password(password, &hash).is_ok(),
+        Err(_) => false,
+    }
+}
+
+/// Checks a password against a scrypt PHC string.
+pub(crate) fn verify_scrypt_hash(password: &[u8], phc: &[u8]) -> bool {
+    verify_phc_hash(&Scrypt, password, phc)
+}
+
+/// Checks a password against an Argon2 PHC string of any variant.
+pub(crate) fn verify_argon2_hash(password: &[u8], phc: &[u8]) -> bool {
+    verify_phc_hash(&argon2::Argon2::default(), password, phc)
+}
+
+/// Checks a password against a PBKDF2 PHC string of any digest.
+pub(crate) fn verify_pbkdf2_hash(password: &[u8], phc: &[u8]) -> bool {
+    verify_phc_hash(&pbkdf2::Pbkdf2, password, phc)
+}
+
+/// Checks a password against a bcrypt modular crypt string.
+pub(crate) fn verify_bcrypt_hash(password: &[u8], hash: &[u8]) -> bool {
+    match std::str::from_utf8(hash) {
+        Ok(hash) => bcrypt::verify(password, hash).unwrap_or(false),
+        Err(_) => false,
+    }
 }
 
 /// Reduces a hash to a printable ASCII password string.
@@ -99,3 +281,43 @@ fn encode(mut num: U512, radix: &Radix, length: u32) -> String {
 
     s
 }
+
+/// Decodes a keyspace index into one digit per position, most significant position first.
+///
+/// This is the mixed-radix counterpart of `encode`: position `i` is a digit in base `radixes[i]`, so
+/// walking indices in increasing order walks the keyspace in lexicographic order.
+///
+/// # Parameters
+///
+/// - `num`: The keyspace index to decode. Must be smaller than `keyspace_size(radixes)`.
+/// - `radixes`: The base of every position.
+///
+/// # Returns
+///
+/// A `Vec` holding the digit of every position, each smaller than the radix of that position.
+///
+pub(crate) fn decode_index(mut num: U512, radixes: &[Radix]) -> Vec<usize> {
+    let mut digits = vec![0; radixes.len()];
+    for (digit, radix) in digits.iter_mut().zip(radixes).rev() {
+        let (div, rem) = num.div_mod(U512::from(radix.get()));
+        num = div;
+        *digit = rem.low_u64() as usize;
+    }
+    digits
+}
+
+/// Computes the number of values in a mixed-radix keyspace.
+///
+/// # Parameters
+///
+/// - `radixes`: The base of every position.
+///
+/// # Returns
+///
+/// The product of all radixes, or `None` if it does not fit in a `U512`.
+///
+pub(crate) fn keyspace_size(radixes: &[Radix]) -> Option<U512> {
+    radixes.iter().try_fold(U512::one(), |acc, radix| {
+        acc.checked_mul(U512::from(radix.get()))
+    })
+}
@@ -0,0 +1,83 @@
+use std::{
+    io::{self, BufRead, BufReader},
+    sync::Arc,
+    time::Instant,
+};
+
+use rayon::prelude::*;
+
+use crate::{HashassinError, hash::HashAlgorithm, utils};
+
+/// Hashes the same passwords with every algorithm and prints how fast each one is, to show the cost gap
+/// between fast hashes and memory-hard or iterated key derivation functions.
+///
+/// Prints one tab separated `ALGORITHM HASHES SECONDS HASHES/S SLOWDOWN` line per algorithm, where the slowdown
+/// is relative to the fastest algorithm. The hash rate is also the rate at which a dictionary attack can test
+/// candidates against a single hash.
+///
+/// # Arguments
+///
+/// * `in_file` - The path to the passwords, one per line.
+/// * `algorithms` - The algorithms to compare.
+/// * `threads` - The number of threads hashing passwords.
+/// * `limit` - Optional number of passwords from the start of the file to use, to keep slow algorithms in check.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read, it holds no passwords, or `threads` is zero.
+pub fn benchmark_hashes(
+    in_file: &str,
+    algorithms: &[Arc<dyn HashAlgorithm>],
+    threads: usize,
+    limit: Option<usize>,
+) -> Result<(), HashassinError> {
+    if threads < 1 {
+        return Err(HashassinError::InvalidThreadCount);
+    }
+
+    let reader = BufReader::new(utils::open_file(in_file)?);
+    let passwords = reader
+        .lines()
+        .take(limit.unwrap_or(usize::MAX))
+        .collect::<io::Result<Vec<String>>>()
+        .map_err(|e| HashassinError::FileRead(e.to_string()))?;
+    if passwords.is_empty() {
+        return Err(HashassinError::InvalidInput(format!(
+            "{in_file} holds no passwords"
+        )));
+    }
+
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()
+        .map_err(|e| HashassinError::ThreadError(e.to_string()))?;
+
+    // Start the worker threads so the first algorithm is not charged for it
+    pool.install(|| passwords.par_iter().for_each(|_| ()));
+
+    let mut results = Vec::new();
+    for algorithm in algorithms {
+        let start = Instant::now();
+        pool.install(|| {
+            passwords
+                .par_iter()
+                .try_for_each(|password| algorithm.hash(password.as_bytes()).map(drop))
+        })?;
+        let seconds = start.elapsed().as_secs_f64();
+        results.push((algorithm.name(), seconds, passwords.len() as f64 / seconds));
+    }
+
+    let fastest = results.iter().map(|(_, _, rate)| *rate).fold(0.0, f64::max);
+    println!("ALGORITHM\tHASHES\tSECONDS\tHASHES/S\tSLOWDOWN");
+    for (name, seconds, rate) in results {
+        println!(
+            "{}\t{}\t{:.4}\t{:.1}\t{:.0}x",
+            name,
+            passwords.len(),
+            seconds,
+            rate,
+            fastest / rate
+        );
+    }
+    Ok(())
+}
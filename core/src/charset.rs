@@ -0,0 +1,178 @@
+use std::{fmt::Display, str::FromStr};
+
+use crate::HashassinError;
+
+/// Specification string matching the historical behaviour of drawing from all
+/// 95 printable ASCII characters.
+pub const DEFAULT_CHARSET: &str = "lower,upper,digit,symbol,space";
+
+const LOWER: &[u8] = b"abcdefghijklmnopqrstuvwxyz";
+const UPPER: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const DIGIT: &[u8] = b"0123456789";
+const SYMBOL: &[u8] = b"!\"#$%&'()*+,-./:;<=>?@[\\]^_`{|}~";
+const SPACE: &[u8] = b" ";
+const HEX: &[u8] = b"0123456789abcdef";
+
+/// A set of printable ASCII characters passwords are drawn from.
+///
+/// The characters are kept sorted and deduplicated, so the same specification
+/// always maps an index to the same character regardless of the order the
+/// classes were listed in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Charset(Vec<u8>);
+
+impl Charset {
+    /// Parses a comma separated charset specification.
+    ///
+    /// Each entry is either one of the named classes `lower`, `upper`, `digit`,
+    /// `symbol`, `space` and `hex`, or a literal set of characters such as
+    /// `abc123`. Entries are combined into a single set.
+    ///
+    /// # Errors
+    ///
+    /// Returns `HashassinError::InvalidInput` if the specification is empty or
+    /// contains characters outside the printable ASCII range.
+    pub fn parse(spec: &str) -> Result<Self, HashassinError> {
+        let mut chars = Vec::new();
+        for entry in spec.split(',').filter(|e| !e.is_empty()) {
+            match Self::class(entry) {
+                Some(class) => chars.extend_from_slice(class),
+                None => {
+                    if let Some(c) = entry.chars().find(|c| !(' '..='~').contains(c)) {
+                        return Err(HashassinError::InvalidInput(format!(
+                            "Charset contains non printable ASCII character {c:?}"
+                        )));
+                    }
+                    chars.extend_from_slice(entry.as_bytes());
+                }
+            }
+        }
+        Self::from_bytes(chars)
+    }
+
+    /// Builds a charset from raw bytes, sorting and removing duplicates.
+    ///
+    /// # Errors
+    ///
+    /// Returns `HashassinError::InvalidInput` if no characters are left.
+    pub fn from_bytes(mut chars: Vec<u8>) -> Result<Self, HashassinError> {
+        chars.sort_unstable();
+        chars.dedup();
+        if chars.is_empty() {
+            return Err(HashassinError::InvalidInput(
+                "Charset cannot be empty".to_string(),
+            ));
+        }
+        Ok(Charset(chars))
+    }
+
+    /// Returns the characters of a named class, if `name` is one.
+    pub fn class(name: &str) -> Option<&'static [u8]> {
+        match name {
+            "lower" => Some(LOWER),
+            "upper" => Some(UPPER),
+            "digit" => Some(DIGIT),
+            "symbol" => Some(SYMBOL),
+            "space" => Some(SPACE),
+            "hex" => Some(HEX),
+            _ => None,
+        }
+    }
+
+    /// Number of distinct characters in the set.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Always `false`; an empty charset cannot be constructed.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns the character at `index` in sorted order.
+    pub fn get(&self, index: usize) -> Option<char> {
+        self.0.get(index).map(|&b| b as char)
+    }
+
+    /// Returns `true` if `c` belongs to the set.
+    pub fn contains(&self, c: char) -> bool {
+        c.is_ascii() && self.0.binary_search(&(c as u8)).is_ok()
+    }
+
+    /// The characters of the set as sorted bytes.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Returns the set without the characters in `excluded`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `HashassinError::InvalidInput` if no characters are left.
+    pub fn without(&self, excluded: &[u8]) -> Result<Self, HashassinError> {
+        Self::from_bytes(
+            self.0
+                .iter()
+                .copied()
+                .filter(|c| !excluded.contains(c))
+                .collect(),
+        )
+    }
+}
+
+impl Default for Charset {
+    fn default() -> Self {
+        Charset((32..=126).collect())
+    }
+}
+
+impl FromStr for Charset {
+    type Err = HashassinError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Charset::parse(s)
+    }
+}
+
+impl Display for Charset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", String::from_utf8_lossy(&self.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn combines_classes_and_literals_in_sorted_order() -> Result<(), HashassinError> {
+        let charset = Charset::parse("digit,cba,a")?;
+        assert_eq!(charset.to_string(), "0123456789abc");
+        assert_eq!(charset.get(10), Some('a'));
+        assert!(charset.contains('7') && !charset.contains('d'));
+        assert_eq!(charset, Charset::parse("a,cba,digit")?);
+        Ok(())
+    }
+
+    #[test]
+    fn default_spec_holds_every_printable_character() -> Result<(), HashassinError> {
+        let charset = Charset::parse(DEFAULT_CHARSET)?;
+        assert_eq!(charset.len(), 95);
+        assert_eq!(charset, Charset::default());
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_empty_and_non_printable_charsets() -> Result<(), HashassinError> {
+        assert!(Charset::parse("").is_err());
+        assert!(Charset::parse(",").is_err());
+        assert!(Charset::parse("ab\tc").is_err());
+        assert!(Charset::parse("abé").is_err());
+        assert_eq!(
+            Charset::parse("hex")?.without(b"abcdef")?.to_string(),
+            "0123456789"
+        );
+        assert!(Charset::parse("ab")?.without(b"ba").is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,236 @@
+use std::sync::Arc;
+
+use hex::encode as hex_encode;
+
+use crate::{
+    HashassinError,
+    hash::{self, HashAlgorithm},
+};
+
+/// How composed algorithm names look, shown when a lookup fails.
+pub(crate) const PATTERN: &str = "<algorithm>[^<n>](<p|salt|expression> + ...)";
+
+/// Deepest nesting of calls an expression may have, which bounds the recursion of parsing and hashing.
+const MAX_DEPTH: usize = 16;
+
+/// An argument of a hash call.
+#[derive(Debug, Clone)]
+enum Term {
+    /// The password, `p`.
+    Password,
+    /// The salt of the hash file entry, `salt`.
+    Salt,
+    /// A nested call, contributing its digest as lowercase hex.
+    Call(Call),
+}
+
+/// `algorithm^iterations(args)`: the algorithm applied `iterations` times to the concatenated arguments.
+#[derive(Debug, Clone)]
+struct Call {
+    algorithm: Arc<dyn HashAlgorithm>,
+    iterations: u32,
+    args: Vec<Term>,
+}
+
+impl Call {
+    /// Raw digest of the call. Every iteration but the first hashes the hex digest of the previous one.
+    fn digest(&self, password: &[u8], salt: &[u8]) -> Result<Vec<u8>, HashassinError> {
+        let mut input = Vec::new();
+        for arg in &self.args {
+            match arg {
+                Term::Password => input.extend_from_slice(password),
+                Term::Salt => input.extend_from_slice(salt),
+                Term::Call(call) => {
+                    input.extend_from_slice(hex_encode(call.digest(password, salt)?).as_bytes())
+                }
+            }
+        }
+
+        let mut digest = self.algorithm.hash(&input)?;
+        for _ in 1..self.iterations {
+            digest = self.algorithm.hash(hex_encode(&digest).as_bytes())?;
+        }
+        Ok(digest)
+    }
+
+    fn uses_salt(&self) -> bool {
+        self.args.iter().any(|arg| match arg {
+            Term::Password => false,
+            Term::Salt => true,
+            Term::Call(call) => call.uses_salt(),
+        })
+    }
+
+    /// Canonical spelling of the call, without spaces and with canonical algorithm names.
+    fn name(&self) -> String {
+        let args: Vec<String> = self
+            .args
+            .iter()
+            .map(|arg| match arg {
+                Term::Password => "p".to_string(),
+                Term::Salt => "salt".to_string(),
+                Term::Call(call) => call.name(),
+            })
+            .collect();
+        match self.iterations {
+            1 => format!("{}({})", self.algorithm.name(), args.join("+")),
+            n => format!("{}^{}({})", self.algorithm.name(), n, args.join("+")),
+        }
+    }
+}
+
+/// A homemade construction of unsalted fixed-length algorithms, such as `md5(sha1(p))`, `sha256^1000(p)`
+/// or `md5(p+salt)`.
+///
+/// Nested calls and repeated iterations hash the lowercase hex digest of the inner step, as the PHP-style
+/// constructions found in web applications do; the outermost call produces a raw digest like any other
+/// algorithm. Expressions using `salt` are salted: they are stored in salted hash files with the `expression`
+/// salt placement and cannot be used for rainbow tables.
+#[derive(Debug, Clone)]
+pub struct Composed {
+    name: String,
+    root: Call,
+    salted: bool,
+}
+
+impl HashAlgorithm for Composed {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn digest_len(&self) -> Option<usize> {
+        self.root.algorithm.digest_len()
+    }
+
+    fn salted(&self) -> bool {
+        self.salted
+    }
+
+    fn hash(&self, password: &[u8]) -> Result<Vec<u8>, HashassinError> {
+        self.root.digest(password, &[])
+    }
+
+    fn hash_with_salt(&self, password: &[u8], salt: &[u8]) -> Result<Vec<u8>, HashassinError> {
+        self.root.digest(password, salt)
+    }
+}
+
+/// Parses an algorithm expression. Whitespace is ignored.
+pub(crate) fn parse(name: &str) -> Option<Arc<dyn HashAlgorithm>> {
+    let expression: String = name.chars().filter(|c| !c.is_whitespace()).collect();
+    // A bare name is not an expression; parsing it would look it up in the registry again
+    if !expression.contains('(') {
+        return None;
+    }
+    // The name has to fit the ALGORITHM LENGTH byte of file headers, which rules out huge inputs up front
+    if expression.len() > u8::MAX as usize {
+        return None;
+    }
+    let mut parser = Parser {
+        input: &expression,
+        pos: 0,
+        depth: 0,
+    };
+    let root = parser.call()?;
+    if parser.pos != expression.len() {
+        return None;
+    }
+    // Canonical algorithm names may be longer than the aliases they were written with
+    let name = root.name();
+    if name.len() > u8::MAX as usize {
+        return None;
+    }
+    Some(Arc::new(Composed {
+        name,
+        salted: root.uses_salt(),
+        root,
+    }))
+}
+
+/// Recursive descent parser over an expression without whitespace.
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+    /// Number of calls enclosing the current position.
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn rest(&self) -> &str {
+        &self.input[self.pos..]
+    }
+
+    fn eat(&mut self, token: char) -> bool {
+        if self.rest().starts_with(token) {
+            self.pos += token.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Takes the longest run of characters matching `accept`.
+    fn take_while(&mut self, accept: impl Fn(char) -> bool) -> &str {
+        let start = self.pos;
+        let len = self
+            .rest()
+            .find(|c| !accept(c))
+            .unwrap_or(self.rest().len());
+        self.pos += len;
+        &self.input[start..self.pos]
+    }
+
+    /// `algorithm[^n](term+term...)`
+    fn call(&mut self) -> Option<Call> {
+        let name = self
+            .take_while(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+            .to_string();
+        let algorithm = hash::registry().get(&name)?;
+        // Only unsalted fixed-length algorithms can be chained
+        algorithm.fixed_digest_len().ok()?;
+
+        let iterations = if self.eat('^') {
+            let count: u32 = self.take_while(|c| c.is_ascii_digit()).parse().ok()?;
+            (count > 0).then_some(count)?
+        } else {
+            1
+        };
+
+        if self.depth == MAX_DEPTH || !self.eat('(') {
+            return None;
+        }
+        self.depth += 1;
+        let mut args = vec![self.term()?];
+        while self.eat('+') {
+            args.push(self.term()?);
+        }
+        if !self.eat(')') {
+            return None;
+        }
+        self.depth -= 1;
+        Some(Call {
+            algorithm,
+            iterations,
+            args,
+        })
+    }
+
+    /// `p`, `salt` or a nested call.
+    fn term(&mut self) -> Option<Term> {
+        let rest = self.rest();
+        let word_end = rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '-'))
+            .unwrap_or(rest.len());
+        match &rest[..word_end] {
+            "p" => {
+                self.pos += 1;
+                Some(Term::Password)
+            }
+            "salt" => {
+                self.pos += 4;
+                Some(Term::Salt)
+            }
+            _ => self.call().map(Term::Call),
+        }
+    }
+}
@@ -1,130 +1,51 @@
-use crate::hash::{HashAlgorithm, hash_with_algorithm};
+use crate::hash::HashAlgorithm;
+use crate::hash_file::HashFile;
 use crate::reduction::reduce;
+use crate::table::MappedRainbowTable;
 use hex::encode as hex_encode;
 use rayon::prelude::*;
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::fs::File;
-use std::io::{Read, Write};
+use std::io::Write;
 use std::sync::{Arc, Mutex};
 
-#[derive(Debug, Clone)]
-pub struct ChainEntry {
-    pub start: String,
-    pub end: String,
+/// Maps a rainbow table into memory; see `MappedRainbowTable::open`.
+pub fn load_rainbow_table(path: &str) -> Result<MappedRainbowTable, String> {
+    MappedRainbowTable::open(path).map_err(|e| e.to_string())
 }
 
-#[derive(Debug)]
-pub struct RainbowTable {
-    pub chains: Vec<ChainEntry>,
-    pub algorithm: HashAlgorithm,
-    pub password_len: usize,
-    pub num_links: usize,
-    pub charset: Vec<u8>,
-    pub ascii_offset: u8,
-}
-
-fn read_exact_or_string(file: &mut File, buf: &mut [u8]) -> Result<(), String> {
-    file.read_exact(buf).map_err(|e| e.to_string())
-}
-
-pub fn load_rainbow_table(path: &str) -> Result<RainbowTable, String> {
-    let mut file = File::open(path).map_err(|e| format!("Failed to open rainbow table: {}", e))?;
-
-    let mut magic = [0u8; 12];
-    read_exact_or_string(&mut file, &mut magic)?;
-    if &magic != b"rainbowtable" {
-        return Err("Invalid magic word in rainbow table.".to_string());
+pub fn load_hashes(path: &str, algorithm: &dyn HashAlgorithm) -> Result<Vec<String>, String> {
+    let file = HashFile::read(path).map_err(|e| e.to_string())?;
+    if file.header.placement.is_some() {
+        return Err(
+            "Salted hashes cannot be cracked with a rainbow table, use crack-wordlist instead."
+                .to_string(),
+        );
     }
-
-    let mut version = [0u8; 1];
-    read_exact_or_string(&mut file, &mut version)?;
-
-    let mut algo_len = [0u8; 1];
-    read_exact_or_string(&mut file, &mut algo_len)?;
-    let algo_len = algo_len[0] as usize;
-
-    let mut algo_buf = vec![0u8; algo_len];
-    read_exact_or_string(&mut file, &mut algo_buf)?;
-    let algorithm = match std::str::from_utf8(&algo_buf)
-        .map_err(|e| e.to_string())?
-        .to_lowercase()
-        .as_str()
-    {
-        "md5" => HashAlgorithm::Md5,
-        "sha256" => HashAlgorithm::Sha256,
-        "sha3_512" => HashAlgorithm::Sha3_512,
-        _ => return Err("Unsupported algorithm.".to_string()),
-    };
-
-    let mut pwd_len_buf = [0u8; 1];
-    read_exact_or_string(&mut file, &mut pwd_len_buf)?;
-    let password_len = pwd_len_buf[0] as usize;
-
-    let mut charset_buf = [0u8; 16];
-    read_exact_or_string(&mut file, &mut charset_buf)?;
-    let _charset_size = u128::from_be_bytes(charset_buf);
-
-    let mut links_buf = [0u8; 16];
-    read_exact_or_string(&mut file, &mut links_buf)?;
-    let num_links = u128::from_be_bytes(links_buf) as usize;
-
-    let mut offset_buf = [0u8; 1];
-    read_exact_or_string(&mut file, &mut offset_buf)?;
-    let ascii_offset = offset_buf[0];
-
-    let mut data = Vec::new();
-    file.read_to_end(&mut data).map_err(|e| e.to_string())?;
-
-    let chain_size = password_len * 2;
-    let mut chains = Vec::new();
-    for chunk in data.chunks_exact(chain_size) {
-        let (start, end) = chunk.split_at(password_len);
-        chains.push(ChainEntry {
-            start: String::from_utf8(start.to_vec()).map_err(|e| e.to_string())?,
-            end: String::from_utf8(end.to_vec()).map_err(|e| e.to_string())?,
-        });
+    if file.header.algorithm.name() != algorithm.name() {
+        return Err(format!(
+            "The hash file holds {} hashes but the rainbow table is for {}.",
+            file.header.algorithm.name(),
+            algorithm.name()
+        ));
     }
 
-    let charset: Vec<u8> = (32..=126).collect();
-    Ok(RainbowTable {
-        chains,
-        algorithm,
-        password_len,
-        num_links,
-        charset,
-        ascii_offset,
-    })
-}
-
-pub fn load_hashes(path: &str, algorithm: &HashAlgorithm) -> Result<Vec<String>, String> {
-    let mut file = File::open(path).map_err(|e| format!("Failed to open hash file: {}", e))?;
-    let mut header = [0u8; 2];
-    read_exact_or_string(&mut file, &mut header)?;
-
-    let algo_len = header[1] as usize;
-    let mut skip = vec![0u8; algo_len + 1];
-    read_exact_or_string(&mut file, &mut skip)?;
-
-    let hash_len = match algorithm {
-        HashAlgorithm::Md5 => 16,
-        HashAlgorithm::Sha256 => 32,
-        HashAlgorithm::Sha3_512 => 64,
-        HashAlgorithm::Scrypt => 64,
-    };
+    let hash_len = algorithm.fixed_digest_len().map_err(|e| e.to_string())?;
     println!("Hash length: {}", hash_len);
-    println!("Algorithm: {:?}", algorithm);
+    println!("Algorithm: {}", algorithm.name());
 
-    let mut buffer = Vec::new();
-    file.read_to_end(&mut buffer).map_err(|e| e.to_string())?;
-    if buffer.len() % hash_len != 0 {
-        return Err("Invalid hash file length.".to_string());
-    }
-
-    Ok(buffer.chunks_exact(hash_len).map(hex_encode).collect())
+    Ok(file
+        .entries
+        .iter()
+        .map(|entry| hex_encode(&entry.hash))
+        .collect())
 }
 
+/// Cracks hashes by walking every chain of a mapped rainbow table in parallel. Chains are read in place
+/// from the mapping; a password is only copied out of it when its hash is cracked.
 pub fn crack_passwords(
-    rainbow_table: RainbowTable,
+    rainbow_table: MappedRainbowTable,
     hashes_to_crack: Vec<String>,
     threads: usize,
     out_path: Option<&str>,
@@ -137,37 +58,44 @@ pub fn crack_passwords(
     let hash_set = Arc::new(hashes_to_crack.clone());
     let found = Arc::new(Mutex::new(HashMap::new()));
 
-    rainbow_table.chains.par_iter().for_each(|chain| {
-        for i in (0..rainbow_table.num_links).rev() {
-            let mut pwd = chain.end.clone();
-            for _ in i..rainbow_table.num_links {
-                let hashed = hash_with_algorithm(&pwd, &rainbow_table.algorithm);
+    let header = &rainbow_table.header;
+    let algorithm = header.algorithm.as_ref();
+    let password_len = header.password_len as usize;
+    let num_links = header.num_links as usize;
+    let charset = header.charset();
+
+    rainbow_table.par_chains().try_for_each(|chain| {
+        for i in (0..num_links).rev() {
+            let mut pwd = Cow::Borrowed(chain.end);
+            for _ in i..num_links {
+                let hashed = algorithm.hash(&pwd).map_err(|e| e.to_string())?;
                 pwd = reduce(
                     &hex_encode(&hashed),
-                    rainbow_table.password_len,
-                    &rainbow_table.charset,
-                    rainbow_table.ascii_offset,
-                );
+                    password_len,
+                    &charset,
+                    header.ascii_offset,
+                )
+                .into_bytes()
+                .into();
             }
 
-            let mut candidate = chain.start.clone();
-            for _ in 0..rainbow_table.num_links {
-                let hashed = hash_with_algorithm(&candidate, &rainbow_table.algorithm);
+            let mut candidate = Cow::Borrowed(chain.start);
+            for _ in 0..num_links {
+                let hashed = algorithm.hash(&candidate).map_err(|e| e.to_string())?;
                 let hash_hex = hex_encode(&hashed);
-                if hash_set.contains(&hash_hex) {
-                    if let Ok(mut map) = found.lock() {
-                        map.entry(hash_hex.clone()).or_insert(candidate.clone());
-                    }
+                if hash_set.contains(&hash_hex)
+                    && let Ok(mut map) = found.lock()
+                {
+                    map.entry(hash_hex.clone())
+                        .or_insert_with(|| String::from_utf8_lossy(&candidate).into_owned());
                 }
-                candidate = reduce(
-                    &hash_hex,
-                    rainbow_table.password_len,
-                    &rainbow_table.charset,
-                    rainbow_table.ascii_offset,
-                );
+                candidate = reduce(&hash_hex, password_len, &charset, header.ascii_offset)
+                    .into_bytes()
+                    .into();
             }
         }
-    });
+        Ok::<_, String>(())
+    })?;
 
     let result = Arc::try_unwrap(found)
         .map_err(|_| "Could not unwrap Arc (still in use)".to_string())?
@@ -198,3 +126,70 @@ pub fn crack_passwords(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        HashassinError, hash, hash_file::HashEntry, salt::SaltPlacement, table::HashFileHeader,
+        utils::TempFile,
+    };
+
+    /// Loads the hash file `data` for `algorithm` from the temporary file `name`.
+    fn load(
+        name: &str,
+        data: &[u8],
+        algorithm: &str,
+    ) -> Result<Result<Vec<String>, String>, HashassinError> {
+        let file = TempFile::new(name, data)?;
+        Ok(load_hashes(file.path(), hash::lookup(algorithm)?.as_ref()))
+    }
+
+    #[test]
+    fn loads_version_1_files() -> Result<(), HashassinError> {
+        let hashes = load(
+            "crack-version-1",
+            include_bytes!("../../sample_outputs/100-sha256.hashes"),
+            "sha256",
+        )?
+        .map_err(HashassinError::InvalidFormat)?;
+        assert_eq!(hashes.len(), 100);
+        assert!(hashes[0].starts_with("ef6fd4ec"));
+        Ok(())
+    }
+
+    #[test]
+    fn loads_version_3_files() -> Result<(), HashassinError> {
+        let md5 = hash::lookup("md5")?;
+        let file = HashFile {
+            header: HashFileHeader::new(md5.clone(), None, true),
+            entries: vec![HashEntry {
+                password_len: None,
+                salt: Vec::new(),
+                label: Some("alice".to_string()),
+                hash: md5.hash(b"abc")?,
+            }],
+        };
+        let out = TempFile::new("crack-written", "")?;
+        file.write(out.path())?;
+        let data =
+            std::fs::read(out.path()).map_err(|e| HashassinError::FileOpen(e.to_string()))?;
+
+        let hashes =
+            load("crack-version-3", &data, "md5")?.map_err(HashassinError::InvalidFormat)?;
+        assert_eq!(hashes, ["900150983cd24fb0d6963f7d28e17f72"]);
+        assert!(load("crack-version-3", &data, "sha256")?.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_salted_version_2_files() -> Result<(), HashassinError> {
+        // VERSION 2, md5, PASSWORD LENGTH 3, suffix salts, then one entry
+        let mut data = vec![2, 3];
+        data.extend_from_slice(b"md5");
+        data.extend_from_slice(&[3, SaltPlacement::Suffix.to_byte(), 1, 0x42]);
+        data.extend_from_slice(&[0x11; 16]);
+        assert!(load("crack-version-2", &data, "md5")?.is_err());
+        Ok(())
+    }
+}
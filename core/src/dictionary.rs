@@ -0,0 +1,356 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Read, Write},
+    sync::{
+        Mutex, PoisonError,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+    thread,
+};
+
+use crossbeam_channel::{Receiver, Sender};
+use hex::encode as hex_encode;
+
+use crate::{
+    HashassinError,
+    hash_file::HashFile,
+    rules::{self, RuleSet},
+    table::HashFileHeader,
+    utils,
+};
+
+/// Number of candidates the wordlist reader may get ahead of the hashing threads, so the wordlist and its rule
+/// expansions are streamed instead of read into memory.
+const CANDIDATE_CHANNEL_CAPACITY: usize = 4096;
+
+/// A hash file entry to crack: its salt and hash.
+type Target = (Vec<u8>, Vec<u8>);
+
+/// Passwords found so far, shared by the hashing threads.
+struct Cracked {
+    passwords: Mutex<HashMap<Target, String>>,
+    /// Number of distinct targets not cracked yet.
+    remaining: AtomicUsize,
+    /// Set once every target is cracked or a thread failed, so the other threads stop.
+    stop: AtomicBool,
+}
+
+impl Cracked {
+    fn is_cracked(&self, salt: &[u8], hash: &[u8]) -> bool {
+        self.passwords
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .contains_key(&(salt.to_vec(), hash.to_vec()))
+    }
+
+    /// Records the password of a target, keeping the first one found.
+    fn record(&self, salt: &[u8], hash: &[u8], password: &str) {
+        let mut passwords = self
+            .passwords
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if passwords
+            .insert((salt.to_vec(), hash.to_vec()), password.to_string())
+            .is_none()
+            && self.remaining.fetch_sub(1, Ordering::Relaxed) == 1
+        {
+            self.stop.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Hashes a candidate the way the entries of a hash file with `salt` were hashed.
+fn hash_candidate(
+    header: &HashFileHeader,
+    salt: &[u8],
+    candidate: &str,
+) -> Result<Vec<u8>, HashassinError> {
+    match header.placement {
+        Some(placement) => placement.hash(header.algorithm.as_ref(), salt, candidate.as_bytes()),
+        None => header.algorithm.hash(candidate.as_bytes()),
+    }
+}
+
+/// Cracks the hashes of a hash file by hashing every word of a wordlist, for salted files once per distinct
+/// salt. Unlike rainbow tables this works for randomly salted hashes. Encoded hashes such as scrypt PHC
+/// strings are verified one by one with the salt and cost parameters they record.
+///
+/// Cracked hashes are printed in file order as `hash<TAB>password`, or `hash<TAB>salt<TAB>password` for
+/// salted files, with hash and salt in hex, followed by `<TAB>label` for labeled files. Encoded hashes are
+/// printed as they are.
+///
+/// # Arguments
+///
+/// * `hashes` - The path to a plain or salted hash file.
+/// * `wordlist` - The path to the candidate passwords, one per line.
+/// * `rules` - Optional mangling rules. When set, every word is expanded into one candidate per rule.
+/// * `threads` - The number of threads hashing candidates.
+/// * `out_path` - Optional output file; cracked hashes are printed to standard output without one.
+///
+/// # Errors
+///
+/// Returns an error if a file cannot be read or written, the hash file is malformed, or no hash is cracked.
+pub fn crack_with_wordlist(
+    hashes: &str,
+    wordlist: &str,
+    rules: Option<RuleSet>,
+    threads: usize,
+    out_path: Option<&str>,
+) -> Result<(), HashassinError> {
+    if threads < 1 {
+        return Err(HashassinError::InvalidThreadCount);
+    }
+
+    let targets = HashFile::read(hashes)?;
+    let reader = BufReader::new(utils::open_file(wordlist)?);
+
+    // Every candidate is hashed once per distinct salt and compared with all digests using that salt
+    let mut digests_by_salt: HashMap<&[u8], HashSet<&[u8]>> = HashMap::new();
+    for entry in &targets.entries {
+        digests_by_salt
+            .entry(&entry.salt)
+            .or_default()
+            .insert(&entry.hash);
+    }
+    let cracked = Cracked {
+        passwords: Mutex::new(HashMap::new()),
+        remaining: AtomicUsize::new(digests_by_salt.values().map(HashSet::len).sum()),
+        stop: AtomicBool::new(targets.entries.is_empty()),
+    };
+
+    let (tx_candidates, rx_candidates) = crossbeam_channel::bounded(CANDIDATE_CHANNEL_CAPACITY);
+    thread::scope(|scope| {
+        let sender = scope.spawn(|| send_candidates(reader, rules.as_ref(), tx_candidates));
+        let workers: Vec<_> = (0..threads)
+            .map(|_| {
+                let rx_candidates = rx_candidates.clone();
+                let (targets, digests_by_salt, cracked) = (&targets, &digests_by_salt, &cracked);
+                scope.spawn(move || {
+                    crack_candidates(targets, digests_by_salt, rx_candidates, cracked)
+                })
+            })
+            .collect();
+        drop(rx_candidates);
+
+        // A hashing error makes the reader stop, so it takes precedence over the reader's result
+        let mut result = Ok(());
+        for worker in workers {
+            let worker_result = worker.join().unwrap_or_else(|e| {
+                Err(HashassinError::ThreadJoin(format!(
+                    "Error joining a cracking thread: {e:?}"
+                )))
+            });
+            if result.is_ok() {
+                result = worker_result;
+            }
+        }
+        let sender_result = sender.join().unwrap_or_else(|e| {
+            Err(HashassinError::ThreadJoin(format!(
+                "Error joining the wordlist reader: {e:?}"
+            )))
+        });
+        result.and(sender_result)
+    })?;
+    let found = cracked
+        .passwords
+        .into_inner()
+        .unwrap_or_else(PoisonError::into_inner);
+    let algorithm = &targets.header.algorithm;
+
+    if found.is_empty() {
+        return Err(HashassinError::CustomError(
+            "No passwords found.".to_string(),
+        ));
+    }
+
+    let mut writer: BufWriter<Box<dyn Write>> = match out_path {
+        Some(path) => BufWriter::new(Box::new(File::create(path).map_err(|e| {
+            HashassinError::CreateFile(format!("Error creating output file: {e:?}"))
+        })?)),
+        None => BufWriter::new(Box::new(io::stdout())),
+    };
+    for entry in &targets.entries {
+        let Some(password) = found.get(&(entry.salt.clone(), entry.hash.clone())) else {
+            continue;
+        };
+        let mut line = if algorithm.digest_len().is_none() {
+            format!("{}\t{}", String::from_utf8_lossy(&entry.hash), password)
+        } else if targets.header.placement.is_some() {
+            format!(
+                "{}\t{}\t{}",
+                hex_encode(&entry.hash),
+                hex_encode(&entry.salt),
+                password
+            )
+        } else {
+            format!("{}\t{}", hex_encode(&entry.hash), password)
+        };
+        if targets.header.labeled {
+            line = format!("{}\t{}", line, entry.label.as_deref().unwrap_or_default());
+        }
+        writeln!(writer, "{}", line).map_err(|e| HashassinError::WriteError(e.to_string()))?;
+    }
+    writer
+        .flush()
+        .map_err(|e| HashassinError::WriteError(e.to_string()))
+}
+
+/// Reads the wordlist, expands every word with the rules and sends the candidates to the hashing threads.
+///
+/// Sending stops early once every hashing thread has stopped.
+///
+/// # Errors
+///
+/// Returns `HashassinError::FileRead` if a line of the wordlist cannot be read.
+fn send_candidates<R: Read>(
+    reader: BufReader<R>,
+    rules: Option<&RuleSet>,
+    tx_candidates: Sender<String>,
+) -> Result<(), HashassinError> {
+    for line in rules::expand_lines(reader.lines(), rules) {
+        let candidate = line.map_err(|e| HashassinError::FileRead(e.to_string()))?;
+        if tx_candidates.send(candidate).is_err() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Hashes the candidates it receives until the wordlist is exhausted, every target is cracked or another
+/// thread failed.
+///
+/// Encoded hashes are verified one by one, skipping those already cracked since every verification pays
+/// their full cost.
+///
+/// # Errors
+///
+/// Returns the first error hashing a candidate, after telling the other threads to stop.
+fn crack_candidates(
+    targets: &HashFile,
+    digests_by_salt: &HashMap<&[u8], HashSet<&[u8]>>,
+    rx_candidates: Receiver<String>,
+    cracked: &Cracked,
+) -> Result<(), HashassinError> {
+    let algorithm = &targets.header.algorithm;
+    while !cracked.stop.load(Ordering::Relaxed) {
+        let Ok(candidate) = rx_candidates.recv() else {
+            break;
+        };
+        if algorithm.digest_len().is_none() {
+            for (salt, hashes) in digests_by_salt {
+                for hash in hashes {
+                    if !cracked.is_cracked(salt, hash)
+                        && algorithm.verify(candidate.as_bytes(), hash)
+                    {
+                        cracked.record(salt, hash, &candidate);
+                    }
+                }
+            }
+            continue;
+        }
+        for (salt, digests) in digests_by_salt {
+            let digest = hash_candidate(&targets.header, salt, &candidate).inspect_err(|_| {
+                cracked.stop.store(true, Ordering::Relaxed);
+            })?;
+            if digests.contains(digest.as_slice()) {
+                cracked.record(salt, &digest, &candidate);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{hash, hash_file::HashEntry, rules::Rule, salt::SaltPlacement, utils::TempFile};
+    use std::fs;
+
+    /// Cracks `file` with `wordlist` and returns the lines written.
+    fn crack(
+        name: &str,
+        file: &HashFile,
+        wordlist: &str,
+        rules: Option<RuleSet>,
+    ) -> Result<Vec<String>, HashassinError> {
+        let hashes = TempFile::new(&format!("{name}-hashes"), "")?;
+        file.write(hashes.path())?;
+        let wordlist = TempFile::new(&format!("{name}-wordlist"), wordlist)?;
+        let out = TempFile::new(&format!("{name}-out"), "")?;
+        crack_with_wordlist(hashes.path(), wordlist.path(), rules, 3, Some(out.path()))?;
+        let text =
+            fs::read_to_string(out.path()).map_err(|e| HashassinError::FileOpen(e.to_string()))?;
+        Ok(text.lines().map(str::to_string).collect())
+    }
+
+    #[test]
+    fn cracks_salted_labeled_files_in_file_order() -> Result<(), HashassinError> {
+        let md5 = hash::lookup("md5")?;
+        let entry =
+            |password: &str, salt: &[u8], label: &str| -> Result<HashEntry, HashassinError> {
+                Ok(HashEntry {
+                    password_len: Some(password.len() as u8),
+                    salt: salt.to_vec(),
+                    label: Some(label.to_string()),
+                    hash: SaltPlacement::Suffix.hash(md5.as_ref(), salt, password.as_bytes())?,
+                })
+            };
+        let file = HashFile {
+            header: HashFileHeader::new(md5.clone(), Some(SaltPlacement::Suffix), true),
+            entries: vec![
+                entry("letmein", b"\x02", "bob")?,
+                entry("unguessable", b"\x01", "carol")?,
+                entry("hunter2", b"\x01", "alice")?,
+            ],
+        };
+        let lines = crack(
+            "dictionary-salted",
+            &file,
+            "hunter2\nletmein\nqwerty\n",
+            None,
+        )?;
+        let hash = |i: usize| hex_encode(&file.entries[i].hash);
+        assert_eq!(
+            lines,
+            [
+                format!("{}\t02\tletmein\tbob", hash(0)),
+                format!("{}\t01\thunter2\talice", hash(2)),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn cracks_encoded_hashes_with_rules() -> Result<(), HashassinError> {
+        let bcrypt = hash::lookup("bcrypt:cost=4")?;
+        let encoded = bcrypt.hash(b"Secret1")?;
+        let file = HashFile {
+            header: HashFileHeader::new(bcrypt, None, false),
+            entries: vec![HashEntry::new(Some(7), encoded.clone())],
+        };
+        let rules = RuleSet::new(vec![Rule::parse("c $1")?]);
+        let lines = crack(
+            "dictionary-encoded",
+            &file,
+            "password\nsecret\n",
+            Some(rules),
+        )?;
+        assert_eq!(
+            lines,
+            [format!("{}\tSecret1", String::from_utf8_lossy(&encoded))]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn reports_when_nothing_is_cracked() -> Result<(), HashassinError> {
+        let md5 = hash::lookup("md5")?;
+        let file = HashFile {
+            header: HashFileHeader::new(md5.clone(), None, false),
+            entries: vec![HashEntry::new(Some(3), md5.hash(b"abc")?)],
+        };
+        assert!(crack("dictionary-none", &file, "abd\n", None).is_err());
+        Ok(())
+    }
+}
@@ -1,96 +1,257 @@
 #![deny(clippy::unwrap_used, clippy::expect_used)]
+use crate::hash_file::{HashEntry, HashFile};
+use crate::output::{OutputFormat, csv_row, write_json};
+use crate::table::HASH_FILE_VERSION;
 use hex::encode;
-use std::fs::File;
-use std::io::{self, Read};
-use std::path::Path;
-
-fn read_file_to_bytes<P: AsRef<Path>>(path: P) -> io::Result<Vec<u8>> {
-    let mut file = File::open(path)?;
-    let mut buffer = Vec::new();
-    file.read_to_end(&mut buffer)?;
-    Ok(buffer)
-}
+use serde_json::json;
+use std::io::{self, BufWriter, Write};
 
-fn get_hash_size(algorithm: &str) -> usize {
-    match algorithm.to_lowercase().as_str() {
-        "md5" => 16,
-        "sha256" => 32,
-        "sha3_512" => 64,
-        "scrypt" => 0,
-        _ => panic!("Unsupported algorithm: {}", algorithm),
+/// Formats the password length of an entry.
+fn password_len(entry: &HashEntry) -> String {
+    match entry.password_len {
+        Some(len) => len.to_string(),
+        None => "unknown".to_string(),
     }
 }
 
-pub fn dump_hashes(file_path: &str) -> io::Result<()> {
-    let data = read_file_to_bytes(file_path)?;
-
-    if data.len() < 4 {
-        return Err(io::Error::new(
-            io::ErrorKind::InvalidData,
-            "Invalid file format: file too small",
-        ));
+/// Formats the hash of an entry: hex, unless it is an encoded hash such as a PHC string.
+fn hash_text(file: &HashFile, entry: &HashEntry) -> String {
+    match file.header.algorithm.digest_len() {
+        Some(_) => encode(&entry.hash),
+        None => String::from_utf8_lossy(&entry.hash).into_owned(),
     }
+}
 
-    let version = data[0];
-    let algo_len = data[1] as usize;
+/// Prints the header of a version 1, 2 or 3 hash file followed by its entries.
+///
+/// As `text`, every entry is one line: the hash, followed by the salt in hex for salted files, the password
+/// length when the passwords do not share one, and the label for labeled files, all tab separated. As `json`,
+/// the header fields and an `entries` array; absent salts, labels and unknown lengths are `null`. As `csv`,
+/// one row per entry with the columns `version,algorithm,salt_placement,hash,salt,password_length,label`.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read or is malformed, or if writing to stdout fails, such as when
+/// the reader of a pipe exits early.
+pub fn dump_hashes(file_path: &str, format: OutputFormat) -> io::Result<()> {
+    let file = HashFile::read(file_path)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
 
-    if data.len() < 2 + algo_len + 1 {
-        return Err(io::Error::new(
-            io::ErrorKind::InvalidData,
-            "Invalid algorithm length",
-        ));
+    // A length shared by every entry is printed once, like the PASSWORD LENGTH of version 1 files
+    let shared_len = match file.entries.first() {
+        Some(first)
+            if file
+                .entries
+                .iter()
+                .all(|e| e.password_len == first.password_len) =>
+        {
+            first.password_len
+        }
+        _ => None,
+    };
+
+    let mut out = BufWriter::new(io::stdout().lock());
+    match format {
+        OutputFormat::Text => print_text(&mut out, &file, shared_len)?,
+        OutputFormat::Json => print_json(&mut out, &file, shared_len)?,
+        OutputFormat::Csv => print_csv(&mut out, &file)?,
     }
+    out.flush()
+}
 
-    let algorithm = String::from_utf8_lossy(&data[2..2 + algo_len]);
-    let password_length = data[2 + algo_len] as usize;
+fn print_text(out: &mut impl Write, file: &HashFile, shared_len: Option<u8>) -> io::Result<()> {
+    let header = &file.header;
+    writeln!(out, "VERSION: {}", header.version)?;
+    writeln!(out, "ALGORITHM: {}", header.algorithm.name())?;
+    match shared_len {
+        Some(len) => writeln!(out, "PASSWORD LENGTH: {}", len)?,
+        None => writeln!(out, "PASSWORD LENGTH: variable")?,
+    }
+    if let Some(placement) = header.placement {
+        writeln!(out, "SALT PLACEMENT: {}", placement)?;
+    }
+    // Older versions print their header as they always have
+    if header.version == HASH_FILE_VERSION {
+        writeln!(out, "ENTRIES: {}", file.entries.len())?;
+    }
 
-    println!("VERSION: {}", version);
-    println!("ALGORITHM: {}", algorithm);
-    println!("PASSWORD LENGTH: {}", password_length);
+    for entry in &file.entries {
+        let mut line = hash_text(file, entry);
+        if header.placement.is_some() {
+            line = format!("{}\t{}", line, encode(&entry.salt));
+        }
+        if shared_len.is_none() {
+            line = format!("{}\t{}", line, password_len(entry));
+        }
+        if header.labeled {
+            line = format!("{}\t{}", line, entry.label.as_deref().unwrap_or_default());
+        }
+        writeln!(out, "{}", line)?;
+    }
+    Ok(())
+}
 
-    let mut offset = 3 + algo_len;
+fn print_json(out: &mut impl Write, file: &HashFile, shared_len: Option<u8>) -> io::Result<()> {
+    let header = &file.header;
+    let fields = [
+        ("version", json!(header.version)),
+        ("algorithm", json!(header.algorithm.name())),
+        ("password_length", json!(shared_len)),
+        (
+            "salt_placement",
+            json!(header.placement.map(|placement| placement.to_string())),
+        ),
+        ("labeled", json!(header.labeled)),
+    ];
+    let entries = file.entries.iter().map(|entry| {
+        json!({
+            "hash": hash_text(file, entry),
+            "salt": header.placement.map(|_| encode(&entry.salt)),
+            "password_length": entry.password_len,
+            "label": entry.label,
+        })
+    });
+    write_json(out, &fields, "entries", entries)
+}
 
-    if algorithm.to_lowercase() == "scrypt" {
-        let hash_data = String::from_utf8_lossy(&data[offset..]);
+fn print_csv(out: &mut impl Write, file: &HashFile) -> io::Result<()> {
+    let header = &file.header;
+    writeln!(
+        out,
+        "{}",
+        csv_row(&[
+            "version",
+            "algorithm",
+            "salt_placement",
+            "hash",
+            "salt",
+            "password_length",
+            "label",
+        ])
+    )?;
+    let placement = header
+        .placement
+        .map(|placement| placement.to_string())
+        .unwrap_or_default();
+    for entry in &file.entries {
+        writeln!(
+            out,
+            "{}",
+            csv_row(&[
+                header.version.to_string(),
+                header.algorithm.name().to_string(),
+                placement.clone(),
+                hash_text(file, entry),
+                encode(&entry.salt),
+                entry
+                    .password_len
+                    .map(|len| len.to_string())
+                    .unwrap_or_default(),
+                entry.label.clone().unwrap_or_default(),
+            ])
+        )?;
+    }
+    Ok(())
+}
 
-        let hashes: Vec<&str> = hash_data.split("$scrypt").collect();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{HashassinError, hash, salt::SaltPlacement, table::HashFileHeader};
 
-        for hash in hashes.iter().filter(|h| !h.is_empty()) {
-            println!("$scrypt{}", hash);
-        }
-        return Ok(());
+    /// A salted, labeled file whose labels need quoting in CSV.
+    fn labeled_file() -> Result<HashFile, HashassinError> {
+        Ok(HashFile {
+            header: HashFileHeader::new(hash::lookup("md5")?, Some(SaltPlacement::Prefix), true),
+            entries: vec![
+                HashEntry {
+                    password_len: Some(4),
+                    salt: vec![0x01, 0x02],
+                    label: Some("doe, \"jd\"".to_string()),
+                    hash: vec![0xab; 16],
+                },
+                HashEntry {
+                    password_len: None,
+                    salt: vec![0x03],
+                    label: None,
+                    hash: vec![0xcd; 16],
+                },
+            ],
+        })
     }
 
-    let hash_size = get_hash_size(&algorithm);
-    while offset + hash_size <= data.len() {
-        let hash_data = &data[offset..offset + hash_size];
-        println!("{}", encode(hash_data));
-        offset += hash_size;
+    fn output(print: impl Fn(&mut Vec<u8>) -> io::Result<()>) -> Result<String, HashassinError> {
+        let mut out = Vec::new();
+        print(&mut out).map_err(|e| HashassinError::WriteError(e.to_string()))?;
+        String::from_utf8(out).map_err(|e| HashassinError::InvalidFormat(e.to_string()))
     }
 
-    if offset < data.len() {
-        let remaining = &data[offset..];
-        if !remaining.iter().all(|&b| b == 0) {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidData,
-                "Trailing data detected after hashes",
-            ));
-        }
+    #[test]
+    fn dumps_version_1_files_as_before() -> Result<(), HashassinError> {
+        let file = HashFile::parse(include_bytes!("../../sample_outputs/100-sha256.hashes"))?;
+        let text = output(|out| print_text(out, &file, Some(4)))?;
+        let expected = include_str!("../../sample_outputs/100-sha256-dump.txt");
+        // The sample was dumped before VERSION was followed by a colon
+        assert_eq!(text.lines().next(), Some("VERSION: 1"));
+        assert!(text.lines().skip(1).eq(expected.lines().skip(1)));
+        Ok(())
     }
 
-    Ok(())
-}
+    #[test]
+    fn dumps_salted_version_2_files() -> Result<(), HashassinError> {
+        // VERSION 2, md5, PASSWORD LENGTH 3, suffix salts, then one entry
+        let mut data = vec![2, 3];
+        data.extend_from_slice(b"md5");
+        data.extend_from_slice(&[3, SaltPlacement::Suffix.to_byte(), 1, 0x42]);
+        data.extend_from_slice(&[0x11; 16]);
+        let file = HashFile::parse(&data)?;
+        let text = output(|out| print_text(out, &file, Some(3)))?;
+        let hash = "11".repeat(16);
+        assert_eq!(
+            text,
+            format!(
+                "VERSION: 2\nALGORITHM: md5\nPASSWORD LENGTH: 3\nSALT PLACEMENT: suffix\n{hash}\t42\n"
+            )
+        );
+        Ok(())
+    }
 
-#[cfg(test)]
-fn main() {
-    let args: Vec<String> = std::env::args().collect();
-    if args.len() != 3 || args[1] != "--in-file" {
-        eprintln!("Usage: {} dump-hashes --in-file <path>", args[0]);
-        std::process::exit(1);
+    #[test]
+    fn csv_quotes_fields() -> Result<(), HashassinError> {
+        let file = labeled_file()?;
+        let csv = output(|out| print_csv(out, &file))?;
+        let hash = "ab".repeat(16);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(
+            lines[0],
+            "version,algorithm,salt_placement,hash,salt,password_length,label"
+        );
+        assert_eq!(
+            lines[1],
+            format!("3,md5,prefix,{hash},0102,4,\"doe, \"\"jd\"\"\"")
+        );
+        assert_eq!(lines[2], format!("3,md5,prefix,{},03,,", "cd".repeat(16)));
+        assert_eq!(lines.len(), 3);
+        Ok(())
     }
 
-    if let Err(e) = dump_hashes(&args[2]) {
-        eprintln!("Error: {}", e);
-        std::process::exit(1);
+    #[test]
+    fn json_is_one_parseable_object() -> Result<(), HashassinError> {
+        let file = labeled_file()?;
+        let text = output(|out| print_json(out, &file, None))?;
+        let value: serde_json::Value = serde_json::from_str(&text)
+            .map_err(|e| HashassinError::InvalidFormat(e.to_string()))?;
+        assert_eq!(value["version"], 3);
+        assert_eq!(value["algorithm"], "md5");
+        assert_eq!(value["password_length"], serde_json::Value::Null);
+        assert_eq!(value["salt_placement"], "prefix");
+        assert_eq!(value["entries"][0]["label"], "doe, \"jd\"");
+        assert_eq!(value["entries"][0]["salt"], "0102");
+        assert_eq!(
+            value["entries"][1]["password_length"],
+            serde_json::Value::Null
+        );
+        assert_eq!(value["entries"][1]["label"], serde_json::Value::Null);
+        Ok(())
     }
 }
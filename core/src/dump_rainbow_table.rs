@@ -1,14 +1,17 @@
 use crate::HashassinError;
-use std::fs::File;
-use std::io::{BufReader, Read};
+use crate::output::{OutputFormat, csv_row, write_json};
+use crate::table::{MappedRainbowTable, RAINBOW_TABLE_VERSION};
+use serde_json::json;
+use std::io::{self, BufWriter, Write};
 
 /// Dumps the contents of a rainbow table file.
 ///
-/// This function reads the given rainbow table file, validates its structure, and prints the metadata
+/// This function maps the given rainbow table file, validates its structure, and prints the metadata
 /// and chains contained within the file. It expects the file to adhere to a specific binary format
 /// that includes a magic word, version, algorithm name, password length, character set size, number of
-/// links, and an ASCII offset. After validating the header, it prints out the table's metadata followed
-/// by each password chain (start and end points of each chain).
+/// links, and an ASCII offset, read and validated by `RainbowTableHeader::read_from`. After validating the
+/// header, it prints out the table's metadata followed by each password chain (start and end points of
+/// each chain).
 ///
 /// The format of the rainbow table is expected to be as follows:
 /// - Magic word (`"rainbowtable"`, 12 bytes)
@@ -23,118 +26,108 @@ use std::io::{BufReader, Read};
 ///
 /// # Parameters
 /// - `in_file`: The file path to the rainbow table file. It must be a valid path to an existing file.
+/// - `format`: `text` for the metadata lines followed by one tab separated line per chain, `json` for one
+///   object holding the metadata and a `chains` array of `start`/`end` objects, or `csv` for one row per
+///   chain with the columns `version,algorithm,password_length,charset_size,num_links,ascii_offset,start,end`.
 ///
 /// # Returns
 /// - `Ok(())` if the rainbow table file is read and processed successfully.
 /// - `Err(HashassinError)` in case of any errors encountered during file reading, validation, or processing.
 ///   This could include:
 ///   - `InvalidInput` if the input file path is empty, or the file format is invalid.
-///   - `FileOpen` if the file cannot be opened.
-///   - `FileRead` if there is an error while reading from the file.
-///   - `InvalidInput` if there is invalid UTF-8 data or an invalid chain size.
+///   - `FileOpen` if the file cannot be opened or mapped.
+///   - `InvalidFormat` or `UnknownAlgorithm` if the header is malformed or the chains do not fill whole
+///     chain lengths; see `MappedRainbowTable::open`.
+///   - `WriteError` if writing to stdout fails, such as when the reader of a pipe exits early.
 ///
-pub fn dump_rainbow_table(in_file: &str) -> Result<(), HashassinError> {
+pub fn dump_rainbow_table(in_file: &str, format: OutputFormat) -> Result<(), HashassinError> {
     if in_file.is_empty() {
         return Err(HashassinError::InvalidInput(
             "Input file path cannot be empty".to_string(),
         ));
     }
 
-    let file = File::open(in_file).map_err(|e| HashassinError::FileOpen(e.to_string()))?;
-    let mut reader = BufReader::new(file);
-
-    let mut magic_word = vec![0u8; 12];
-    reader
-        .read_exact(&mut magic_word)
-        .map_err(|e| HashassinError::FileRead(e.to_string()))?;
-
-    if magic_word != b"rainbowtable" {
-        return Err(HashassinError::InvalidInput(
-            "Invalid file format: missing magic word".to_string(),
-        ));
+    let table = MappedRainbowTable::open(in_file)?;
+    let mut out = BufWriter::new(io::stdout().lock());
+    match format {
+        OutputFormat::Text => print_text(&mut out, &table),
+        OutputFormat::Json => print_json(&mut out, &table),
+        OutputFormat::Csv => print_csv(&mut out, &table),
     }
+    .and_then(|()| out.flush())
+    .map_err(|e| HashassinError::WriteError(e.to_string()))
+}
 
-    // Read version (1 byte)
-    let mut version = [0u8; 1];
-    reader
-        .read_exact(&mut version)
-        .map_err(|e| HashassinError::FileRead(e.to_string()))?;
-    let version = version[0];
-
-    // Read algorithm length (1 byte)
-    let mut algorithm_len = [0u8; 1];
-    reader
-        .read_exact(&mut algorithm_len)
-        .map_err(|e| HashassinError::FileRead(e.to_string()))?;
-    let algorithm_len = algorithm_len[0] as usize;
-
-    // Read algorithm (variable length)
-    let mut algorithm = vec![0u8; algorithm_len];
-    reader
-        .read_exact(&mut algorithm)
-        .map_err(|e| HashassinError::FileRead(e.to_string()))?;
-    let algorithm = String::from_utf8(algorithm)
-        .map_err(|_| HashassinError::InvalidInput("Invalid UTF-8 in algorithm name".to_string()))?;
-
-    // Read password length (1 byte)
-    let mut password_length = [0u8; 1];
-    reader
-        .read_exact(&mut password_length)
-        .map_err(|e| HashassinError::FileRead(e.to_string()))?;
-    let password_length = password_length[0];
-
-    // Read character set size (16 bytes)
-    let mut char_set_size_bytes = [0u8; 16];
-    reader
-        .read_exact(&mut char_set_size_bytes)
-        .map_err(|e| HashassinError::FileRead(e.to_string()))?;
-    let char_set_size = u128::from_be_bytes(char_set_size_bytes);
-
-    // Read number of links (16 bytes)
-    let mut num_links_bytes = [0u8; 16];
-    reader
-        .read_exact(&mut num_links_bytes)
-        .map_err(|e| HashassinError::FileRead(e.to_string()))?;
-    let num_links = u128::from_be_bytes(num_links_bytes);
-
-    // Read ASCII offset (1 byte)
-    let mut ascii_offset = [0u8; 1];
-    reader
-        .read_exact(&mut ascii_offset)
-        .map_err(|e| HashassinError::FileRead(e.to_string()))?;
-    let ascii_offset = ascii_offset[0];
-
-    // Print metadata
-    println!("Hashassin Rainbow Table");
-    println!("VERSION: {}", version);
-    println!("ALGORITHM: {}", algorithm);
-    println!("PASSWORD LENGTH: {}", password_length);
-    println!("CHAR SET SIZE: {}", char_set_size);
-    println!("NUM LINKS: {}", num_links);
-    println!("ASCII OFFSET: {}", ascii_offset);
-
-    // Read and print chains (rest of the file)
-    let mut buffer = Vec::new();
-    reader
-        .read_to_end(&mut buffer)
-        .map_err(|e| HashassinError::FileOpen(e.to_string()))?;
-
-    // Each chain is password_length * 2 bytes (start + end)
-    let chain_size = (password_length as usize) * 2;
-    for chunk in buffer.chunks(chain_size) {
-        if chunk.len() != chain_size {
-            return Err(HashassinError::InvalidInput(
-                "Invalid chain size in file".to_string(),
-            ));
-        }
-
-        let (start, end) = chunk.split_at(password_length as usize);
-        println!(
+fn print_text(out: &mut impl Write, table: &MappedRainbowTable) -> io::Result<()> {
+    let header = &table.header;
+    writeln!(out, "Hashassin Rainbow Table")?;
+    writeln!(out, "VERSION: {}", RAINBOW_TABLE_VERSION)?;
+    writeln!(out, "ALGORITHM: {}", header.algorithm.name())?;
+    writeln!(out, "PASSWORD LENGTH: {}", header.password_len)?;
+    writeln!(out, "CHAR SET SIZE: {}", header.charset_size)?;
+    writeln!(out, "NUM LINKS: {}", header.num_links)?;
+    writeln!(out, "ASCII OFFSET: {}", header.ascii_offset)?;
+    for chain in table.chains() {
+        writeln!(
+            out,
             "{}\t{}",
-            String::from_utf8_lossy(start),
-            String::from_utf8_lossy(end)
-        );
+            String::from_utf8_lossy(chain.start),
+            String::from_utf8_lossy(chain.end)
+        )?;
     }
+    Ok(())
+}
 
+fn print_json(out: &mut impl Write, table: &MappedRainbowTable) -> io::Result<()> {
+    let header = &table.header;
+    let fields = [
+        ("version", json!(RAINBOW_TABLE_VERSION)),
+        ("algorithm", json!(header.algorithm.name())),
+        ("password_length", json!(header.password_len)),
+        ("charset_size", json!(header.charset_size)),
+        ("num_links", json!(header.num_links)),
+        ("ascii_offset", json!(header.ascii_offset)),
+    ];
+    let chains = table.chains().map(|chain| {
+        json!({
+            "start": String::from_utf8_lossy(chain.start),
+            "end": String::from_utf8_lossy(chain.end),
+        })
+    });
+    write_json(out, &fields, "chains", chains)
+}
+
+fn print_csv(out: &mut impl Write, table: &MappedRainbowTable) -> io::Result<()> {
+    let header = &table.header;
+    writeln!(
+        out,
+        "{}",
+        csv_row(&[
+            "version",
+            "algorithm",
+            "password_length",
+            "charset_size",
+            "num_links",
+            "ascii_offset",
+            "start",
+            "end",
+        ])
+    )?;
+    for chain in table.chains() {
+        writeln!(
+            out,
+            "{}",
+            csv_row(&[
+                RAINBOW_TABLE_VERSION.to_string(),
+                header.algorithm.name().to_string(),
+                header.password_len.to_string(),
+                header.charset_size.to_string(),
+                header.num_links.to_string(),
+                header.ascii_offset.to_string(),
+                String::from_utf8_lossy(chain.start).into_owned(),
+                String::from_utf8_lossy(chain.end).into_owned(),
+            ])
+        )?;
+    }
     Ok(())
 }
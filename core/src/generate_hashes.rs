@@ -1,7 +1,11 @@
 // #![deny(clippy::unwrap_used, clippy::expect_used)]
 use std::{
-    fs::File,
-    io::{BufRead, BufReader, Write},
+    fs::{self, File},
+    io::{BufRead, BufReader, BufWriter},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
     thread::{self, JoinHandle},
 };
 
@@ -9,11 +13,17 @@ use crossbeam_channel::{Receiver, Sender};
 
 use crate::{
     HashassinError,
-    algorithms::{
-        generate_md5_hash, generate_scrypt_hash, generate_sha3_512_hash, generate_sha256_hash,
-    },
+    hash::HashAlgorithm,
+    hash_file::{self, HashEntry, HashFileWriter},
+    rules::{self, RuleSet},
+    salt::{self, Salting},
+    table::HashFileHeader,
 };
 
+/// Number of passwords or entries a thread may get ahead of the thread it feeds, so the input is streamed
+/// instead of read into memory.
+const CHANNEL_CAPACITY: usize = 4096;
+
 /// Generates hashes for passwords read from an input file and writes the results to an output file.
 /// The hashing process is parallelized using multiple threads, with the specified algorithm used
 /// for hashing each password.
@@ -23,21 +33,41 @@ use crate::{
 /// * `in_file` - The path to the input file containing passwords. Each password should be on a new line.
 /// * `out_file` - The path to the output file where the hashes will be written.
 /// * `num_threads` - The number of threads to be used for hashing the passwords.
-/// * `algorithm` - The hashing algorithm to be used. Supported values are "md5", "sha256", "sha3_512", and "scrypt".
+/// * `algorithm` - The hashing algorithm to be used, usually looked up with `hash::lookup`.
+/// * `rules` - Optional mangling rules. When set, every input line is expanded into one password per rule.
+/// * `salting` - Optional salting. When set, every password is hashed with its own random salt, which is stored
+///   in its entry.
 ///
 /// # Errors
 ///
-/// If the input file cannot be opened, or if the specified number of threads is less than 1, an error message is printed.
+/// If the input file cannot be opened, if the specified number of threads is less than 1, if the algorithm
+/// cannot be salted as requested, or if hashing a password or writing the output fails, an error is returned.
+/// Every thread stops at the first failure and the incomplete output file is removed.
 pub fn generate_hashes(
     in_file: String,
     out_file: String,
     num_threads: usize,
-    algorithm: String,
+    algorithm: Arc<dyn HashAlgorithm>,
+    rules: Option<RuleSet>,
+    salting: Option<Salting>,
 ) -> Result<(), HashassinError> {
     if num_threads < 1 {
         return Err(HashassinError::InvalidThreadCount);
         // return;
     }
+    if let Some(salting) = salting {
+        if salting.salt_len == 0 {
+            return Err(HashassinError::InvalidInput(
+                "Salt length must be greater than zero".to_string(),
+            ));
+        }
+        salting.placement.digest_len(algorithm.as_ref())?;
+    } else if algorithm.salted() && algorithm.digest_len().is_some() {
+        return Err(HashassinError::InvalidInput(format!(
+            "{} needs a salt, use --salt-len",
+            algorithm.name()
+        )));
+    }
 
     println!("Generating Hashes");
     println!("Reading File: {}", in_file);
@@ -51,77 +81,86 @@ pub fn generate_hashes(
         }
     };
 
-    let (tx_encrpyter, rx_encrpyter) = crossbeam_channel::unbounded();
-    let (tx_printer, rx_printer) = crossbeam_channel::unbounded();
+    let header = HashFileHeader::new(
+        algorithm.clone(),
+        salting.map(|salting| salting.placement),
+        false,
+    );
+
+    let (tx_encrpyter, rx_encrpyter) = crossbeam_channel::bounded(CHANNEL_CAPACITY);
+    let (tx_printer, rx_printer) = crossbeam_channel::bounded(CHANNEL_CAPACITY);
+    let failed = Arc::new(AtomicBool::new(false));
     let mut handles = generate_hash(
         num_threads as u32,
         rx_encrpyter,
-        tx_printer.clone(),
-        algorithm.clone(),
+        tx_printer,
+        algorithm,
+        salting,
+        failed.clone(),
     );
 
-    let reader = BufReader::new(file);
-
-    handles.push(create_print_to_file_thread(out_file, rx_printer));
+    handles.push(create_print_to_file_thread(
+        out_file.clone(),
+        header,
+        rx_printer,
+        failed,
+    ));
 
     // Spawn the thread to send passwords
     thread::spawn(move || {
-        send_passwords(reader, tx_encrpyter, tx_printer, &algorithm);
+        send_passwords(BufReader::new(file), rules.as_ref(), tx_encrpyter);
     });
 
-    // Wait for all threads to finish
+    // Wait for all threads to finish, reporting the first error. A failed printer makes every encryption
+    // thread fail to send, so the error that caused the others takes precedence.
+    let mut result = Ok(());
     for handle in handles {
-        match handle.join() {
-            Ok(_) => (),
-            Err(e) => {
-                return Err(HashassinError::ThreadJoin(format!(
-                    "Error Joining the threads method name: generate_hashas {e:?}"
-                )));
-            }
+        let thread_result = match handle.join() {
+            Ok(thread_result) => thread_result,
+            Err(e) => Err(HashassinError::ThreadJoin(format!(
+                "Error Joining the threads method name: generate_hashas {e:?}"
+            ))),
+        };
+        if let Err(e) = thread_result
+            && (result.is_ok() || !matches!(e, HashassinError::SendError(_)))
+        {
+            result = Err(e);
         }
     }
 
-    Ok(())
+    if result.is_err() {
+        // The file lacks the entries of the failed passwords; it may not exist if creating it failed
+        let _ = fs::remove_file(&out_file);
+    }
+    result
 }
 
-/// Sends passwords from the input file to encryption threads. It also sends metadata on the first iteration
-/// and manages the communication between threads.
+/// Reads the passwords of the input file, one per line, and sends them to the encryption threads as they
+/// are read.
 ///
 /// # Arguments
 ///
 /// * `reader` - A buffered reader that reads the passwords from the input file.
+/// * `rules` - Optional mangling rules applied to every line.
 /// * `tx_encrpyter` - The sender channel that sends passwords to the encryption threads.
-/// * `tx_printer` - The sender channel that sends metadata to the printer thread.
-/// * `algorithm` - The hashing algorithm to be used, which will be included in the metadata.
-fn send_passwords<T>(
-    reader: BufReader<T>,
-    tx_encrpyter: Sender<String>,
-    tx_printer: Sender<Vec<u8>>,
-    algorithm: &str,
-) where
+///
+/// Lines that cannot be read are reported and skipped. Sending stops once every encryption thread has
+/// stopped.
+fn send_passwords<T>(reader: BufReader<T>, rules: Option<&RuleSet>, tx_encrpyter: Sender<String>)
+where
     T: std::io::Read,
 {
-    let mut first_iteration = true;
-    for line in reader.lines() {
-        match line {
-            Ok(password) => {
-                if first_iteration {
-                    // Write metadata first (VERSION, ALGORITHM, PASSWORD LENGTH)
-                    let mut metadata = vec![];
-                    metadata.push(1); // VERSION: 1 byte (constant value 1)
-                    metadata.push(algorithm.len() as u8); // ALGORITHM LENGTH
-                    metadata.extend_from_slice(algorithm.to_lowercase().as_bytes()); // ALGORITHM string
-                    metadata.push(password.len() as u8); // PASSWORD LENGTH (assume first line represents password length)
-                    if let Err(e) = tx_printer.send(metadata) {
-                        eprintln!("Failed to send metadata: {}", e);
-                    }
-                    first_iteration = false;
-                }
-                if let Err(e) = tx_encrpyter.send(password) {
-                    eprintln!("Failed to send password: {}", e);
-                }
+    for line in rules::expand_lines(reader.lines(), rules) {
+        let password = match line {
+            Ok(password) => password,
+            Err(e) => {
+                eprintln!("Error reading line: {}", e);
+                continue;
             }
-            Err(e) => eprintln!("Error reading line: {}", e),
+        };
+        if let Err(e) = tx_encrpyter.send(password) {
+            eprintln!("Failed to send password: {}", e);
+            return;
         }
     }
 }
@@ -132,81 +171,100 @@ fn send_passwords<T>(
 ///
 /// * `num_threads` - The number of threads to be spawned for processing.
 /// * `rx_encrpyter` - The receiver channel to receive passwords from the main thread.
-/// * `tx_printer` - The sender channel to send hashed passwords to the printer thread.
+/// * `tx_printer` - The sender channel to send hash file entries to the printer thread.
 /// * `algorithm` - The hashing algorithm to be used.
+/// * `salting` - When set, every password is hashed with a fresh random salt stored in its entry.
+/// * `failed` - Set by the first thread that fails, which makes every other thread stop.
 ///
 /// # Returns
 ///
-/// A vector of thread handles that need to be joined after all threads have been spawned.
+/// A vector of thread handles that need to be joined after all threads have been spawned. A thread stops at
+/// the first password it fails to hash or send, or once another thread failed.
 fn generate_hash(
     num_threads: u32,
     rx_encrpyter: Receiver<String>,
-    tx_printer: Sender<Vec<u8>>,
-    algorithm: String,
-) -> Vec<JoinHandle<()>> {
+    tx_printer: Sender<HashEntry>,
+    algorithm: Arc<dyn HashAlgorithm>,
+    salting: Option<Salting>,
+    failed: Arc<AtomicBool>,
+) -> Vec<JoinHandle<Result<(), HashassinError>>> {
     (0..num_threads)
         .map(|_| {
             let tx_printer = tx_printer.clone();
             let rx_encrpyter = rx_encrpyter.clone();
             let algorithm = algorithm.clone();
+            let failed = failed.clone();
             thread::spawn(move || {
-                for _ in 0..num_threads {
-                    while let Ok(password) = rx_encrpyter.recv() {
-                        let hashed_password: Vec<u8> = match algorithm.as_str() {
-                            "md5" => generate_md5_hash(password),
-                            "sha256" => generate_sha256_hash(password),
-                            "sha3_512" => generate_sha3_512_hash(password),
-                            "scrypt" => generate_scrypt_hash(password),
-                            _ => {
-                                eprintln!("Unknown algorithm: {}", algorithm);
-                                return;
-                            }
-                        };
-
-                        let _ = tx_printer.send(hashed_password);
+                while !failed.load(Ordering::Relaxed) {
+                    let Ok(password) = rx_encrpyter.recv() else {
+                        break;
+                    };
+                    let password_len = hash_file::password_len(&password);
+                    let entry = match salting {
+                        Some(salting) => {
+                            let salt = salt::random_salt(salting.salt_len);
+                            salting
+                                .placement
+                                .hash(algorithm.as_ref(), &salt, password.as_bytes())
+                                .map(|digest| HashEntry {
+                                    password_len,
+                                    salt,
+                                    label: None,
+                                    hash: digest,
+                                })
+                        }
+                        None => algorithm
+                            .hash(password.as_bytes())
+                            .map(|digest| HashEntry::new(password_len, digest)),
+                    };
+                    let sent = entry.and_then(|entry| {
+                        tx_printer
+                            .send(entry)
+                            .map_err(|e| HashassinError::SendError(e.to_string()))
+                    });
+                    if sent.is_err() {
+                        failed.store(true, Ordering::Relaxed);
+                        return sent;
                     }
                 }
+                Ok(())
             })
         })
         .collect::<Vec<_>>()
 }
 
-/// Creates a thread that writes hashed passwords to a file.
+/// Creates a thread that writes the entries of a hash file.
 ///
 /// # Arguments
-/// * `out_file` - A `String` representing the path to the output file where hashed passwords will be written.
-/// * `rx_printer` - A `Receiver<Vec<u8>>` that receives hashed passwords to be written to the file.
+/// * `out_file` - A `String` representing the path to the output file where the hash file will be written.
+/// * `header` - The header of the hash file, written before the first entry.
+/// * `rx_printer` - A `Receiver<HashEntry>` that receives the entries to be written to the file.
+/// * `failed` - Set by an encryption thread that failed.
 ///
 /// # Returns
-/// A `thread::JoinHandle<()>` which allows you to wait for the thread to finish its execution.
+/// A `thread::JoinHandle` which allows you to wait for the thread to finish its execution and learn whether the
+/// file was written.
 ///
-/// # Example
-/// ```rust
-/// let out_file = String::from("hashed_passwords.txt");
-/// let (tx, rx) = mpsc::channel();
-/// let handle = create_print_to_file_thread(out_file, rx);
-/// tx.send(generate_sha256_hash(String::from("password1")))
-/// ```
 /// # Note
-/// This function spawns a new thread that listens for `Vec<u8>` values and writes them to the specified file.
-/// It uses a `Receiver` to receive the hashed passwords. Make sure to properly handle the file path and thread synchronization as needed.
+/// The entry count and checksum are written once every sender has been dropped, and only if no encryption
+/// thread failed, so an incomplete file never passes as a valid one.
 fn create_print_to_file_thread(
     out_file: String,
-    rx_printer: Receiver<Vec<u8>>, // Updated to Vec<u8>
-) -> thread::JoinHandle<()> {
-    let mut file = match File::create(&out_file) {
-        Ok(f) => f,
-        Err(e) => {
-            eprintln!("Failed to create file {}: {}", out_file, e);
-            return thread::spawn(|| ());
-        }
-    };
-    // let mut first_iteration = true;
+    header: HashFileHeader,
+    rx_printer: Receiver<HashEntry>,
+    failed: Arc<AtomicBool>,
+) -> thread::JoinHandle<Result<(), HashassinError>> {
     thread::spawn(move || {
-        while let Ok(hashed_password) = rx_printer.recv() {
-            if let Err(e) = file.write_all(&hashed_password) {
-                eprintln!("Failed to write to file: {}", e);
-            }
+        let file = File::create(&out_file).map_err(|e| {
+            HashassinError::CreateFile(format!("Failed to create file {out_file}: {e}"))
+        })?;
+        let mut writer = HashFileWriter::new(BufWriter::new(file), header)?;
+        while let Ok(entry) = rx_printer.recv() {
+            writer.write_entry(&entry)?;
+        }
+        if failed.load(Ordering::Relaxed) {
+            return Ok(());
         }
+        writer.finish().map(drop)
     })
 }
@@ -2,103 +2,365 @@
 use std::{
     fs::File,
     io::Write,
+    sync::Arc,
     thread::{self, JoinHandle},
 };
 
 use crossbeam_channel::{Receiver, Sender};
-use rand::Rng;
+use ethereum_types::U512;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
 
-use crate::HashassinError;
+use crate::{
+    HashassinError, algorithms,
+    charset::Charset,
+    lengths::LengthDistribution,
+    markov::MarkovModel,
+    mask::Mask,
+    passphrase::Passphrase,
+    policy::{self, Policy},
+};
+
+/// Number of passwords a generation thread may get ahead of the printer.
+///
+/// The printer drains the threads in order, so without a bound every thread but the one being drained would
+/// keep its whole output in memory.
+const PRINTER_CHANNEL_CAPACITY: usize = 4096;
+
+/// Handle of a generation or printer thread, which reports why it stopped early.
+type WorkerHandle = JoinHandle<Result<(), HashassinError>>;
 
-/// Generates a specified number of random passwords with a given character length, distributed across multiple threads,
+/// Describes how every random password of `generate_passwords` is built.
+#[derive(Debug, Clone)]
+pub enum PasswordSource {
+    /// Every password follows the same mask, so all of them have the same length.
+    Mask(Mask),
+    /// The length of every password is drawn from a distribution and every character from a charset.
+    Lengths {
+        lengths: LengthDistribution,
+        charset: Charset,
+    },
+    /// Passwords are sampled from a Markov model trained on a password corpus.
+    Markov(Arc<MarkovModel>),
+    /// Diceware-style passphrases of words drawn from a wordlist.
+    Passphrase(Arc<Passphrase>),
+}
+
+impl PasswordSource {
+    /// Draws one password.
+    ///
+    /// # Arguments
+    ///
+    /// * `rng` - The random number generator to draw from.
+    fn sample<R: Rng>(&self, rng: &mut R) -> String {
+        match self {
+            PasswordSource::Mask(mask) => generate_random_string(mask, rng),
+            PasswordSource::Lengths { lengths, charset } => {
+                let length = lengths.sample(rng);
+                let charset = charset.as_bytes();
+                (0..length)
+                    .map(|_| charset[rng.random_range(0..charset.len())] as char)
+                    .collect()
+            }
+            PasswordSource::Markov(model) => model.sample(rng),
+            PasswordSource::Passphrase(passphrase) => passphrase.sample(rng),
+        }
+    }
+}
+
+/// Generates a specified number of random passwords, distributed across multiple threads,
 /// and writes them to either standard output or an output file.
 ///
 /// # Arguments
 ///
-/// * `chars` - The length of each generated password (in characters).
+/// * `source` - How every password is built. Use `PasswordSource::Mask` with `Mask::uniform` for fixed-length
+///   passwords drawn from a single charset.
 /// * `out_file` - The path to the output file where the passwords will be written. If set to "std", passwords are printed to standard output.
 /// * `threads` - The number of threads to use for password generation.
 /// * `num` - The total number of passwords to generate.
+/// * `seed` - Optional seed. When set, every thread draws from its own deterministic stream, so the same seed,
+///   thread count and `num` always produce the same output.
+/// * `policy` - Requirements every password must meet. Use `Policy::default()` to accept every password.
 ///
 /// # Errors
 ///
 /// If the number of threads is less than 1, an error message is printed and the function returns without generating any passwords.
+/// Also returns an error if the source can never satisfy the policy.
 pub fn generate_passwords(
-    chars: u8,
+    source: PasswordSource,
     out_file: String,
     threads: usize,
     num: usize,
+    seed: Option<u64>,
+    policy: Policy,
 ) -> Result<(), HashassinError> {
     if threads < 1 {
         return Err(HashassinError::InvalidThreadCount);
         // std::process::exit(1);
         // return;
     }
+    let source = policy.restrict(source)?;
 
-    let (tx_printer, rx_printer) = crossbeam_channel::unbounded();
-    let mut num_per_threads = num;
-    let mut new_thread_count = threads;
-    if threads <= num {
-        num_per_threads = num / threads;
-    } else {
-        new_thread_count = num
+    let new_thread_count = threads.min(num);
+
+    // One channel per thread so the printer can drain them in thread order,
+    // keeping the output independent of thread scheduling.
+    let (tx_printers, rx_printers): (Vec<_>, Vec<_>) = (0..new_thread_count)
+        .map(|_| crossbeam_channel::bounded(PRINTER_CHANNEL_CAPACITY))
+        .unzip();
+
+    let handles: Vec<WorkerHandle> =
+        create_gen_passwords_threads(&source, &policy, tx_printers, num, seed)?;
+
+    print_and_join(handles, out_file, rx_printers)
+}
+
+/// Enumerates every password matching a mask in lexicographic order, distributed across multiple threads,
+/// and writes them to either standard output or an output file.
+///
+/// The keyspace is the product of the charset sizes of all mask positions. Passwords are numbered from 0 in
+/// lexicographic order, and each thread enumerates its own contiguous range of indices, so the output is
+/// always sorted regardless of the thread count.
+///
+/// # Arguments
+///
+/// * `mask` - The charset of every password position. Use `Mask::uniform` to enumerate every password of a
+///   given length and charset.
+/// * `out_file` - The path to the output file where the passwords will be written. If set to "std", passwords are printed to standard output.
+/// * `threads` - The number of threads to use for password generation.
+/// * `skip` - The number of passwords at the start of the keyspace to skip.
+/// * `limit` - The maximum number of passwords to emit. If `None`, the rest of the keyspace is emitted.
+///
+/// # Errors
+///
+/// Returns an error if the number of threads is less than 1, if `skip` is past the end of the keyspace, or if
+/// the selected slice of the keyspace holds more than `u128::MAX` passwords.
+pub fn enumerate_passwords(
+    mask: Mask,
+    out_file: String,
+    threads: usize,
+    skip: u128,
+    limit: Option<u128>,
+) -> Result<(), HashassinError> {
+    if threads < 1 {
+        return Err(HashassinError::InvalidThreadCount);
+    }
+
+    let radixes = mask.radixes();
+    let keyspace = algorithms::keyspace_size(&radixes).ok_or_else(|| {
+        HashassinError::InvalidInput("Keyspace is too large to enumerate".to_string())
+    })?;
+    let skip_index = U512::from(skip);
+    if skip_index > keyspace {
+        return Err(HashassinError::InvalidInput(format!(
+            "Cannot skip {skip} passwords, the keyspace only holds {keyspace}"
+        )));
     }
 
-    let mut handles: Vec<JoinHandle<()>> =
-        create_gen_passwords_threads(chars, new_thread_count, tx_printer, num_per_threads)?;
+    let mut remaining = keyspace - skip_index;
+    if let Some(limit) = limit {
+        remaining = remaining.min(U512::from(limit));
+    }
+    if remaining > U512::from(u128::MAX) {
+        return Err(HashassinError::InvalidInput(format!(
+            "Keyspace holds {remaining} passwords, use --limit to select a slice of it"
+        )));
+    }
+    let total = remaining.low_u128();
+
+    let new_thread_count = (threads as u128).min(total) as usize;
+    let (tx_printers, rx_printers): (Vec<_>, Vec<_>) = (0..new_thread_count)
+        .map(|_| crossbeam_channel::bounded(PRINTER_CHANNEL_CAPACITY))
+        .unzip();
+
+    let mut handles = Vec::new();
+    let mut start = skip_index;
+    for (thread_id, tx_printer) in tx_printers.into_iter().enumerate() {
+        // The first `total % new_thread_count` threads pick up the remainder
+        let count = total / new_thread_count as u128
+            + u128::from((thread_id as u128) < total % new_thread_count as u128);
+        let digits = algorithms::decode_index(start, &radixes);
+        handles.push(create_enumerate_thread(
+            thread_id as u32,
+            mask.clone(),
+            digits,
+            count,
+            tx_printer,
+        ));
+        start += U512::from(count);
+    }
+
+    print_and_join(handles, out_file, rx_printers)
+}
+
+/// Enumerates the passwords of a Markov model in decreasing order of probability and writes them to either
+/// standard output or an output file.
+///
+/// The enumeration is a single best-first search, so it runs on one thread.
+///
+/// # Arguments
+///
+/// * `model` - The trained Markov model.
+/// * `out_file` - The path to the output file where the passwords will be written. If set to "std", passwords are printed to standard output.
+/// * `skip` - The number of most probable passwords to skip.
+/// * `limit` - The maximum number of passwords to emit. If `None`, every password reachable through transitions
+///   seen in the corpus is emitted.
+///
+/// # Errors
+///
+/// Returns an error if the output file cannot be created or the enumeration thread panicked.
+pub fn enumerate_markov_passwords(
+    model: MarkovModel,
+    out_file: String,
+    skip: u128,
+    limit: Option<u128>,
+) -> Result<(), HashassinError> {
+    let (tx_printer, rx_printer) = crossbeam_channel::bounded(PRINTER_CHANNEL_CAPACITY);
+    let handle = thread::spawn(move || {
+        let candidates = model
+            .enumerate()
+            .skip(usize::try_from(skip).unwrap_or(usize::MAX));
+        let limit = limit.map_or(usize::MAX, |l| usize::try_from(l).unwrap_or(usize::MAX));
+        for password in candidates.take(limit) {
+            tx_printer
+                .send(password)
+                .map_err(|e| HashassinError::SendError(e.to_string()))?;
+        }
+        Ok(())
+    });
+
+    print_and_join(vec![handle], out_file, vec![rx_printer])
+}
 
+/// Starts the printer thread for the generated passwords and waits for every thread to finish.
+///
+/// # Arguments
+///
+/// * `handles` - The handles of the password generation threads.
+/// * `out_file` - The path to the output file where the passwords will be written. If set to "std", passwords are printed to standard output.
+/// * `rx_printers` - The receiver channels of the generation threads, drained in order.
+///
+/// # Errors
+///
+/// Returns an error if the output file cannot be created, if a thread panicked, or the first error a thread
+/// returned. A printer error takes precedence, since it makes every generation thread fail to send.
+fn print_and_join(
+    mut handles: Vec<WorkerHandle>,
+    out_file: String,
+    rx_printers: Vec<Receiver<String>>,
+) -> Result<(), HashassinError> {
     if out_file == "std" {
-        match create_print_thread(rx_printer.clone()) {
+        match create_print_thread(rx_printers) {
             Ok(handle) => handles.push(handle),
             Err(e) => return Err(e), // If thread creation failed, return the error
         }
     } else {
-        match create_print_to_file_thread(out_file, rx_printer.clone()) {
+        match create_print_to_file_thread(out_file, rx_printers) {
             Ok(handle) => handles.push(handle),
             Err(e) => return Err(e), // If thread creation failed, return the error
         }
     }
 
+    // The printer is the last handle
+    let mut result = Ok(());
     for handle in handles {
-        match handle.join() {
-            Ok(_) => (),
-            Err(e) => {
-                return Err(HashassinError::ThreadJoin(format!(
-                    "Error Joining the threads method name: generate_hashas {e:?}"
-                )));
-            }
+        let thread_result = match handle.join() {
+            Ok(thread_result) => thread_result,
+            Err(e) => Err(HashassinError::ThreadJoin(format!(
+                "Error Joining the threads method name: generate_hashas {e:?}"
+            ))),
+        };
+        if let Err(e) = thread_result
+            && (result.is_ok() || !matches!(e, HashassinError::SendError(_)))
+        {
+            result = Err(e);
         }
     }
 
-    Ok(())
+    result
+}
+
+/// Creates a thread that enumerates a contiguous range of the keyspace of a mask.
+///
+/// The thread treats the password as an odometer: it starts at `digits` and increments the last position,
+/// carrying into the previous one whenever a position wraps around its charset.
+///
+/// # Arguments
+///
+/// * `thread_id` - The ID of the thread (for logging purposes).
+/// * `mask` - The charset of every password position.
+/// * `digits` - The charset index of every position of the first password to emit.
+/// * `count` - The number of passwords this thread will emit.
+/// * `tx_printer` - The sender channel used to pass generated passwords to the printer thread.
+///
+/// # Returns
+///
+/// A thread handle for the enumeration thread.
+fn create_enumerate_thread(
+    thread_id: u32,
+    mask: Mask,
+    mut digits: Vec<usize>,
+    count: u128,
+    tx_printer: Sender<String>,
+) -> WorkerHandle {
+    thread::spawn(move || {
+        let positions = mask.positions();
+        for _ in 0..count {
+            let password: String = digits
+                .iter()
+                .zip(positions)
+                .map(|(&digit, charset)| charset.as_bytes()[digit] as char)
+                .collect();
+            tx_printer
+                .send(password)
+                .map_err(|e| HashassinError::SendError(format!("Thread_id {thread_id}: {e}")))?;
+
+            for (digit, charset) in digits.iter_mut().zip(positions).rev() {
+                *digit += 1;
+                if *digit < charset.len() {
+                    break;
+                }
+                *digit = 0;
+            }
+        }
+        Ok(())
+    })
 }
 
 /// Creates and spawns multiple threads to generate passwords concurrently.
 ///
 /// # Arguments
 ///
-/// * `chars` - The length of each generated password (in characters).
-/// * `new_thread_count` - The number of threads to spawn for password generation.
-/// * `tx_printer` - The sender channel used to pass generated passwords to the printer threads.
-/// * `num_per_thread` - The number of passwords to generate per thread.
+/// * `source` - How every password is built.
+/// * `policy` - Requirements every password must meet.
+/// * `tx_printers` - One sender channel per thread used to pass generated passwords to the printer thread.
+/// * `num` - The total number of passwords to generate, split as evenly as possible across the threads.
+/// * `seed` - Optional seed used to derive a deterministic stream for each thread.
 ///
 /// # Returns
 ///
 /// A vector of thread handles that can be joined to ensure all threads have completed.
 fn create_gen_passwords_threads(
-    chars: u8,
-    new_thread_count: usize,
-    tx_printer: Sender<String>,
-    num_per_thread: usize,
-) -> Result<Vec<JoinHandle<()>>, HashassinError> {
+    source: &PasswordSource,
+    policy: &Policy,
+    tx_printers: Vec<Sender<String>>,
+    num: usize,
+    seed: Option<u64>,
+) -> Result<Vec<WorkerHandle>, HashassinError> {
     let mut handles = Vec::new();
+    let new_thread_count = tx_printers.len();
 
-    for thread_id in 0..new_thread_count {
+    for (thread_id, tx_printer) in tx_printers.into_iter().enumerate() {
+        // The first `num % new_thread_count` threads pick up the remainder
+        let num_per_thread =
+            num / new_thread_count + usize::from(thread_id < num % new_thread_count);
         match create_gen_password_thread(
             thread_id as u32,
-            chars,
-            tx_printer.clone(),
+            source.clone(),
+            policy.clone(),
+            tx_printer,
             num_per_thread,
+            create_rng(seed, thread_id as u32),
         ) {
             Ok(handle) => handles.push(handle),
             Err(e) => {
@@ -119,61 +381,89 @@ fn create_gen_passwords_threads(
 /// # Arguments
 ///
 /// * `thread_id` - The ID of the thread (for logging purposes).
-/// * `chars` - The length of each generated password (in characters).
+/// * `source` - How every password is built.
+/// * `policy` - Requirements every password must meet; non-compliant candidates are redrawn.
 /// * `tx_printer` - The sender channel used to pass generated passwords to the printer thread.
 /// * `num_per_thread` - The number of passwords this thread will generate.
+/// * `rng` - The random number generator owned by this thread.
 ///
 /// # Returns
 ///
-/// A thread handle for the password generation thread.
+/// A thread handle for the password generation thread. The thread fails if no candidate satisfies the policy
+/// within `MAX_ATTEMPTS` draws.
 fn create_gen_password_thread(
     thread_id: u32,
-    chars: u8,
+    source: PasswordSource,
+    policy: Policy,
     tx_printer: Sender<String>,
     num_per_thread: usize,
-) -> Result<JoinHandle<()>, HashassinError> {
+    mut rng: ChaCha20Rng,
+) -> Result<WorkerHandle, HashassinError> {
     // Spawn the thread
     let handle = thread::spawn(move || {
         for _ in 0..num_per_thread {
-            let random_string = generate_random_string(chars);
+            // Redrawing until the policy holds keeps the distribution proportional among compliant passwords
+            let Some(random_string) = (0..policy::MAX_ATTEMPTS)
+                .map(|_| source.sample(&mut rng))
+                .find(|candidate| policy.allows(candidate))
+            else {
+                return Err(HashassinError::InvalidInput(format!(
+                    "Thread_id {}: no password satisfying the policy after {} attempts",
+                    thread_id,
+                    policy::MAX_ATTEMPTS
+                )));
+            };
             // println!("Thread_id {} Random String: {}", thread_id, random_string);
 
             // Try sending the message to the printer thread
-            match tx_printer.send(random_string.to_string()) {
-                Ok(_) => (),
-                Err(e) => {
-                    eprintln!(
-                        "Thread_id {}: Error sending message to printer: {}",
-                        thread_id,
-                        HashassinError::SendError(e.to_string())
-                    );
-                    return; // Return early if sending fails (you can choose to handle this differently)
-                }
-            }
+            tx_printer
+                .send(random_string.to_string())
+                .map_err(|e| HashassinError::SendError(format!("Thread_id {thread_id}: {e}")))?;
         }
+        Ok(())
     });
 
     // If the thread was spawned successfully, return the handle; otherwise, return an error
     Ok(handle)
 }
 
-/// Generates a random string of printable ASCII characters of a given length.
+/// Creates the random number generator for a password generation thread.
+///
+/// With a seed, the ChaCha20 key is derived from the seed and the thread id selects the stream, so every
+/// thread gets an independent but reproducible sequence. Without one, the generator is seeded from the
+/// thread-local OS-seeded generator.
 ///
 /// # Arguments
 ///
-/// * `length` - The length of the string to generate.
+/// * `seed` - Optional user supplied seed.
+/// * `thread_id` - The ID of the thread the generator belongs to.
+fn create_rng(seed: Option<u64>, thread_id: u32) -> ChaCha20Rng {
+    match seed {
+        Some(seed) => {
+            let mut rng = ChaCha20Rng::seed_from_u64(seed);
+            rng.set_stream(thread_id as u64);
+            rng
+        }
+        None => ChaCha20Rng::from_rng(&mut rand::rng()),
+    }
+}
+
+/// Generates a random string following a mask, drawing each position uniformly from its charset.
+///
+/// # Arguments
+///
+/// * `mask` - The charset of every position of the string.
+/// * `rng` - The random number generator to draw from.
 ///
 /// # Returns
 ///
-/// A random string of printable ASCII characters.
-fn generate_random_string(length: u8) -> String {
-    let mut rng = rand::rng();
-    // This range covers all uppercase and lowercase letters, digits, punctuation marks, and spaces, which are all valid printable ASCII characters.
-
-    let mut random_string = String::new();
+/// A random string matching `mask`.
+fn generate_random_string<R: Rng>(mask: &Mask, rng: &mut R) -> String {
+    let mut random_string = String::with_capacity(mask.len());
 
-    for _ in 0..length {
-        let random_char = rng.random_range(32..=126) as u8 as char; // Generate a random ASCII character
+    for charset in mask.positions() {
+        let charset = charset.as_bytes();
+        let random_char = charset[rng.random_range(0..charset.len())] as char; // Pick a random character from the charset
         random_string.push(random_char); // Add the random character to the string
     }
     random_string
@@ -183,12 +473,15 @@ fn generate_random_string(length: u8) -> String {
 ///
 /// # Arguments
 ///
-/// * `rx_printer` - The receiver channel to receive the generated passwords.
-fn create_print_thread(rx_printer: Receiver<String>) -> Result<JoinHandle<()>, HashassinError> {
+/// * `rx_printers` - The receiver channels to receive the generated passwords, drained in order.
+fn create_print_thread(rx_printers: Vec<Receiver<String>>) -> Result<WorkerHandle, HashassinError> {
     Ok(thread::spawn(move || {
-        while let Ok(msg) = rx_printer.recv() {
-            println!("{msg}");
+        for rx_printer in rx_printers {
+            while let Ok(msg) = rx_printer.recv() {
+                println!("{msg}");
+            }
         }
+        Ok(())
     }))
 }
 
@@ -197,11 +490,11 @@ fn create_print_thread(rx_printer: Receiver<String>) -> Result<JoinHandle<()>, H
 /// # Arguments
 ///
 /// * `out_file` - The path to the output file where the passwords will be written.
-/// * `rx_printer` - The receiver channel to receive the generated passwords.
+/// * `rx_printers` - The receiver channels to receive the generated passwords, drained in order.
 fn create_print_to_file_thread(
     out_file: String,
-    rx_printer: Receiver<String>,
-) -> Result<JoinHandle<()>, HashassinError> {
+    rx_printers: Vec<Receiver<String>>,
+) -> Result<WorkerHandle, HashassinError> {
     let mut file = match File::create(out_file) {
         Ok(f) => f,
         Err(e) => {
@@ -213,10 +506,69 @@ fn create_print_to_file_thread(
 
     // let writer = BufWriter::new(file);
     Ok(thread::spawn(move || {
-        // let mut counter = 0;
-        while let Ok(msg) = rx_printer.recv() {
-            // counter += 1;
-            writeln!(&mut file, "{msg}").unwrap();
+        for rx_printer in rx_printers {
+            while let Ok(msg) = rx_printer.recv() {
+                writeln!(&mut file, "{msg}").map_err(|e| {
+                    HashassinError::WriteError(format!("Failed to write to file: {e}"))
+                })?;
+            }
         }
+        Ok(())
     }))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::TempFile;
+
+    /// Lines of the file `generate` writes to the temporary file `name`.
+    fn output(
+        name: &str,
+        generate: impl FnOnce(String) -> Result<(), HashassinError>,
+    ) -> Result<Vec<String>, HashassinError> {
+        let file = TempFile::new(name, "")?;
+        generate(file.path().to_string())?;
+        let text = std::fs::read_to_string(file.path())
+            .map_err(|e| HashassinError::FileOpen(e.to_string()))?;
+        Ok(text.lines().map(str::to_string).collect())
+    }
+
+    fn seeded(name: &str, threads: usize, seed: u64) -> Result<Vec<String>, HashassinError> {
+        let source = PasswordSource::Mask(Mask::uniform(8, &Charset::default()));
+        output(name, |out| {
+            generate_passwords(source, out, threads, 10, Some(seed), Policy::default())
+        })
+    }
+
+    #[test]
+    fn seeded_generation_is_reproducible() -> Result<(), HashassinError> {
+        let first = seeded("seeded-first", 3, 42)?;
+        assert_eq!(first.len(), 10);
+        assert!(first.iter().all(|password| password.len() == 8));
+        assert_eq!(first, seeded("seeded-again", 3, 42)?);
+        assert_ne!(first, seeded("seeded-other", 3, 43)?);
+        Ok(())
+    }
+
+    #[test]
+    fn enumerates_the_keyspace_in_order() -> Result<(), HashassinError> {
+        let mask = Mask::uniform(2, &Charset::parse("abc")?);
+        let all = output("enumerate-all", |out| {
+            enumerate_passwords(mask.clone(), out, 4, 0, None)
+        })?;
+        assert_eq!(all, ["aa", "ab", "ac", "ba", "bb", "bc", "ca", "cb", "cc"]);
+
+        let slice = output("enumerate-slice", |out| {
+            enumerate_passwords(mask.clone(), out, 2, 2, Some(3))
+        })?;
+        assert_eq!(slice, ["ac", "ba", "bb"]);
+
+        let tail = output("enumerate-tail", |out| {
+            enumerate_passwords(mask.clone(), out, 2, 8, Some(5))
+        })?;
+        assert_eq!(tail, ["cc"]);
+        assert!(enumerate_passwords(mask, "std".to_string(), 1, 10, None).is_err());
+        Ok(())
+    }
+}
@@ -1,6 +1,9 @@
 use crate::{
     HashassinError, algorithms,
+    hash::HashAlgorithm,
     radix_type::Radix,
+    rules::{self, RuleSet},
+    table::RainbowTableHeader,
     utils::{self, create_print_to_file_thread},
 };
 use crossbeam_channel::{Receiver, Sender};
@@ -8,6 +11,7 @@ use log::{error, info};
 use std::{
     fs::File,
     io::{BufRead, BufReader},
+    sync::Arc,
     thread::{self, JoinHandle},
 }; // Logging
 
@@ -24,8 +28,9 @@ use std::{
 /// - `num_links`: The number of links (hash-reduction pairs) to generate per chain.
 /// - `num_threads`: The number of threads to use for parallel processing.
 /// - `out_file`: The path to the output file where the rainbow table will be written.
-/// - `algorithm`: The name of the hash algorithm to use (e.g., "sha256").
+/// - `algorithm`: The hash algorithm to use. It must be unsalted with a fixed digest length.
 /// - `in_file`: The path to the input file containing plaintext values to seed the chains.
+/// - `rules`: Optional mangling rules. When set, every input line is expanded into one chain start per rule.
 ///
 /// # Returns
 ///
@@ -39,12 +44,19 @@ pub fn generate_rainbow_table(
     num_links: usize,
     num_threads: usize,
     out_file: String,
-    algorithm: String,
+    algorithm: Arc<dyn HashAlgorithm>,
     in_file: String,
+    rules: Option<RuleSet>,
 ) -> Result<(), HashassinError> {
     info!("Starting rainbow table generation...");
 
-    match validate_inputs(num_links, num_threads, &out_file, &algorithm, &in_file) {
+    match validate_inputs(
+        num_links,
+        num_threads,
+        &out_file,
+        algorithm.as_ref(),
+        &in_file,
+    ) {
         Ok(_) => {
             // Proceed with the generation of the rainbow table
             let file = utils::open_file(&in_file)?;
@@ -63,7 +75,14 @@ pub fn generate_rainbow_table(
 
             handles.push(create_print_to_file_thread(out_file, rx_printer)?);
 
-            read_passwords(num_links, reader, tx_password, tx_printer, &algorithm);
+            read_passwords(
+                num_links,
+                reader,
+                rules.as_ref(),
+                tx_password,
+                tx_printer,
+                &algorithm,
+            );
 
             for handle in handles {
                 match handle.join() {
@@ -98,7 +117,7 @@ pub fn generate_rainbow_table(
 /// - `num_links`: The number of hash-reduction links to be generated. Must be greater than zero.
 /// - `threads`: The number of threads to use. Must be greater than zero.
 /// - `out_file`: Path to the file where the rainbow table will be written. Must be a valid writable path.
-/// - `algorithm`: The name of the hash algorithm to use. Must be one of the supported algorithms.
+/// - `algorithm`: The hash algorithm to use. Must be unsalted with a fixed digest length.
 /// - `in_file`: Path to the input file containing plaintexts. Must exist and be readable.
 ///
 /// # Returns
@@ -108,16 +127,16 @@ pub fn generate_rainbow_table(
 /// # Errors
 ///
 /// Returns a `HashassinError` if:
-/// - `num_links` or `threads` are zero.
+/// - `num_links` or `threads` are zero, or `num_links` does not fit the table header.
 /// - `out_file` cannot be created or written to.
 /// - `in_file` does not exist or is not readable.
-/// - `algorithm` is not among the supported list.
+/// - `algorithm` is salted or has variable-length output, so chains could never be replayed.
 ///
 fn validate_inputs(
     num_links: usize,
     threads: usize,
     out_file: &str,
-    algorithm: &str,
+    algorithm: &dyn HashAlgorithm,
     in_file: &str,
 ) -> Result<(), HashassinError> {
     if num_links == 0 {
@@ -125,6 +144,12 @@ fn validate_inputs(
             "Number of links must be greater than 0".to_string(),
         ));
     }
+    if u32::try_from(num_links).is_err() {
+        return Err(HashassinError::InvalidInput(format!(
+            "Number of links must be at most {}",
+            u32::MAX
+        )));
+    }
     if threads == 0 {
         return Err(HashassinError::InvalidThreadCount);
     }
@@ -133,11 +158,7 @@ fn validate_inputs(
             "Output file path cannot be empty".to_string(),
         ));
     }
-    if algorithm.is_empty() {
-        return Err(HashassinError::InvalidInput(
-            "Algorithm cannot be empty".to_string(),
-        ));
-    }
+    algorithm.fixed_digest_len()?;
     if in_file.is_empty() {
         return Err(HashassinError::InvalidInput(
             "Input file path cannot be empty".to_string(),
@@ -157,51 +178,54 @@ fn validate_inputs(
 ///
 /// - `num_links`: The number of hash-reduction links to generate for each password.
 /// - `reader`: A buffered reader over the input file containing plaintext passwords.
+/// - `rules`: Optional mangling rules applied to every line.
 /// - `tx_password`: A sending channel used to transmit original plaintext passwords for further processing.
 /// - `tx_printer`: A sending channel used to transmit the final byte representation of processed chains for output.
 /// - `algorithm`: The hash algorithm to use (e.g., "sha256").
+///
+/// The metadata is sent first, built by `RainbowTableHeader` from the first password. Passwords the header
+/// cannot describe, such as empty ones, are skipped.
 fn read_passwords(
     num_links: usize,
     reader: BufReader<File>,
+    rules: Option<&RuleSet>,
     tx_password: Sender<String>,
     tx_printer: Sender<Vec<u8>>,
-    algorithm: &str,
+    algorithm: &Arc<dyn HashAlgorithm>,
 ) {
     let mut first_iteration = true;
-    for line in reader.lines() {
+    let mut password_len = 0;
+    for line in rules::expand_lines(reader.lines(), rules) {
         match line {
             Ok(password) => {
+                // Chains are stored at a fixed width, so every password must match the first one
+                if !first_iteration && password.len() != password_len {
+                    error!(
+                        "Skipping password of length {}, table password length is {}",
+                        password.len(),
+                        password_len
+                    );
+                    continue;
+                }
                 if first_iteration {
-                    // Write metadata first (MAGIC WORD, VERSION, ALGORITHM LENGTH, ALGORITHM, PASSWORD LENGTH, CHARACTER SET SIZE, NUMBER OF LINKS, ASCII OFFSET)
+                    // The metadata goes first, sized after the first password
+                    let header =
+                        match RainbowTableHeader::new(algorithm.clone(), password.len(), num_links)
+                        {
+                            Ok(header) => header,
+                            Err(e) => {
+                                error!("Skipping password: {}", e);
+                                continue;
+                            }
+                        };
                     let mut metadata: Vec<u8> = vec![];
-                    // MAGIC WORD: UTF-8 "rainbowtable"
-                    metadata.extend_from_slice(b"rainbowtable");
-                    // VERSION: 1 byte (value 1)
-                    metadata.push(1);
-                    // ALGORITHM LENGTH: length of the algorithm string
-                    let algo_lower = algorithm.to_lowercase();
-                    metadata.push(algo_lower.len() as u8);
-                    // ALGORITHM: the algorithm string in lowercase, no null terminator
-                    metadata.extend_from_slice(algo_lower.as_bytes());
-                    // PASSWORD LENGTH: length of the password
-                    metadata.push(password.len() as u8);
-                    // CHARACTER SET SIZE: 16 bytes, big-endian with leading zeros
-                    let charset_size: u8 = 95;
-                    let charset_bytes = charset_size.to_be_bytes(); // 2 bytes
-                    let charset_padding = vec![0u8; 16 - charset_bytes.len()];
-                    metadata.extend_from_slice(&charset_padding); // pad first
-                    metadata.extend_from_slice(&charset_bytes); // then actual value
-                    // NUMBER OF LINKS: 16 bytes, little-endian with leading zeros
-                    let num_links_bytes = num_links.to_be_bytes(); // 8 bytes
-                    let link_padding = vec![0u8; 16 - num_links_bytes.len()];
-                    metadata.extend_from_slice(&link_padding); // pad first
-                    metadata.extend_from_slice(&num_links_bytes); // then actual value
-                    // ASCII OFFSET: 1 byte
-                    metadata.push(32);
-
+                    if let Err(e) = header.write_to(&mut metadata) {
+                        error!("Failed to write metadata: {}", e);
+                    }
                     if let Err(e) = tx_printer.send(metadata) {
                         error!("Failed to send metadata: {}", e);
                     }
+                    password_len = password.len();
                     first_iteration = false;
                 }
                 if let Err(e) = tx_password.send(password) {
@@ -226,7 +250,7 @@ fn read_passwords(
 /// - `num_threads`: The number of threads to spawn for parallel chain generation.
 /// - `rx_encrpyter`: A channel receiver that provides plaintext passwords to be processed.
 /// - `tx_printer`: A channel sender that receives the final result (e.g., chain endpoint or serialized data).
-/// - `algorithm`: The hash algorithm to use (e.g., "sha256", "md5").
+/// - `algorithm`: The hash algorithm to use.
 ///
 /// # Returns
 ///
@@ -241,7 +265,7 @@ fn generate_rainbow_chain(
     num_threads: u32,
     rx_encrpyter: Receiver<String>,
     tx_printer: Sender<Vec<u8>>,
-    algorithm: String,
+    algorithm: Arc<dyn HashAlgorithm>,
 ) -> Result<Vec<JoinHandle<()>>, HashassinError> {
     let result = (0..num_threads)
         .map(|_| {
@@ -250,29 +274,8 @@ fn generate_rainbow_chain(
             let algorithm_clone = algorithm.clone();
             thread::spawn(move || {
                 while let Ok(password) = rx_encrpyter.recv() {
-                    let result = match algorithm_clone.as_str() {
-                        "md5" => {
-                            create_chain(password.clone(), num_links, algorithms::generate_md5_hash)
-                        }
-                        "sha256" => create_chain(
-                            password.clone(),
-                            num_links,
-                            algorithms::generate_sha256_hash,
-                        ),
-                        "sha3_512" => create_chain(
-                            password.clone(),
-                            num_links,
-                            algorithms::generate_sha3_512_hash,
-                        ),
-                        "scrypt" => create_chain(
-                            password.clone(),
-                            num_links,
-                            algorithms::generate_scrypt_hash,
-                        ),
-                        _ => Err(HashassinError::UnknownAlgorithm(
-                            algorithm_clone.to_string(),
-                        )),
-                    };
+                    let result =
+                        create_chain(password.clone(), num_links, algorithm_clone.as_ref());
 
                     match result {
                         Ok(hashed_password) => {
@@ -295,30 +298,26 @@ fn generate_rainbow_chain(
     Ok(result)
 }
 
-/// Creates a rainbow chain from a given password using a specified hash function.
+/// Creates a rainbow chain from a given password using a specified hash algorithm.
 ///
 /// This function takes an initial `password` and applies a hash-reduction process
-/// for `num_links` iterations. A custom hash function is provided as `hash_func`,
-/// which is applied repeatedly to simulate a rainbow chain. The final result is a
-/// serialized representation of the chain endpoint or intermediate data.
+/// for `num_links` iterations. The `algorithm` is applied repeatedly to simulate a
+/// rainbow chain. The final result is a serialized representation of the chain endpoint
+/// or intermediate data.
 ///
 /// # Parameters
 ///
 /// - `password`: The starting plaintext string for the rainbow chain.
 /// - `num_links`: The number of hash-reduction steps to perform in the chain.
-/// - `hash_func`: A function or closure that performs the hash-reduction operation.
-///   It must implement `Fn(&str) -> Result<String, HashassinError>`.
-fn create_chain<F>(
+/// - `algorithm`: The hash algorithm applied at every link.
+fn create_chain(
     mut password: String,
     num_links: u32,
-    hash_func: F,
-) -> Result<Vec<u8>, HashassinError>
-where
-    F: Fn(String) -> Vec<u8>,
-{
+    algorithm: &dyn HashAlgorithm,
+) -> Result<Vec<u8>, HashassinError> {
     let radix = Radix::new(95); // 95 printable ASCII characters 
     for round in 0..num_links {
-        let hash = hash_func(password.clone());
+        let hash = algorithm.hash(password.as_bytes())?;
         let reduced =
             algorithms::reduction_function(hash, round as u128, password.len() as u32, &radix);
         password = reduced;
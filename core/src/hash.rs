@@ -1,32 +1,1092 @@
-use crate::algorithms::{
-    generate_md5_hash, generate_scrypt_hash, generate_sha3_512_hash, generate_sha256_hash,
+use crate::{HashassinError, algorithms, compose};
+use std::{
+    collections::HashMap,
+    fmt::{Debug, Display},
+    sync::{Arc, OnceLock},
 };
-use std::fmt::Display;
 
+/// A hash function Hashassin can generate, store and crack.
+///
+/// Algorithms are looked up in the [`HashRegistry`] by the name stored in hash file and rainbow table
+/// headers, so every command and the server agree on names, digest lengths and how passwords are hashed.
+pub trait HashAlgorithm: Debug + Send + Sync {
+    /// Canonical lowercase name, written into hash file and rainbow table headers.
+    fn name(&self) -> &str;
+
+    /// Other names the algorithm is known by, accepted wherever a name is.
+    fn aliases(&self) -> &[&str] {
+        &[]
+    }
+
+    /// Length of a digest in bytes, or `None` if the output is a variable-length encoded string.
+    fn digest_len(&self) -> Option<usize>;
+
+    /// Block length of the compression function in bytes, needed to use the algorithm as an HMAC, or `None`
+    /// if it cannot be used as one.
+    fn block_len(&self) -> Option<usize> {
+        None
+    }
+
+    /// Whether every hash uses its own salt, so equal passwords hash differently.
+    fn salted(&self) -> bool {
+        false
+    }
+
+    /// Hashes a password.
+    ///
+    /// # Errors
+    ///
+    /// Returns `HashassinError::HashError` if the hash function fails, such as on a password its cost
+    /// parameters cannot take. Unsalted algorithms with a fixed digest length never fail.
+    fn hash(&self, password: &[u8]) -> Result<Vec<u8>, HashassinError>;
+
+    /// Hashes a password with the salt of a salted hash file entry, for algorithms that place the salt
+    /// themselves, such as the expression `md5(p+salt)`. Other algorithms ignore the salt.
+    ///
+    /// # Errors
+    ///
+    /// Same as `HashAlgorithm::hash`.
+    fn hash_with_salt(&self, password: &[u8], salt: &[u8]) -> Result<Vec<u8>, HashassinError> {
+        let _ = salt;
+        self.hash(password)
+    }
+
+    /// Whether `password` hashes to `hash`. Salted algorithms take the salt and cost parameters from `hash`
+    /// instead of their own settings.
+    fn verify(&self, password: &[u8], hash: &[u8]) -> bool {
+        self.hash(password).is_ok_and(|digest| digest == hash)
+    }
+
+    /// Whether `hash` is an encoded hash, such as a PHC or crypt string, produced by this algorithm.
+    fn recognizes(&self, hash: &str) -> bool {
+        let _ = hash;
+        false
+    }
+
+    /// Digest length of an unsalted, fixed-length algorithm, the only kind raw hash entries and rainbow
+    /// tables can hold.
+    ///
+    /// # Errors
+    ///
+    /// Returns `HashassinError::UnknownAlgorithm` if the algorithm is salted or has variable-length output.
+    fn fixed_digest_len(&self) -> Result<usize, HashassinError> {
+        match self.digest_len() {
+            Some(len) if !self.salted() => Ok(len),
+            _ => Err(HashassinError::UnknownAlgorithm(format!(
+                "{} is salted or has variable-length output and cannot be used here",
+                self.name()
+            ))),
+        }
+    }
+}
+
+impl Display for dyn HashAlgorithm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Declares an unsalted algorithm with a fixed digest length and an optional HMAC block length.
+macro_rules! fixed_hash {
+    ($(#[$doc:meta])* $ty:ident, $name:literal, [$($alias:literal),*], $len:literal, $block:expr, $hash:expr) => {
+        $(#[$doc])*
+        #[derive(Debug, Clone, Copy)]
+        pub struct $ty;
+
+        impl HashAlgorithm for $ty {
+            fn name(&self) -> &str {
+                $name
+            }
+
+            fn aliases(&self) -> &[&str] {
+                &[$($alias),*]
+            }
+
+            fn digest_len(&self) -> Option<usize> {
+                Some($len)
+            }
+
+            fn block_len(&self) -> Option<usize> {
+                $block
+            }
+
+            fn hash(&self, password: &[u8]) -> Result<Vec<u8>, HashassinError> {
+                Ok($hash(password))
+            }
+        }
+    };
+}
+
+fixed_hash!(
+    /// MD5, 16-byte digests.
+    Md5, "md5", [], 16, Some(64), algorithms::generate_md5_hash
+);
+fixed_hash!(
+    /// SHA-1, 20-byte digests.
+    Sha1, "sha1", ["sha-1"], 20, Some(64), algorithms::generate_digest::<sha1::Sha1>
+);
+fixed_hash!(
+    /// SHA-224, 28-byte digests.
+    Sha224, "sha224", ["sha-224", "sha2-224"], 28, Some(64), algorithms::generate_digest::<sha2::Sha224>
+);
+fixed_hash!(
+    /// SHA-256, 32-byte digests.
+    Sha256, "sha256", ["sha-256", "sha2-256"], 32, Some(64), algorithms::generate_sha256_hash
+);
+fixed_hash!(
+    /// SHA-384, 48-byte digests.
+    Sha384, "sha384", ["sha-384", "sha2-384"], 48, Some(128), algorithms::generate_digest::<sha2::Sha384>
+);
+fixed_hash!(
+    /// SHA-512, 64-byte digests.
+    Sha512, "sha512", ["sha-512", "sha2-512"], 64, Some(128), algorithms::generate_digest::<sha2::Sha512>
+);
+fixed_hash!(
+    /// SHA3-224, 28-byte digests.
+    Sha3_224, "sha3_224", ["sha3-224"], 28, Some(144), algorithms::generate_digest::<sha3::Sha3_224>
+);
+fixed_hash!(
+    /// SHA3-256, 32-byte digests.
+    Sha3_256, "sha3_256", ["sha3-256"], 32, Some(136), algorithms::generate_digest::<sha3::Sha3_256>
+);
+fixed_hash!(
+    /// SHA3-384, 48-byte digests.
+    Sha3_384, "sha3_384", ["sha3-384"], 48, Some(104), algorithms::generate_digest::<sha3::Sha3_384>
+);
+fixed_hash!(
+    /// SHA3-512, 64-byte digests.
+    Sha3_512, "sha3_512", ["sha3-512"], 64, Some(72), algorithms::generate_sha3_512_hash
+);
+fixed_hash!(
+    /// BLAKE2b, 64-byte digests.
+    Blake2b, "blake2b", ["blake2b-512", "blake2b512"], 64, Some(128), algorithms::generate_digest::<blake2::Blake2b512>
+);
+fixed_hash!(
+    /// BLAKE2s, 32-byte digests.
+    Blake2s, "blake2s", ["blake2s-256", "blake2s256"], 32, Some(64), algorithms::generate_digest::<blake2::Blake2s256>
+);
+fixed_hash!(
+    /// NTLM, the MD4 of the UTF-16LE password used by Windows, 16-byte digests.
+    Ntlm, "ntlm", ["nt"], 16, None, algorithms::generate_ntlm_hash
+);
+fixed_hash!(
+    /// Legacy LAN Manager hash, 16-byte digests. Case-insensitive and limited to 14 characters.
+    Lm, "lm", ["lanman"], 16, None, algorithms::generate_lm_hash
+);
+
+/// Splits the cost parameters off a parameterized name such as `scrypt:ln=15,r=8`.
+///
+/// Returns `None` if `name` is not `family` followed by `:` and comma separated `key=value` pairs.
+fn cost_params<'a>(name: &'a str, family: &str) -> Option<HashMap<&'a str, &'a str>> {
+    name.strip_prefix(family)?
+        .strip_prefix(':')?
+        .split(',')
+        .map(|param| param.split_once('='))
+        .collect()
+}
+
+/// Scrypt with a random salt, producing a PHC string.
+///
+/// Every PHC string records its own salt and cost parameters, so a hash file only needs the name `scrypt`
+/// whatever the cost. Hashes are generated with the recommended parameters unless the algorithm is looked up
+/// as `scrypt:ln=<log2 N>,r=<r>,p=<p>` (or `n=<N>`), where omitted parameters keep their recommended value.
 #[derive(Debug, Clone)]
-pub enum HashAlgorithm {
-    Md5,
-    Sha256,
-    Sha3_512,
-    Scrypt,
+pub struct Scrypt {
+    params: scrypt::Params,
 }
 
-pub fn hash_with_algorithm(password: &str, algo: &HashAlgorithm) -> Vec<u8> {
-    match algo {
-        HashAlgorithm::Md5 => generate_md5_hash(password.to_string()),
-        HashAlgorithm::Sha256 => generate_sha256_hash(password.to_string()),
-        HashAlgorithm::Sha3_512 => generate_sha3_512_hash(password.to_string()),
-        HashAlgorithm::Scrypt => generate_scrypt_hash(password.to_string()),
+impl Scrypt {
+    /// Scrypt with cost `N = 2^log_n`, block size `r` and parallelism `p`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `HashassinError::InvalidInput` if scrypt rejects the parameters.
+    pub fn new(log_n: u8, r: u32, p: u32) -> Result<Self, HashassinError> {
+        let params = scrypt::Params::new(log_n, r, p, scrypt::Params::RECOMMENDED_LEN)
+            .map_err(|e| HashassinError::InvalidInput(format!("Invalid scrypt parameters: {e}")))?;
+        Ok(Scrypt { params })
+    }
+
+    /// Parses `scrypt:ln=<log2 N>,r=<r>,p=<p>`, also accepting `n=<N>` for a power of two N.
+    fn parse(name: &str) -> Option<Arc<dyn HashAlgorithm>> {
+        let params = cost_params(name, "scrypt")?;
+        let mut log_n = scrypt::Params::RECOMMENDED_LOG_N;
+        let mut r = scrypt::Params::RECOMMENDED_R;
+        let mut p = scrypt::Params::RECOMMENDED_P;
+        for (key, value) in params {
+            match key {
+                "ln" => log_n = value.parse().ok()?,
+                "n" => {
+                    let n: u64 = value.parse().ok()?;
+                    if !n.is_power_of_two() {
+                        return None;
+                    }
+                    log_n = n.trailing_zeros() as u8;
+                }
+                "r" => r = value.parse().ok()?,
+                "p" => p = value.parse().ok()?,
+                _ => return None,
+            }
+        }
+        Some(Arc::new(Scrypt::new(log_n, r, p).ok()?))
     }
 }
 
-impl Display for HashAlgorithm {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            HashAlgorithm::Md5 => write!(f, "MD5"),
-            HashAlgorithm::Sha256 => write!(f, "SHA256"),
-            HashAlgorithm::Sha3_512 => write!(f, "SHA3-512"),
-            HashAlgorithm::Scrypt => write!(f, "Scrypt"),
+impl Default for Scrypt {
+    fn default() -> Self {
+        Scrypt {
+            params: scrypt::Params::recommended(),
+        }
+    }
+}
+
+impl HashAlgorithm for Scrypt {
+    fn name(&self) -> &str {
+        "scrypt"
+    }
+
+    fn digest_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn salted(&self) -> bool {
+        true
+    }
+
+    fn hash(&self, password: &[u8]) -> Result<Vec<u8>, HashassinError> {
+        algorithms::generate_scrypt_hash(password, self.params)
+    }
+
+    fn verify(&self, password: &[u8], hash: &[u8]) -> bool {
+        algorithms::verify_scrypt_hash(password, hash)
+    }
+
+    fn recognizes(&self, hash: &str) -> bool {
+        hash.starts_with("$scrypt$")
+    }
+}
+
+/// Argon2id or Argon2i with a random salt, producing a PHC string.
+///
+/// Hashes are generated with the default cost (19 MiB, 2 passes, 1 lane) unless the algorithm is looked up as
+/// `argon2id:m=<KiB>,t=<passes>,p=<lanes>` (or `argon2i:...`). Verification reads the variant, salt and cost
+/// from the PHC string.
+#[derive(Debug, Clone)]
+pub struct Argon2 {
+    algorithm: argon2::Algorithm,
+    params: argon2::Params,
+}
+
+impl Argon2 {
+    /// Argon2id using `m_cost` KiB of memory, `t_cost` passes and `p_cost` lanes.
+    ///
+    /// # Errors
+    ///
+    /// Returns `HashassinError::InvalidInput` if Argon2 rejects the parameters.
+    pub fn argon2id(m_cost: u32, t_cost: u32, p_cost: u32) -> Result<Self, HashassinError> {
+        Self::with_algorithm(argon2::Algorithm::Argon2id, m_cost, t_cost, p_cost)
+    }
+
+    /// Argon2i using `m_cost` KiB of memory, `t_cost` passes and `p_cost` lanes.
+    ///
+    /// # Errors
+    ///
+    /// Returns `HashassinError::InvalidInput` if Argon2 rejects the parameters.
+    pub fn argon2i(m_cost: u32, t_cost: u32, p_cost: u32) -> Result<Self, HashassinError> {
+        Self::with_algorithm(argon2::Algorithm::Argon2i, m_cost, t_cost, p_cost)
+    }
+
+    fn with_algorithm(
+        algorithm: argon2::Algorithm,
+        m_cost: u32,
+        t_cost: u32,
+        p_cost: u32,
+    ) -> Result<Self, HashassinError> {
+        let params = argon2::Params::new(m_cost, t_cost, p_cost, None)
+            .map_err(|e| HashassinError::InvalidInput(format!("Invalid argon2 parameters: {e}")))?;
+        Ok(Argon2 { algorithm, params })
+    }
+
+    fn default_with(algorithm: argon2::Algorithm) -> Self {
+        Argon2 {
+            algorithm,
+            params: argon2::Params::DEFAULT,
+        }
+    }
+
+    /// Parses `<variant>:m=<KiB>,t=<passes>,p=<lanes>`.
+    fn parse(algorithm: argon2::Algorithm, name: &str) -> Option<Arc<dyn HashAlgorithm>> {
+        let params = cost_params(name, algorithm.as_str())?;
+        let mut m_cost = argon2::Params::DEFAULT_M_COST;
+        let mut t_cost = argon2::Params::DEFAULT_T_COST;
+        let mut p_cost = argon2::Params::DEFAULT_P_COST;
+        for (key, value) in params {
+            match key {
+                "m" => m_cost = value.parse().ok()?,
+                "t" => t_cost = value.parse().ok()?,
+                "p" => p_cost = value.parse().ok()?,
+                _ => return None,
+            }
+        }
+        let argon2 = Self::with_algorithm(algorithm, m_cost, t_cost, p_cost).ok()?;
+        Some(Arc::new(argon2))
+    }
+}
+
+impl HashAlgorithm for Argon2 {
+    fn name(&self) -> &str {
+        self.algorithm.as_str()
+    }
+
+    fn digest_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn salted(&self) -> bool {
+        true
+    }
+
+    fn hash(&self, password: &[u8]) -> Result<Vec<u8>, HashassinError> {
+        algorithms::generate_argon2_hash(password, self.algorithm, self.params.clone())
+    }
+
+    fn verify(&self, password: &[u8], hash: &[u8]) -> bool {
+        algorithms::verify_argon2_hash(password, hash)
+    }
+
+    fn recognizes(&self, hash: &str) -> bool {
+        hash.strip_prefix('$')
+            .and_then(|hash| hash.strip_prefix(self.name()))
+            .is_some_and(|rest| rest.starts_with('$'))
+    }
+}
+
+/// PBKDF2-HMAC-SHA256 or PBKDF2-HMAC-SHA512 with a random salt, producing a PHC string.
+///
+/// Hashes are generated with the iteration counts recommended by OWASP (600,000 for SHA-256, 210,000 for
+/// SHA-512) unless the algorithm is looked up as `pbkdf2-sha256:i=<iterations>` (or `pbkdf2-sha512:...`).
+#[derive(Debug, Clone)]
+pub struct Pbkdf2 {
+    algorithm: pbkdf2::Algorithm,
+    params: pbkdf2::Params,
+}
+
+impl Pbkdf2 {
+    /// Recommended iterations of PBKDF2-HMAC-SHA512.
+    pub const SHA512_ROUNDS: u32 = 210_000;
+
+    /// PBKDF2-HMAC-SHA256 with `rounds` iterations.
+    ///
+    /// # Errors
+    ///
+    /// Returns `HashassinError::InvalidInput` if `rounds` is 0.
+    pub fn sha256(rounds: u32) -> Result<Self, HashassinError> {
+        Self::with_algorithm(pbkdf2::Algorithm::Pbkdf2Sha256, rounds)
+    }
+
+    /// PBKDF2-HMAC-SHA512 with `rounds` iterations.
+    ///
+    /// # Errors
+    ///
+    /// Returns `HashassinError::InvalidInput` if `rounds` is 0.
+    pub fn sha512(rounds: u32) -> Result<Self, HashassinError> {
+        Self::with_algorithm(pbkdf2::Algorithm::Pbkdf2Sha512, rounds)
+    }
+
+    fn with_algorithm(algorithm: pbkdf2::Algorithm, rounds: u32) -> Result<Self, HashassinError> {
+        if rounds == 0 {
+            return Err(HashassinError::InvalidInput(
+                "PBKDF2 needs at least one iteration".to_string(),
+            ));
+        }
+        let params = pbkdf2::Params {
+            rounds,
+            ..pbkdf2::Params::default()
+        };
+        Ok(Pbkdf2 { algorithm, params })
+    }
+
+    fn default_rounds(algorithm: pbkdf2::Algorithm) -> u32 {
+        match algorithm {
+            pbkdf2::Algorithm::Pbkdf2Sha512 => Self::SHA512_ROUNDS,
+            _ => pbkdf2::Params::RECOMMENDED_ROUNDS as u32,
+        }
+    }
+
+    fn default_with(algorithm: pbkdf2::Algorithm) -> Self {
+        let params = pbkdf2::Params {
+            rounds: Self::default_rounds(algorithm),
+            ..pbkdf2::Params::default()
+        };
+        Pbkdf2 { algorithm, params }
+    }
+
+    /// Parses `<pbkdf2-sha256|pbkdf2-sha512>:i=<iterations>`.
+    fn parse(algorithm: pbkdf2::Algorithm, name: &str) -> Option<Arc<dyn HashAlgorithm>> {
+        let params = cost_params(name, algorithm.as_str())?;
+        let mut rounds = Self::default_rounds(algorithm);
+        for (key, value) in params {
+            match key {
+                "i" => rounds = value.parse().ok()?,
+                _ => return None,
+            }
+        }
+        Some(Arc::new(Self::with_algorithm(algorithm, rounds).ok()?))
+    }
+}
+
+impl HashAlgorithm for Pbkdf2 {
+    fn name(&self) -> &str {
+        self.algorithm.as_str()
+    }
+
+    fn digest_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn salted(&self) -> bool {
+        true
+    }
+
+    fn hash(&self, password: &[u8]) -> Result<Vec<u8>, HashassinError> {
+        algorithms::generate_pbkdf2_hash(password, self.algorithm, self.params)
+    }
+
+    fn verify(&self, password: &[u8], hash: &[u8]) -> bool {
+        algorithms::verify_pbkdf2_hash(password, hash)
+    }
+
+    fn recognizes(&self, hash: &str) -> bool {
+        hash.strip_prefix('$')
+            .and_then(|hash| hash.strip_prefix(self.name()))
+            .is_some_and(|rest| rest.starts_with('$'))
+    }
+}
+
+/// Bcrypt with a random salt, producing a `$2b$` modular crypt string.
+///
+/// Hashes are generated with cost 12 unless the algorithm is looked up as `bcrypt:cost=<4-31>`. Bcrypt only
+/// uses the first 72 bytes of a password.
+#[derive(Debug, Clone, Copy)]
+pub struct Bcrypt {
+    cost: u32,
+}
+
+impl Bcrypt {
+    /// Bcrypt with `2^cost` rounds.
+    ///
+    /// # Errors
+    ///
+    /// Returns `HashassinError::InvalidInput` if `cost` is outside 4 to 31.
+    pub fn new(cost: u32) -> Result<Self, HashassinError> {
+        if !(4..=31).contains(&cost) {
+            return Err(HashassinError::InvalidInput(format!(
+                "bcrypt cost must be between 4 and 31, got {cost}"
+            )));
+        }
+        Ok(Bcrypt { cost })
+    }
+
+    /// Parses `bcrypt:cost=<4-31>`.
+    fn parse(name: &str) -> Option<Arc<dyn HashAlgorithm>> {
+        let params = cost_params(name, "bcrypt")?;
+        let mut cost = bcrypt::DEFAULT_COST;
+        for (key, value) in params {
+            match key {
+                "cost" => cost = value.parse().ok()?,
+                _ => return None,
+            }
+        }
+        Some(Arc::new(Bcrypt::new(cost).ok()?))
+    }
+}
+
+impl Default for Bcrypt {
+    fn default() -> Self {
+        Bcrypt {
+            cost: bcrypt::DEFAULT_COST,
+        }
+    }
+}
+
+impl HashAlgorithm for Bcrypt {
+    fn name(&self) -> &str {
+        "bcrypt"
+    }
+
+    fn digest_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn salted(&self) -> bool {
+        true
+    }
+
+    fn hash(&self, password: &[u8]) -> Result<Vec<u8>, HashassinError> {
+        algorithms::generate_bcrypt_hash(password, self.cost)
+    }
+
+    fn verify(&self, password: &[u8], hash: &[u8]) -> bool {
+        algorithms::verify_bcrypt_hash(password, hash)
+    }
+
+    fn recognizes(&self, hash: &str) -> bool {
+        ["$2a$", "$2b$", "$2x$", "$2y$"]
+            .iter()
+            .any(|prefix| hash.starts_with(prefix))
+    }
+}
+
+/// md5crypt, the `$1$` crypt(3) scheme, with a random salt.
+#[derive(Debug, Clone, Copy)]
+pub struct Md5Crypt;
+
+impl HashAlgorithm for Md5Crypt {
+    fn name(&self) -> &str {
+        "md5crypt"
+    }
+
+    fn aliases(&self) -> &[&str] {
+        &["md5-crypt"]
+    }
+
+    fn digest_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn salted(&self) -> bool {
+        true
+    }
+
+    fn hash(&self, password: &[u8]) -> Result<Vec<u8>, HashassinError> {
+        algorithms::generate_md5_crypt_hash(password)
+    }
+
+    fn verify(&self, password: &[u8], hash: &[u8]) -> bool {
+        hash.starts_with(b"$1$") && algorithms::verify_crypt_hash(password, hash)
+    }
+
+    fn recognizes(&self, hash: &str) -> bool {
+        hash.starts_with("$1$")
+    }
+}
+
+/// sha256crypt or sha512crypt, the `$5$` and `$6$` crypt(3) schemes, with a random salt.
+///
+/// Hashes use 5000 rounds unless the algorithm is looked up as `sha512crypt:rounds=<1000-999999999>` (or
+/// `sha256crypt:...`); the rounds are then recorded in the crypt string.
+#[derive(Debug, Clone, Copy)]
+pub struct ShaCrypt {
+    bits: u16,
+    rounds: Option<u32>,
+}
+
+impl ShaCrypt {
+    /// Fewest rounds crypt(3) accepts.
+    pub const MIN_ROUNDS: u32 = 1000;
+    /// Most rounds crypt(3) accepts.
+    pub const MAX_ROUNDS: u32 = 999_999_999;
+
+    /// sha256crypt with `rounds` rounds, or the default 5000.
+    ///
+    /// # Errors
+    ///
+    /// Returns `HashassinError::InvalidInput` if `rounds` is outside 1000 to 999999999.
+    pub fn sha256(rounds: Option<u32>) -> Result<Self, HashassinError> {
+        Self::with_bits(256, rounds)
+    }
+
+    /// sha512crypt with `rounds` rounds, or the default 5000.
+    ///
+    /// # Errors
+    ///
+    /// Returns `HashassinError::InvalidInput` if `rounds` is outside 1000 to 999999999.
+    pub fn sha512(rounds: Option<u32>) -> Result<Self, HashassinError> {
+        Self::with_bits(512, rounds)
+    }
+
+    fn with_bits(bits: u16, rounds: Option<u32>) -> Result<Self, HashassinError> {
+        if let Some(rounds) = rounds
+            && !(Self::MIN_ROUNDS..=Self::MAX_ROUNDS).contains(&rounds)
+        {
+            return Err(HashassinError::InvalidInput(format!(
+                "crypt rounds must be between {} and {}, got {rounds}",
+                Self::MIN_ROUNDS,
+                Self::MAX_ROUNDS
+            )));
+        }
+        Ok(ShaCrypt { bits, rounds })
+    }
+
+    /// Parses `sha<bits>crypt:rounds=<rounds>`.
+    fn parse(bits: u16, name: &str) -> Option<Arc<dyn HashAlgorithm>> {
+        let params = cost_params(name, &format!("sha{bits}crypt"))?;
+        let mut rounds = None;
+        for (key, value) in params {
+            match key {
+                "rounds" => rounds = Some(value.parse().ok()?),
+                _ => return None,
+            }
+        }
+        Some(Arc::new(Self::with_bits(bits, rounds).ok()?))
+    }
+
+    fn prefix(&self) -> &'static str {
+        match self.bits {
+            256 => "$5$",
+            _ => "$6$",
+        }
+    }
+}
+
+impl HashAlgorithm for ShaCrypt {
+    fn name(&self) -> &str {
+        match self.bits {
+            256 => "sha256crypt",
+            _ => "sha512crypt",
+        }
+    }
+
+    fn aliases(&self) -> &[&str] {
+        match self.bits {
+            256 => &["sha256-crypt"],
+            _ => &["sha512-crypt"],
         }
     }
+
+    fn digest_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn salted(&self) -> bool {
+        true
+    }
+
+    fn hash(&self, password: &[u8]) -> Result<Vec<u8>, HashassinError> {
+        algorithms::generate_sha_crypt_hash(password, self.bits, self.rounds)
+    }
+
+    fn verify(&self, password: &[u8], hash: &[u8]) -> bool {
+        hash.starts_with(self.prefix().as_bytes()) && algorithms::verify_crypt_hash(password, hash)
+    }
+
+    fn recognizes(&self, hash: &str) -> bool {
+        hash.starts_with(self.prefix())
+    }
+}
+
+/// BLAKE3 with a configurable output length.
+///
+/// The default 32-byte variant is called `blake3`; other lengths are named `blake3_<bits>`, such as
+/// `blake3_512`, so the length travels with the algorithm name in hash file and rainbow table headers.
+#[derive(Debug, Clone)]
+pub struct Blake3 {
+    name: String,
+    digest_len: usize,
+}
+
+impl Blake3 {
+    /// Output length of plain `blake3` in bytes.
+    pub const DEFAULT_LEN: usize = 32;
+    /// Longest output in bytes; the rainbow table reduction reads at most 64 digest bytes.
+    pub const MAX_LEN: usize = 64;
+
+    /// BLAKE3 producing `digest_len` bytes.
+    ///
+    /// # Errors
+    ///
+    /// Returns `HashassinError::InvalidInput` if `digest_len` is 0 or greater than `Blake3::MAX_LEN`.
+    pub fn new(digest_len: usize) -> Result<Self, HashassinError> {
+        if !(1..=Self::MAX_LEN).contains(&digest_len) {
+            return Err(HashassinError::InvalidInput(format!(
+                "BLAKE3 output length must be between 1 and {} bytes, got {digest_len}",
+                Self::MAX_LEN
+            )));
+        }
+        let name = if digest_len == Self::DEFAULT_LEN {
+            "blake3".to_string()
+        } else {
+            format!("blake3_{}", digest_len * 8)
+        };
+        Ok(Blake3 { name, digest_len })
+    }
+
+    /// Parses `blake3_<bits>`, where the bit count is a multiple of 8.
+    fn parse(name: &str) -> Option<Arc<dyn HashAlgorithm>> {
+        let bits: usize = name.strip_prefix("blake3_")?.parse().ok()?;
+        if !bits.is_multiple_of(8) {
+            return None;
+        }
+        let algorithm = Blake3::new(bits / 8).ok()?;
+        Some(Arc::new(algorithm))
+    }
+}
+
+impl Default for Blake3 {
+    fn default() -> Self {
+        Blake3 {
+            name: "blake3".to_string(),
+            digest_len: Self::DEFAULT_LEN,
+        }
+    }
+}
+
+impl HashAlgorithm for Blake3 {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn digest_len(&self) -> Option<usize> {
+        Some(self.digest_len)
+    }
+
+    fn block_len(&self) -> Option<usize> {
+        Some(64)
+    }
+
+    fn hash(&self, password: &[u8]) -> Result<Vec<u8>, HashassinError> {
+        Ok(algorithms::generate_blake3_hash(password, self.digest_len))
+    }
+}
+
+/// A family of parameterized algorithms recognised by the shape of their name.
+#[derive(Debug, Clone, Copy)]
+struct Family {
+    /// How names of the family look, shown when a lookup fails.
+    pattern: &'static str,
+    parse: fn(&str) -> Option<Arc<dyn HashAlgorithm>>,
+}
+
+/// The set of algorithms known by name.
+#[derive(Debug, Default)]
+pub struct HashRegistry {
+    algorithms: Vec<Arc<dyn HashAlgorithm>>,
+    families: Vec<Family>,
+}
+
+impl HashRegistry {
+    /// A registry holding every built-in algorithm.
+    pub fn with_builtins() -> Self {
+        let mut registry = HashRegistry::default();
+        registry.register(Md5);
+        registry.register(Sha1);
+        registry.register(Sha224);
+        registry.register(Sha256);
+        registry.register(Sha384);
+        registry.register(Sha512);
+        registry.register(Sha3_224);
+        registry.register(Sha3_256);
+        registry.register(Sha3_384);
+        registry.register(Sha3_512);
+        registry.register(Blake2b);
+        registry.register(Blake2s);
+        registry.register(Blake3::default());
+        registry.register_family("blake3_<bits>", Blake3::parse);
+        registry.register(Ntlm);
+        registry.register(Lm);
+        registry.register(Scrypt::default());
+        registry.register_family("scrypt:ln=<log2 N>,r=<r>,p=<p>", Scrypt::parse);
+        registry.register(Argon2::default_with(argon2::Algorithm::Argon2id));
+        registry.register_family("argon2id:m=<KiB>,t=<passes>,p=<lanes>", |name| {
+            Argon2::parse(argon2::Algorithm::Argon2id, name)
+        });
+        registry.register(Argon2::default_with(argon2::Algorithm::Argon2i));
+        registry.register_family("argon2i:m=<KiB>,t=<passes>,p=<lanes>", |name| {
+            Argon2::parse(argon2::Algorithm::Argon2i, name)
+        });
+        registry.register(Bcrypt::default());
+        registry.register_family("bcrypt:cost=<4-31>", Bcrypt::parse);
+        registry.register(Pbkdf2::default_with(pbkdf2::Algorithm::Pbkdf2Sha256));
+        registry.register_family("pbkdf2-sha256:i=<iterations>", |name| {
+            Pbkdf2::parse(pbkdf2::Algorithm::Pbkdf2Sha256, name)
+        });
+        registry.register(Pbkdf2::default_with(pbkdf2::Algorithm::Pbkdf2Sha512));
+        registry.register_family("pbkdf2-sha512:i=<iterations>", |name| {
+            Pbkdf2::parse(pbkdf2::Algorithm::Pbkdf2Sha512, name)
+        });
+        registry.register(Md5Crypt);
+        registry.register(ShaCrypt {
+            bits: 256,
+            rounds: None,
+        });
+        registry.register_family("sha256crypt:rounds=<rounds>", |name| {
+            ShaCrypt::parse(256, name)
+        });
+        registry.register(ShaCrypt {
+            bits: 512,
+            rounds: None,
+        });
+        registry.register_family("sha512crypt:rounds=<rounds>", |name| {
+            ShaCrypt::parse(512, name)
+        });
+        registry.register_family(compose::PATTERN, compose::parse);
+        registry
+    }
+
+    /// Adds an algorithm. Its name and aliases must not be taken by an algorithm registered earlier.
+    pub fn register(&mut self, algorithm: impl HashAlgorithm + 'static) {
+        self.algorithms.push(Arc::new(algorithm));
+    }
+
+    /// Adds a family of parameterized algorithms. `parse` receives lowercased names that match no registered
+    /// algorithm and returns the algorithm they describe, if any; `pattern` documents the accepted names.
+    pub fn register_family(
+        &mut self,
+        pattern: &'static str,
+        parse: fn(&str) -> Option<Arc<dyn HashAlgorithm>>,
+    ) {
+        self.families.push(Family { pattern, parse });
+    }
+
+    /// Finds an algorithm by its name or one of its aliases, ignoring case.
+    pub fn get(&self, name: &str) -> Option<Arc<dyn HashAlgorithm>> {
+        let name = name.to_lowercase();
+        self.algorithms
+            .iter()
+            .find(|algorithm| {
+                algorithm.name() == name || algorithm.aliases().contains(&name.as_str())
+            })
+            .cloned()
+            .or_else(|| {
+                self.families
+                    .iter()
+                    .find_map(|family| (family.parse)(&name))
+            })
+    }
+
+    /// Names of every registered algorithm followed by the patterns of every family.
+    pub fn names(&self) -> Vec<&str> {
+        self.algorithms
+            .iter()
+            .map(|algorithm| algorithm.name())
+            .chain(self.families.iter().map(|family| family.pattern))
+            .collect()
+    }
+
+    /// Every registered algorithm in registration order.
+    pub fn algorithms(&self) -> impl Iterator<Item = &Arc<dyn HashAlgorithm>> {
+        self.algorithms.iter()
+    }
+}
+
+/// The registry of built-in algorithms shared by every command and the server.
+pub fn registry() -> &'static HashRegistry {
+    static REGISTRY: OnceLock<HashRegistry> = OnceLock::new();
+    REGISTRY.get_or_init(HashRegistry::with_builtins)
+}
+
+/// Looks up a built-in algorithm by name or alias, ignoring case.
+///
+/// # Errors
+///
+/// Returns `HashassinError::UnknownAlgorithm` listing the supported names if no algorithm matches.
+pub fn lookup(name: &str) -> Result<Arc<dyn HashAlgorithm>, HashassinError> {
+    registry().get(name).ok_or_else(|| {
+        HashassinError::UnknownAlgorithm(format!(
+            "{name} (supported: {})",
+            registry().names().join(", ")
+        ))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn digests_match_known_answers() -> Result<(), HashassinError> {
+        let vectors = [
+            ("md5", "abc", "900150983cd24fb0d6963f7d28e17f72"),
+            ("sha1", "abc", "a9993e364706816aba3e25717850c26c9cd0d89d"),
+            (
+                "sha224",
+                "abc",
+                "23097d223405d8228642a477bda255b32aadbce4bda0b3f7e36c9da7",
+            ),
+            (
+                "sha256",
+                "abc",
+                "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+            ),
+            (
+                "sha384",
+                "abc",
+                "cb00753f45a35e8bb5a03d699ac65007272c32ab0eded1631a8b605a43ff5bed8086072ba1e7cc2358baeca134c825a7",
+            ),
+            (
+                "sha512",
+                "abc",
+                "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f",
+            ),
+            (
+                "sha3_224",
+                "abc",
+                "e642824c3f8cf24ad09234ee7d3c766fc9a3a5168d0c94ad73b46fdf",
+            ),
+            (
+                "sha3_256",
+                "abc",
+                "3a985da74fe225b2045c172d6bd390bd855f086e3e9d525b46bfe24511431532",
+            ),
+            (
+                "sha3_384",
+                "abc",
+                "ec01498288516fc926459f58e2c6ad8df9b473cb0fc08c2596da7cf0e49be4b298d88cea927ac7f539f1edf228376d25",
+            ),
+            (
+                "sha3_512",
+                "abc",
+                "b751850b1a57168a5693cd924b6b096e08f621827444f70d884f5d0240d2712e10e116e9192af3c91a7ec57647e3934057340b4cf408d5a56592f8274eec53f0",
+            ),
+            (
+                "blake2b",
+                "abc",
+                "ba80a53f981c4d0d6a2797b69f12f6e94c212f14685ac4b74b12bb6fdbffa2d17d87c5392aab792dc252d5de4533cc9518d38aa8dbf1925ab92386edd4009923",
+            ),
+            (
+                "blake2s",
+                "abc",
+                "508c5e8c327c14e2e1a72ba34eeb452f37458b209ed63a294d999b4c86675982",
+            ),
+            (
+                "blake3",
+                "abc",
+                "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85",
+            ),
+            ("ntlm", "password", "8846f7eaee8fb117ad06bdd830b7586c"),
+            ("lm", "password", "e52cac67419a9a224a3b108f3fa6cb6d"),
+        ];
+        for (name, password, expected) in vectors {
+            let digest = lookup(name)?.hash(password.as_bytes())?;
+            assert_eq!(hex::encode(digest), expected, "{name}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn blake3_lengths_share_a_prefix() -> Result<(), HashassinError> {
+        let short = lookup("blake3")?.hash(b"abc")?;
+        let long = lookup("blake3_512")?.hash(b"abc")?;
+        assert_eq!(long.len(), 64);
+        assert_eq!(&long[..32], short.as_slice());
+        Ok(())
+    }
+
+    #[test]
+    fn encoded_hashes_verify_known_answers() -> Result<(), HashassinError> {
+        let vectors = [
+            ("md5crypt", "password", "$1$3azHgidD$SrJPt7B.9rekpmwJwtON31"),
+            (
+                "sha256crypt",
+                "Hello world!",
+                "$5$saltstring$5B8vYYiY.CVt1RlTTf8KbXBH3hsxY/GNooZaBBGWEc5",
+            ),
+            (
+                "sha256crypt",
+                "Hello world!",
+                "$5$rounds=10000$saltstringsaltst$3xv.VbSHBb41AL9AvLeujZkZRBAwqFMz2.opqey6IcA",
+            ),
+            (
+                "sha512crypt",
+                "Hello world!",
+                "$6$saltstring$svn8UoSVapNtMuq1ukKS4tPQd8iKwSMHWjl/O817G3uBnIFNjnQJuesI68u4OTLiBFdcbYEdFCoEOfaS35inz1",
+            ),
+            (
+                "pbkdf2-sha256",
+                "password",
+                "$pbkdf2-sha256$i=1000,l=32$c2FsdHNhbHRzYWx0c2FsdA$8nX7hwFEzIB8aPajJTYK8weHQc5Ngz0pFVAKvSu4jQA",
+            ),
+            (
+                "pbkdf2-sha512",
+                "password",
+                "$pbkdf2-sha512$i=1000,l=64$c2FsdHNhbHRzYWx0c2FsdA$715rqIr5dXOVPpBhqqsugl037zT5bWJTWYmZtIcK8hBnisKpwfY7kokvwjDrNHqHhF50Pb7MD6HvkJwiDQw4ww",
+            ),
+            (
+                "scrypt",
+                "password",
+                "$scrypt$ln=4,r=8,p=1$c2FsdHNhbHRzYWx0c2FsdA$5f/Vi+XRWGUNGScbsma6KJ4zLFIke/NJsrvr7lQLAyA",
+            ),
+            (
+                "bcrypt",
+                "U*U",
+                "$2a$05$CCCCCCCCCCCCCCCCCCCCC.E5YPO9kmyuRGyh0XouQYb4YMJKvyOeW",
+            ),
+            (
+                "argon2i",
+                "password",
+                "$argon2i$v=19$m=256,t=2,p=1$c29tZXNhbHQ$iekCn0Y3spW+sCcFanM2xBT63UP2sghkUoHLIUpWRS8",
+            ),
+            (
+                "argon2id",
+                "password",
+                "$argon2id$v=19$m=256,t=2,p=1$c29tZXNhbHQ$nf65EOgLrQMR/uIPnA4rEsF5h7TKyQwu9U1bMCHGi/4",
+            ),
+        ];
+        for (name, password, hash) in vectors {
+            let algorithm = lookup(name)?;
+            assert!(algorithm.recognizes(hash), "{name} recognizes {hash}");
+            assert!(
+                algorithm.verify(password.as_bytes(), hash.as_bytes()),
+                "{name} verifies {hash}"
+            );
+            assert!(!algorithm.verify(b"wrong", hash.as_bytes()), "{name}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn salted_hashes_verify_their_own_output() -> Result<(), HashassinError> {
+        let names = [
+            "scrypt:ln=4,r=8,p=1",
+            "argon2id:m=64,t=1,p=1",
+            "argon2i:m=64,t=1,p=1",
+            "bcrypt:cost=4",
+            "pbkdf2-sha256:i=1000",
+            "pbkdf2-sha512:i=1000",
+            "md5crypt",
+            "sha256crypt:rounds=1000",
+            "sha512crypt:rounds=1000",
+        ];
+        for name in names {
+            let algorithm = lookup(name)?;
+            let hash = algorithm.hash(b"hunter2")?;
+            assert!(algorithm.verify(b"hunter2", &hash), "{name}");
+            assert_ne!(hash, algorithm.hash(b"hunter2")?, "{name} salts every hash");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn expressions_match_known_answers() -> Result<(), HashassinError> {
+        let nested = lookup("md5(sha1(p))")?.hash(b"password")?;
+        assert_eq!(hex::encode(nested), "1619d7adc23f4f633f11014d2f22b7d8");
+        let iterated = lookup("md5^2(p)")?.hash(b"password")?;
+        assert_eq!(hex::encode(iterated), "696d29e0940a4957748fe3fc9efd22a3");
+        let salted = lookup("md5(p+salt)")?.hash_with_salt(b"password", b"pepper")?;
+        assert_eq!(hex::encode(salted), "d89eddeec748c49d5add2f8f347b8899");
+        Ok(())
+    }
+
+    #[test]
+    fn lookup_accepts_aliases_in_any_case() -> Result<(), HashassinError> {
+        assert_eq!(lookup("SHA-256")?.name(), "sha256");
+        assert_eq!(lookup("nt")?.name(), "ntlm");
+        assert!(lookup("sha257").is_err());
+        assert!(lookup("md5(sha257(p))").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn expressions_are_limited_in_depth_and_length() -> Result<(), HashassinError> {
+        let nested = |depth: usize| format!("{}p{}", "md5(".repeat(depth), ")".repeat(depth));
+        lookup(&nested(16))?;
+        assert!(lookup(&nested(17)).is_err());
+        assert!(lookup(&nested(100_000)).is_err());
+
+        let arguments = |count: usize| format!("md5({})", vec!["p"; count].join("+"));
+        assert_eq!(arguments(126).len(), 256);
+        lookup(&arguments(125))?;
+        assert!(lookup(&arguments(126)).is_err());
+        Ok(())
+    }
 }
@@ -0,0 +1,507 @@
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Write},
+};
+
+use crate::{
+    HashassinError,
+    table::{HASH_FILE_VERSION, HashFileHeader, invalid, read_u8, read_vec, truncated},
+};
+
+/// PASSWORD LENGTH of version 3 entries whose password length is unknown, such as imported hashes.
+pub const UNKNOWN_PASSWORD_LENGTH: u8 = u8::MAX;
+
+/// Length of the ENTRY COUNT and CHECKSUM trailer.
+const TRAILER_LEN: usize = 8;
+
+/// Start of every scrypt PHC string. Version 1 scrypt files written before entries had a HASH LENGTH
+/// concatenate the strings, so they are split on it.
+const SCRYPT_PHC_PREFIX: &str = "$scrypt$";
+
+/// The PASSWORD LENGTH of `password`, unknown if it does not fit the byte.
+pub fn password_len(password: &str) -> Option<u8> {
+    u8::try_from(password.len())
+        .ok()
+        .filter(|&len| len != UNKNOWN_PASSWORD_LENGTH)
+}
+
+/// One hash of a hash file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HashEntry {
+    /// Length of the hashed password, if known.
+    pub password_len: Option<u8>,
+    /// Salt of salted files, empty otherwise.
+    pub salt: Vec<u8>,
+    /// Label of labeled files, such as a username.
+    pub label: Option<String>,
+    /// The digest, or the text of an encoded hash.
+    pub hash: Vec<u8>,
+}
+
+impl HashEntry {
+    /// An unsalted, unlabeled entry.
+    pub fn new(password_len: Option<u8>, hash: Vec<u8>) -> Self {
+        HashEntry {
+            password_len,
+            salt: Vec::new(),
+            label: None,
+            hash,
+        }
+    }
+}
+
+/// A hash file read into memory.
+#[derive(Debug, Clone)]
+pub struct HashFile {
+    pub header: HashFileHeader,
+    pub entries: Vec<HashEntry>,
+}
+
+impl HashFile {
+    /// Reads a version 1, 2 or 3 hash file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or is malformed; see `HashFile::parse`.
+    pub fn read(path: &str) -> Result<Self, HashassinError> {
+        let data = fs::read(path).map_err(|e| {
+            HashassinError::FileOpen(format!("Error opening hash file {path}: {e}"))
+        })?;
+        HashFile::parse(&data).map_err(|e| match e {
+            HashassinError::InvalidFormat(msg) => {
+                HashassinError::InvalidFormat(format!("{path}: {msg}"))
+            }
+            e => e,
+        })
+    }
+
+    /// Parses a version 1, 2 or 3 hash file.
+    ///
+    /// Version 1 entries of files with variable password lengths that record a length of 0 were imported
+    /// and have an unknown password length. Version 1 scrypt files whose PHC strings have no HASH LENGTH
+    /// are still read.
+    ///
+    /// # Errors
+    ///
+    /// Returns `HashassinError::InvalidFormat` if the file is truncated, has an unknown version, flags or salt
+    /// placement, or, for version 3, if its entry count or checksum do not match, and
+    /// `HashassinError::UnknownAlgorithm` for algorithms that do not exist or cannot be stored this way.
+    pub fn parse(data: &[u8]) -> Result<Self, HashassinError> {
+        let mut rest = data;
+        let header = HashFileHeader::read_from(&mut rest)?;
+        match header.version {
+            HASH_FILE_VERSION => parse_v3(data, header, rest),
+            _ => parse_v1(header, rest),
+        }
+    }
+
+    /// Writes the file in version 3 layout.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be written or an entry does not fit the header; see
+    /// `HashFileWriter::write_entry`.
+    pub fn write(&self, path: &str) -> Result<(), HashassinError> {
+        let file = File::create(path).map_err(|e| {
+            HashassinError::CreateFile(format!("Error creating output file: {e:?}"))
+        })?;
+        let mut writer = HashFileWriter::new(BufWriter::new(file), self.header.clone())?;
+        for entry in &self.entries {
+            writer.write_entry(entry)?;
+        }
+        writer.finish().map(drop)
+    }
+}
+
+/// Writes a version 3 hash file one entry at a time, counting the entries and computing the checksum of the
+/// trailer on the way.
+#[derive(Debug)]
+pub struct HashFileWriter<W: Write> {
+    out: W,
+    header: HashFileHeader,
+    hash_len: Option<usize>,
+    checksum: crc32fast::Hasher,
+    entry_count: u32,
+}
+
+impl<W: Write> HashFileWriter<W> {
+    /// Writes the header.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the algorithm cannot be stored with the salt placement of the header, or the
+    /// header cannot be written.
+    pub fn new(out: W, header: HashFileHeader) -> Result<Self, HashassinError> {
+        let hash_len = header.hash_len()?;
+        let mut writer = HashFileWriter {
+            out,
+            hash_len,
+            checksum: crc32fast::Hasher::new(),
+            entry_count: 0,
+            header,
+        };
+        let mut bytes = Vec::new();
+        writer.header.write_to(&mut bytes)?;
+        writer.write(&bytes)?;
+        Ok(writer)
+    }
+
+    /// Writes an entry.
+    ///
+    /// # Errors
+    ///
+    /// Returns `HashassinError::InvalidInput` if the entry has a salt or label the header does not provide for,
+    /// a salt, label or encoded hash longer than 255 bytes, or a digest of the wrong length.
+    pub fn write_entry(&mut self, entry: &HashEntry) -> Result<(), HashassinError> {
+        let too_long = |what: &str| {
+            HashassinError::InvalidInput(format!("{what} longer than 255 bytes cannot be stored"))
+        };
+        let mut data = vec![entry.password_len.unwrap_or(UNKNOWN_PASSWORD_LENGTH)];
+        if self.header.placement.is_some() {
+            data.push(u8::try_from(entry.salt.len()).map_err(|_| too_long("Salt"))?);
+            data.extend_from_slice(&entry.salt);
+        } else if !entry.salt.is_empty() {
+            return Err(HashassinError::InvalidInput(
+                "Salted entry in an unsalted hash file".to_string(),
+            ));
+        }
+        let label = entry.label.as_deref().unwrap_or_default();
+        if self.header.labeled {
+            data.push(u8::try_from(label.len()).map_err(|_| too_long("Label"))?);
+            data.extend_from_slice(label.as_bytes());
+        } else if !label.is_empty() {
+            return Err(HashassinError::InvalidInput(
+                "Labeled entry in an unlabeled hash file".to_string(),
+            ));
+        }
+        match self.hash_len {
+            Some(hash_len) if entry.hash.len() != hash_len => {
+                return Err(HashassinError::InvalidHashLength(format!(
+                    "{} hashes are {hash_len} bytes, found {}",
+                    self.header.algorithm.name(),
+                    entry.hash.len()
+                )));
+            }
+            Some(_) => (),
+            None => data.push(u8::try_from(entry.hash.len()).map_err(|_| too_long("Hash"))?),
+        }
+        data.extend_from_slice(&entry.hash);
+
+        self.entry_count += 1;
+        self.write(&data)
+    }
+
+    /// Writes the ENTRY COUNT and CHECKSUM trailer and flushes the output.
+    ///
+    /// # Errors
+    ///
+    /// Returns `HashassinError::WriteError` if the trailer cannot be written.
+    pub fn finish(mut self) -> Result<W, HashassinError> {
+        let entry_count = self.entry_count.to_be_bytes();
+        self.write(&entry_count)?;
+        let checksum = self.checksum.clone().finalize().to_be_bytes();
+        self.out
+            .write_all(&checksum)
+            .and_then(|()| self.out.flush())
+            .map_err(|e| HashassinError::WriteError(e.to_string()))?;
+        Ok(self.out)
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<(), HashassinError> {
+        self.checksum.update(data);
+        self.out
+            .write_all(data)
+            .map_err(|e| HashassinError::WriteError(e.to_string()))
+    }
+}
+
+/// Reads the entries of a version 1 or salted version 2 file, everything after its header.
+fn parse_v1(header: HashFileHeader, mut rest: &[u8]) -> Result<HashFile, HashassinError> {
+    let hash_len = header.hash_len()?;
+
+    // A HASH LENGTH byte would come before the prefix
+    if hash_len.is_none()
+        && header.password_len.is_some()
+        && rest.starts_with(SCRYPT_PHC_PREFIX.as_bytes())
+    {
+        return parse_v1_unprefixed_scrypt(header, rest);
+    }
+
+    let mut entries = Vec::new();
+    while !rest.is_empty() {
+        let password_len = match header.password_len {
+            None => Some(read_u8(&mut rest)?).filter(|&len| len != 0),
+            shared => shared,
+        };
+        let salt = match header.placement {
+            Some(_) => {
+                let salt_len = read_u8(&mut rest)?;
+                read_vec(&mut rest, salt_len.into())?
+            }
+            None => Vec::new(),
+        };
+        let hash = match hash_len {
+            Some(hash_len) => read_vec(&mut rest, hash_len)?,
+            None => {
+                let hash_len = read_u8(&mut rest)?;
+                read_vec(&mut rest, hash_len.into())?
+            }
+        };
+        entries.push(HashEntry {
+            salt,
+            ..HashEntry::new(password_len, hash)
+        });
+    }
+    Ok(HashFile { header, entries })
+}
+
+/// Reads the entries of a version 1 scrypt file that concatenates its PHC strings.
+fn parse_v1_unprefixed_scrypt(
+    header: HashFileHeader,
+    rest: &[u8],
+) -> Result<HashFile, HashassinError> {
+    let text = std::str::from_utf8(rest)
+        .map_err(|_| invalid("scrypt hashes are not valid UTF-8".to_string()))?;
+    let entries = text
+        .split(SCRYPT_PHC_PREFIX)
+        .skip(1)
+        .map(|hash| {
+            HashEntry::new(
+                header.password_len,
+                format!("{SCRYPT_PHC_PREFIX}{hash}").into_bytes(),
+            )
+        })
+        .collect();
+    Ok(HashFile { header, entries })
+}
+
+/// Reads the rest of a version 3 file, everything after its header.
+fn parse_v3(data: &[u8], header: HashFileHeader, rest: &[u8]) -> Result<HashFile, HashassinError> {
+    let hash_len = header.hash_len()?;
+
+    if rest.len() < TRAILER_LEN {
+        return Err(truncated());
+    }
+    let (mut rest, trailer) = rest.split_at(rest.len() - TRAILER_LEN);
+    let (entry_count, checksum) = trailer.split_at(4);
+    let entry_count = u32::from_be_bytes([
+        entry_count[0],
+        entry_count[1],
+        entry_count[2],
+        entry_count[3],
+    ]);
+    let checksum = u32::from_be_bytes([checksum[0], checksum[1], checksum[2], checksum[3]]);
+    if crc32fast::hash(&data[..data.len() - 4]) != checksum {
+        return Err(invalid("checksum mismatch".to_string()));
+    }
+
+    let mut entries = Vec::new();
+    while !rest.is_empty() {
+        let password_len = Some(read_u8(&mut rest)?).filter(|&len| len != UNKNOWN_PASSWORD_LENGTH);
+        let salt = match header.placement {
+            Some(_) => {
+                let salt_len = read_u8(&mut rest)?;
+                read_vec(&mut rest, salt_len.into())?
+            }
+            None => Vec::new(),
+        };
+        let label = match header.labeled {
+            true => {
+                let label_len = read_u8(&mut rest)?;
+                let label = read_vec(&mut rest, label_len.into())?;
+                Some(String::from_utf8_lossy(&label).into_owned()).filter(|label| !label.is_empty())
+            }
+            false => None,
+        };
+        let hash = match hash_len {
+            Some(hash_len) => read_vec(&mut rest, hash_len)?,
+            None => {
+                let hash_len = read_u8(&mut rest)?;
+                read_vec(&mut rest, hash_len.into())?
+            }
+        };
+        entries.push(HashEntry {
+            password_len,
+            salt,
+            label,
+            hash,
+        });
+    }
+    if entries.len() != entry_count as usize {
+        return Err(invalid(format!(
+            "entry count {entry_count} does not match the {} entries",
+            entries.len()
+        )));
+    }
+    Ok(HashFile { header, entries })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{hash, salt::SaltPlacement};
+
+    /// Message of the `InvalidFormat` error `data` is rejected with.
+    fn format_error(data: &[u8]) -> String {
+        match HashFile::parse(data) {
+            Err(HashassinError::InvalidFormat(msg)) => msg,
+            other => panic!("expected an invalid format, got {other:?}"),
+        }
+    }
+
+    /// A salted, labeled version 3 file of two sha256 entries.
+    fn version_3_file() -> Result<(Vec<HashEntry>, Vec<u8>), HashassinError> {
+        let header =
+            HashFileHeader::new(hash::lookup("sha256")?, Some(SaltPlacement::Suffix), true);
+        let entries = vec![
+            HashEntry {
+                password_len: Some(4),
+                salt: b"salt".to_vec(),
+                label: Some("alice".to_string()),
+                hash: vec![0xab; 32],
+            },
+            HashEntry {
+                password_len: None,
+                salt: Vec::new(),
+                label: None,
+                hash: vec![0xcd; 32],
+            },
+        ];
+        let mut writer = HashFileWriter::new(Vec::new(), header)?;
+        for entry in &entries {
+            writer.write_entry(entry)?;
+        }
+        Ok((entries, writer.finish()?))
+    }
+
+    #[test]
+    fn reads_version_1_sample() -> Result<(), HashassinError> {
+        let file = HashFile::parse(include_bytes!("../../sample_outputs/100-sha256.hashes"))?;
+        assert_eq!(file.header.version, 1);
+        assert_eq!(file.header.algorithm.name(), "sha256");
+        assert_eq!(file.header.password_len, Some(4));
+        assert_eq!(file.entries.len(), 100);
+        assert!(file.entries.iter().all(|e| e.password_len == Some(4)));
+        assert_eq!(hex::encode(&file.entries[0].hash[..4]), "ef6fd4ec");
+        Ok(())
+    }
+
+    #[test]
+    fn reads_version_1_scrypt_sample_without_hash_lengths() -> Result<(), HashassinError> {
+        let file = HashFile::parse(include_bytes!("../../sample_outputs/100-scrypt.hashes"))?;
+        assert_eq!(file.entries.len(), 100);
+        for entry in &file.entries {
+            assert!(entry.hash.starts_with(SCRYPT_PHC_PREFIX.as_bytes()));
+            assert!(
+                file.header
+                    .algorithm
+                    .recognizes(&String::from_utf8_lossy(&entry.hash))
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn reads_version_1_variable_lengths() -> Result<(), HashassinError> {
+        let mut data = vec![1, 3];
+        data.extend_from_slice(b"md5");
+        data.push(crate::VARIABLE_PASSWORD_LENGTH);
+        data.push(5);
+        data.extend_from_slice(&[0x11; 16]);
+        // Imported entries record a length of 0
+        data.push(0);
+        data.extend_from_slice(&[0x22; 16]);
+
+        let file = HashFile::parse(&data)?;
+        assert_eq!(file.header.password_len, None);
+        assert_eq!(
+            file.entries,
+            vec![
+                HashEntry::new(Some(5), vec![0x11; 16]),
+                HashEntry::new(None, vec![0x22; 16]),
+            ]
+        );
+
+        data.pop();
+        assert_eq!(format_error(&data), "truncated file");
+        Ok(())
+    }
+
+    #[test]
+    fn reads_salted_version_2() -> Result<(), HashassinError> {
+        let algorithm = hash::lookup("sha256")?;
+        let digest = SaltPlacement::Prefix.hash(algorithm.as_ref(), b"salt", b"pass")?;
+        let mut data = vec![2, 6];
+        data.extend_from_slice(b"sha256");
+        data.push(4);
+        data.push(SaltPlacement::Prefix.to_byte());
+        data.push(4);
+        data.extend_from_slice(b"salt");
+        data.extend_from_slice(&digest);
+
+        let file = HashFile::parse(&data)?;
+        assert_eq!(file.header.version, 2);
+        assert_eq!(file.header.placement, Some(SaltPlacement::Prefix));
+        assert_eq!(file.entries.len(), 1);
+        assert_eq!(file.entries[0].password_len, Some(4));
+        assert_eq!(file.entries[0].salt, b"salt");
+        assert_eq!(file.entries[0].hash, digest);
+
+        data[9] = 9;
+        assert_eq!(format_error(&data), "unknown salt placement 9");
+        Ok(())
+    }
+
+    #[test]
+    fn round_trips_version_3() -> Result<(), HashassinError> {
+        let (entries, data) = version_3_file()?;
+        let file = HashFile::parse(&data)?;
+        assert_eq!(file.header.version, HASH_FILE_VERSION);
+        assert_eq!(file.header.placement, Some(SaltPlacement::Suffix));
+        assert!(file.header.labeled);
+        assert_eq!(file.entries, entries);
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_damaged_version_3() -> Result<(), HashassinError> {
+        let (_, data) = version_3_file()?;
+
+        let mut corrupted = data.clone();
+        corrupted[20] ^= 1;
+        assert_eq!(format_error(&corrupted), "checksum mismatch");
+
+        // A wrong entry count with a checksum that matches it
+        let mut miscounted = data.clone();
+        let count_at = data.len() - TRAILER_LEN;
+        miscounted[count_at + 3] = 3;
+        let checksum = crc32fast::hash(&miscounted[..data.len() - 4]).to_be_bytes();
+        miscounted[data.len() - 4..].copy_from_slice(&checksum);
+        assert_eq!(
+            format_error(&miscounted),
+            "entry count 3 does not match the 2 entries"
+        );
+
+        for len in 0..data.len() {
+            assert!(HashFile::parse(&data[..len]).is_err(), "{len} bytes");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn writer_rejects_entries_the_header_does_not_provide_for() -> Result<(), HashassinError> {
+        let header = HashFileHeader::new(hash::lookup("md5")?, None, false);
+        let mut writer = HashFileWriter::new(Vec::new(), header)?;
+        let salted = HashEntry {
+            salt: b"salt".to_vec(),
+            ..HashEntry::new(None, vec![0; 16])
+        };
+        assert!(writer.write_entry(&salted).is_err());
+        assert!(
+            writer
+                .write_entry(&HashEntry::new(None, vec![0; 20]))
+                .is_err()
+        );
+        Ok(())
+    }
+}
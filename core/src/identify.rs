@@ -0,0 +1,246 @@
+use std::{
+    fmt::Display,
+    io::{self, BufRead, BufReader},
+};
+
+use crate::{HashassinError, hash, utils};
+
+/// Encoded hash prefixes of schemes Hashassin cannot compute.
+const UNSUPPORTED_PREFIXES: [(&str, &str); 10] = [
+    ("$y$", "yescrypt"),
+    ("$gy$", "gost-yescrypt"),
+    ("$7$", "scrypt (crypt)"),
+    ("$argon2d$", "argon2d"),
+    ("$pbkdf2$", "pbkdf2-sha1"),
+    ("$pbkdf2-sha1$", "pbkdf2-sha1"),
+    ("$apr1$", "apr1 (Apache md5crypt)"),
+    ("$sha1$", "sha1crypt"),
+    ("$P$", "phpass"),
+    ("$H$", "phpass"),
+];
+
+/// Raw digest lengths in bytes of common algorithms Hashassin cannot compute.
+const UNSUPPORTED_DIGESTS: [(usize, &str); 4] = [
+    (4, "crc32"),
+    (8, "mysql323"),
+    (16, "md4"),
+    (20, "ripemd160"),
+];
+
+/// LM hash of an empty password half, the second half of the LM hash of every password of at most 7
+/// characters.
+const LM_EMPTY_HALF: &str = "aad3b435b51404ee";
+
+/// How Hashassin can crack hashes of a candidate algorithm.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Support {
+    /// Unsalted with a fixed digest length: rainbow tables and wordlists.
+    RainbowTable,
+    /// Salted or encoded: wordlists only.
+    Wordlist,
+    /// Recognized, but not implemented.
+    Unsupported,
+}
+
+impl Display for Support {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Support::RainbowTable => write!(f, "crack, crack-wordlist"),
+            Support::Wordlist => write!(f, "crack-wordlist"),
+            Support::Unsupported => write!(f, "not supported"),
+        }
+    }
+}
+
+/// An algorithm that may have produced a hash.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Candidate {
+    /// Canonical algorithm name, usable as `--algorithm` unless unsupported.
+    pub algorithm: String,
+    pub support: Support,
+}
+
+impl Candidate {
+    fn supported(algorithm: &dyn hash::HashAlgorithm) -> Self {
+        Candidate {
+            algorithm: algorithm.name().to_string(),
+            support: if algorithm.fixed_digest_len().is_ok() {
+                Support::RainbowTable
+            } else {
+                Support::Wordlist
+            },
+        }
+    }
+
+    fn unsupported(algorithm: &str) -> Self {
+        Candidate {
+            algorithm: algorithm.to_string(),
+            support: Support::Unsupported,
+        }
+    }
+}
+
+/// Lists the algorithms that may have produced a hash, most likely first.
+///
+/// Encoded hashes, such as PHC strings (`$argon2id$...`) and crypt strings (`$6$...`), are identified by their
+/// prefix. Hex digests are identified by their length, which is ambiguous: a 32 character digest may be MD5,
+/// NTLM or LM alike. LM is listed first when the digest ends with the LM hash of an empty half, as it does for
+/// passwords of at most 7 characters.
+///
+/// Returns no candidates if the hash is neither encoded nor hex.
+pub fn identify_hash(hash: &str) -> Vec<Candidate> {
+    let hash = hash.trim();
+
+    if hash.starts_with('$') {
+        let mut candidates: Vec<Candidate> = hash::registry()
+            .algorithms()
+            .filter(|algorithm| algorithm.recognizes(hash))
+            .map(|algorithm| Candidate::supported(algorithm.as_ref()))
+            .collect();
+        candidates.extend(
+            UNSUPPORTED_PREFIXES
+                .iter()
+                .filter(|(prefix, _)| hash.starts_with(prefix))
+                .map(|(_, name)| Candidate::unsupported(name)),
+        );
+        return candidates;
+    }
+
+    // MySQL 4.1+ PASSWORD(): '*' followed by an uppercase SHA1(SHA1(password)) in hex
+    if let Some(digest) = hash.strip_prefix('*') {
+        if digest.len() == 40 && is_hex(digest) {
+            return vec![Candidate::unsupported("mysql41")];
+        }
+        return Vec::new();
+    }
+
+    if hash.is_empty() || !hash.len().is_multiple_of(2) || !is_hex(hash) {
+        return Vec::new();
+    }
+    let digest_len = hash.len() / 2;
+
+    let mut candidates: Vec<Candidate> = hash::registry()
+        .algorithms()
+        .filter(|algorithm| algorithm.fixed_digest_len().ok() == Some(digest_len))
+        .map(|algorithm| Candidate::supported(algorithm.as_ref()))
+        .collect();
+    // BLAKE3 produces any length; only the 32-byte default is registered by name
+    if let Some(blake3) = hash::registry().get(&format!("blake3_{}", digest_len * 8))
+        && candidates.iter().all(|c| c.algorithm != blake3.name())
+    {
+        candidates.push(Candidate::supported(blake3.as_ref()));
+    }
+    candidates.extend(
+        UNSUPPORTED_DIGESTS
+            .iter()
+            .filter(|(len, _)| *len == digest_len)
+            .map(|(_, name)| Candidate::unsupported(name)),
+    );
+
+    if hash.to_lowercase().ends_with(LM_EMPTY_HALF)
+        && let Some(lm) = candidates.iter().position(|c| c.algorithm == "lm")
+    {
+        let lm = candidates.remove(lm);
+        candidates.insert(0, lm);
+    }
+    candidates
+}
+
+fn is_hex(s: &str) -> bool {
+    s.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// Prints the candidate algorithms of every hash, as the hash followed by one indented
+/// `ALGORITHM<TAB>CRACK WITH` line per candidate, or `unknown`.
+///
+/// # Arguments
+///
+/// * `hashes` - Hashes given directly.
+/// * `in_file` - Optional file of additional hashes, one per line. Blank lines are skipped.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read or no hash is given.
+pub fn identify_hashes(hashes: &[String], in_file: Option<&str>) -> Result<(), HashassinError> {
+    let mut hashes = hashes.to_vec();
+    if let Some(in_file) = in_file {
+        let reader = BufReader::new(utils::open_file(in_file)?);
+        let lines = reader
+            .lines()
+            .collect::<io::Result<Vec<String>>>()
+            .map_err(|e| HashassinError::FileRead(e.to_string()))?;
+        hashes.extend(lines.into_iter().filter(|line| !line.trim().is_empty()));
+    }
+    if hashes.is_empty() {
+        return Err(HashassinError::InvalidInput(
+            "No hashes to identify".to_string(),
+        ));
+    }
+
+    for hash in &hashes {
+        println!("{}", hash.trim());
+        let candidates = identify_hash(hash);
+        if candidates.is_empty() {
+            println!("  unknown");
+        }
+        for candidate in candidates {
+            println!("  {}\t{}", candidate.algorithm, candidate.support);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(hash: &str) -> Vec<String> {
+        identify_hash(hash)
+            .into_iter()
+            .map(|c| c.algorithm)
+            .collect()
+    }
+
+    #[test]
+    fn identifies_digests_by_length() {
+        let md5 = names("900150983cd24fb0d6963f7d28e17f72");
+        assert_eq!(md5[0], "md5");
+        assert!(md5.contains(&"ntlm".to_string()) && md5.contains(&"md4".to_string()));
+        assert_eq!(
+            identify_hash(&"ab".repeat(32))[0],
+            Candidate {
+                algorithm: "sha256".to_string(),
+                support: Support::RainbowTable,
+            }
+        );
+        assert!(names(&"ab".repeat(24)).contains(&"blake3_192".to_string()));
+    }
+
+    #[test]
+    fn lists_lm_first_for_short_passwords() {
+        assert_eq!(names("E52CAC67419A9A22AAD3B435B51404EE")[0], "lm");
+    }
+
+    #[test]
+    fn identifies_encoded_hashes_by_prefix() -> Result<(), HashassinError> {
+        let sha512crypt = hash::lookup("sha512crypt:rounds=1000")?.hash(b"x")?;
+        let candidates = identify_hash(&String::from_utf8_lossy(&sha512crypt));
+        assert_eq!(
+            candidates,
+            [Candidate {
+                algorithm: "sha512crypt".to_string(),
+                support: Support::Wordlist,
+            }]
+        );
+        assert_eq!(names("$y$j9T$salt$hash"), ["yescrypt"]);
+        assert_eq!(names(&format!("*{}", "A".repeat(40))), ["mysql41"]);
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_other_strings() {
+        for hash in ["", "abc", "xyz1", "*abc", "hello world"] {
+            assert!(identify_hash(hash).is_empty(), "{hash:?}");
+        }
+    }
+}
@@ -0,0 +1,504 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    io::{BufRead, BufReader},
+    str::FromStr,
+    sync::Arc,
+};
+
+use crate::{
+    HashassinError,
+    hash::{self, HashAlgorithm},
+    hash_file::{HashEntry, HashFile},
+    salt::SaltPlacement,
+    table::HashFileHeader,
+    utils,
+};
+
+/// Text formats of hash lists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HashFormat {
+    /// One hash per line.
+    #[default]
+    Hex,
+    /// `user:hash` lines; further `:` separated fields are ignored.
+    User,
+    /// hashcat hash lists: one hash per line, or `hash:salt` for salted modes.
+    Hashcat,
+    /// John the Ripper password files: `user:hash` or bare hashes, optionally tagged (`$NT$...`,
+    /// `$dynamic_0$...`), and pwdump `user:rid:lm:nt:::` lines.
+    John,
+}
+
+impl FromStr for HashFormat {
+    type Err = HashassinError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "hex" => Ok(HashFormat::Hex),
+            "user" => Ok(HashFormat::User),
+            "hashcat" => Ok(HashFormat::Hashcat),
+            "john" => Ok(HashFormat::John),
+            _ => Err(HashassinError::InvalidInput(format!(
+                "Unknown hash format {s:?}, expected hex, user, hashcat or john"
+            ))),
+        }
+    }
+}
+
+impl Display for HashFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HashFormat::Hex => write!(f, "hex"),
+            HashFormat::User => write!(f, "user"),
+            HashFormat::Hashcat => write!(f, "hashcat"),
+            HashFormat::John => write!(f, "john"),
+        }
+    }
+}
+
+/// Tag John the Ripper puts in front of raw hashes of an algorithm.
+fn john_tag(algorithm: &str) -> Option<&'static str> {
+    match algorithm {
+        "md5" => Some("$dynamic_0$"),
+        "sha1" => Some("$dynamic_26$"),
+        "sha224" => Some("$SHA224$"),
+        "sha256" => Some("$SHA256$"),
+        "sha384" => Some("$SHA384$"),
+        "sha512" => Some("$SHA512$"),
+        "ntlm" => Some("$NT$"),
+        "lm" => Some("$LM$"),
+        _ => None,
+    }
+}
+
+/// Takes the username, the hash and, for salted hashcat lines, the salt out of a line.
+fn split_line<'a>(
+    line: &'a str,
+    format: HashFormat,
+    algorithm: &dyn HashAlgorithm,
+    salted: bool,
+) -> (Option<&'a str>, &'a str, Option<&'a str>) {
+    match format {
+        HashFormat::Hex => (None, line, None),
+        HashFormat::User => {
+            let mut fields = line.split(':');
+            let user = fields.next();
+            (user, fields.next().unwrap_or_default(), None)
+        }
+        HashFormat::Hashcat if salted => match line.split_once(':') {
+            Some((hash, salt)) => (None, hash, Some(salt)),
+            None => (None, line, None),
+        },
+        HashFormat::Hashcat => (None, line, None),
+        HashFormat::John => {
+            let fields: Vec<&str> = line.split(':').collect();
+            let user = (fields.len() > 1).then_some(fields[0]);
+            let pwdump = fields.len() >= 4 && fields[1].bytes().all(|b| b.is_ascii_digit());
+            let hash = match algorithm.name() {
+                "lm" if pwdump => fields[2],
+                "ntlm" if pwdump => fields[3],
+                _ if fields.len() > 1 => fields[1],
+                _ => line,
+            };
+            let hash = john_tag(algorithm.name())
+                .and_then(|tag| hash.strip_prefix(tag))
+                .unwrap_or(hash);
+            (user, hash, None)
+        }
+    }
+}
+
+/// Decodes a hashcat salt, either literal or written as `$HEX[...]`.
+fn decode_salt(salt: &str) -> Result<Vec<u8>, String> {
+    match salt
+        .strip_prefix("$HEX[")
+        .and_then(|salt| salt.strip_suffix(']'))
+    {
+        Some(salt) => hex::decode(salt).map_err(|e| format!("invalid $HEX salt: {e}")),
+        None => Ok(salt.as_bytes().to_vec()),
+    }
+}
+
+/// Writes imported hashes into a hash file, salted if `placement` is set and labeled if any entry has a label.
+fn write_hash_file(
+    out_file: &str,
+    algorithm: Arc<dyn HashAlgorithm>,
+    placement: Option<SaltPlacement>,
+    entries: Vec<HashEntry>,
+) -> Result<(), HashassinError> {
+    let labeled = entries.iter().any(|entry| entry.label.is_some());
+    HashFile {
+        header: HashFileHeader::new(algorithm, placement, labeled),
+        entries,
+    }
+    .write(out_file)
+}
+
+/// An imported entry, whose password length is unknown.
+fn imported(label: Option<&str>, salt: Vec<u8>, hash: Vec<u8>) -> HashEntry {
+    HashEntry {
+        password_len: None,
+        salt,
+        label: label.filter(|label| !label.is_empty()).map(str::to_string),
+        hash,
+    }
+}
+
+/// Reads a text list of hashes found in the wild into a hash file that `crack`, `crack-wordlist` and the
+/// server can read.
+///
+/// Raw digests are given in hex and must have the digest length of `algorithm`. Encoded hashes, such as crypt
+/// strings, must be recognized as produced by `algorithm`. Blank lines and lines starting with `#` are skipped.
+/// Usernames of the user and John formats are kept as entry labels; password lengths are unknown.
+/// With `placement`, hashcat `hash:salt` lines are written into a salted hash file; the salt is literal text
+/// or `$HEX[...]`.
+///
+/// # Arguments
+///
+/// * `in_file` - The path to the hash list.
+/// * `out_file` - The path to the hash file to write.
+/// * `algorithm` - The algorithm of the hashes.
+/// * `format` - The format of the hash list.
+/// * `placement` - Optional salt placement of salted hashcat lines.
+///
+/// # Errors
+///
+/// Returns an error if a file cannot be read or written, a line holds no valid hash of `algorithm`, the file
+/// holds no hashes, or `placement` is used with another format than hashcat or an algorithm that cannot be
+/// salted that way.
+pub fn import_hashes(
+    in_file: &str,
+    out_file: &str,
+    algorithm: Arc<dyn HashAlgorithm>,
+    format: HashFormat,
+    placement: Option<SaltPlacement>,
+) -> Result<(), HashassinError> {
+    let digest_len = match placement {
+        Some(_) if format != HashFormat::Hashcat => {
+            return Err(HashassinError::InvalidInput(
+                "Salted hashes can only be imported from the hashcat format".to_string(),
+            ));
+        }
+        Some(placement) => Some(placement.digest_len(algorithm.as_ref())?),
+        None if algorithm.digest_len().is_none() => None,
+        None => Some(algorithm.fixed_digest_len()?),
+    };
+
+    let reader = BufReader::new(utils::open_file(in_file)?);
+    let lines = reader
+        .lines()
+        .collect::<Result<Vec<String>, _>>()
+        .map_err(|e| HashassinError::FileRead(e.to_string()))?;
+
+    let mut entries = Vec::new();
+    for (number, line) in lines.iter().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let at = |msg: String| format!("{in_file} line {}: {msg}", number + 1);
+        let (user, hash, salt) = split_line(line, format, algorithm.as_ref(), placement.is_some());
+
+        let Some(digest_len) = digest_len else {
+            if !algorithm.recognizes(hash) {
+                return Err(HashassinError::InvalidFormat(at(format!(
+                    "not a {} hash",
+                    algorithm.name()
+                ))));
+            }
+            if hash.len() > u8::MAX as usize {
+                return Err(HashassinError::InvalidFormat(at(
+                    "hash longer than 255 bytes".to_string(),
+                )));
+            }
+            entries.push(imported(user, Vec::new(), hash.as_bytes().to_vec()));
+            continue;
+        };
+
+        let digest = hex::decode(hash)
+            .map_err(|e| HashassinError::InvalidFormat(at(format!("invalid hex digest: {e}"))))?;
+        if digest.len() != digest_len {
+            return Err(HashassinError::InvalidHashLength(at(format!(
+                "{} digests are {digest_len} bytes, found {}",
+                algorithm.name(),
+                digest.len()
+            ))));
+        }
+        let salt = match (placement, salt) {
+            (Some(_), Some(salt)) => {
+                decode_salt(salt).map_err(|e| HashassinError::InvalidFormat(at(e)))?
+            }
+            (Some(_), None) => {
+                return Err(HashassinError::InvalidFormat(at(
+                    "missing salt, expected hash:salt".to_string(),
+                )));
+            }
+            (None, _) => Vec::new(),
+        };
+        if salt.len() > u8::MAX as usize {
+            return Err(HashassinError::InvalidFormat(at(
+                "salt longer than 255 bytes".to_string(),
+            )));
+        }
+        entries.push(imported(user, salt, digest));
+    }
+
+    if entries.is_empty() {
+        return Err(HashassinError::InvalidInput(format!(
+            "No hashes in {in_file}"
+        )));
+    }
+    let count = entries.len();
+    write_hash_file(out_file, algorithm.clone(), placement, entries)?;
+    println!(
+        "Imported {} {} hashes into {out_file}",
+        count,
+        algorithm.name()
+    );
+    Ok(())
+}
+
+/// Reads the password hashes of a shadow-style file, such as `/etc/shadow`, into a hash file.
+///
+/// Every line is `user:hash:...`; the users are kept as entry labels. Locked accounts and accounts without a password (hashes starting with `!` or
+/// `*`, or empty) are skipped. The algorithm of every hash is recognized by its prefix, for example `$1$`
+/// (md5crypt), `$5$` (sha256crypt), `$6$` (sha512crypt) or `$2b$` (bcrypt). A hash file holds a single
+/// algorithm, so files mixing several need `algorithm` to pick the hashes to import.
+///
+/// # Arguments
+///
+/// * `in_file` - The path to the shadow file.
+/// * `out_file` - The path to the hash file to write.
+/// * `algorithm` - Optional algorithm; hashes of other algorithms are skipped.
+///
+/// # Errors
+///
+/// Returns an error if a file cannot be read or written, no hash is found, or hashes of several algorithms are
+/// found without `algorithm`.
+pub fn import_shadow(
+    in_file: &str,
+    out_file: &str,
+    algorithm: Option<Arc<dyn HashAlgorithm>>,
+) -> Result<(), HashassinError> {
+    let reader = BufReader::new(utils::open_file(in_file)?);
+    let lines = reader
+        .lines()
+        .collect::<Result<Vec<String>, _>>()
+        .map_err(|e| HashassinError::FileRead(e.to_string()))?;
+
+    let mut by_algorithm: BTreeMap<String, (Arc<dyn HashAlgorithm>, Vec<HashEntry>)> =
+        BTreeMap::new();
+    let mut locked = 0;
+    let mut unknown = 0;
+    for line in &lines {
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut fields = line.split(':');
+        let user = fields.next().unwrap_or_default();
+        let hash = fields.next().unwrap_or_default();
+        if hash.is_empty() || hash.starts_with('!') || hash.starts_with('*') {
+            locked += 1;
+            continue;
+        }
+        let Some(found) = hash::registry()
+            .algorithms()
+            .find(|algorithm| algorithm.recognizes(hash))
+        else {
+            unknown += 1;
+            continue;
+        };
+        by_algorithm
+            .entry(found.name().to_string())
+            .or_insert_with(|| (found.clone(), Vec::new()))
+            .1
+            .push(imported(Some(user), Vec::new(), hash.as_bytes().to_vec()));
+    }
+
+    if locked > 0 {
+        println!("Skipped {locked} locked accounts or accounts without a password");
+    }
+    if unknown > 0 {
+        println!("Skipped {unknown} hashes of unsupported algorithms");
+    }
+
+    let (algorithm, entries) = match algorithm {
+        Some(algorithm) => by_algorithm.remove(algorithm.name()).ok_or_else(|| {
+            HashassinError::InvalidInput(format!("No {} hashes in {in_file}", algorithm.name()))
+        })?,
+        None if by_algorithm.len() > 1 => {
+            let found: Vec<String> = by_algorithm
+                .iter()
+                .map(|(name, (_, hashes))| format!("{name}: {}", hashes.len()))
+                .collect();
+            return Err(HashassinError::InvalidInput(format!(
+                "{in_file} mixes several algorithms ({}), choose one with --algorithm",
+                found.join(", ")
+            )));
+        }
+        None => by_algorithm.into_values().next().ok_or_else(|| {
+            HashassinError::InvalidInput(format!("No supported hashes in {in_file}"))
+        })?,
+    };
+
+    if let Some(entry) = entries
+        .iter()
+        .find(|entry| entry.hash.len() > u8::MAX as usize)
+    {
+        return Err(HashassinError::InvalidInput(format!(
+            "Hash longer than 255 bytes: {}",
+            String::from_utf8_lossy(&entry.hash)
+        )));
+    }
+    let count = entries.len();
+    write_hash_file(out_file, algorithm.clone(), None, entries)?;
+    println!(
+        "Imported {} {} hashes into {out_file}",
+        count,
+        algorithm.name()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::TempFile;
+
+    /// Imports the hash list `text` and reads the hash file written.
+    fn import(
+        name: &str,
+        text: &str,
+        algorithm: &str,
+        format: HashFormat,
+        placement: Option<SaltPlacement>,
+    ) -> Result<HashFile, HashassinError> {
+        let list = TempFile::new(&format!("{name}-list"), text)?;
+        let out = TempFile::new(&format!("{name}-out"), "")?;
+        import_hashes(
+            list.path(),
+            out.path(),
+            hash::lookup(algorithm)?,
+            format,
+            placement,
+        )?;
+        HashFile::read(out.path())
+    }
+
+    /// Labels of the entries of a file.
+    fn labels(file: &HashFile) -> Vec<Option<&str>> {
+        file.entries
+            .iter()
+            .map(|entry| entry.label.as_deref())
+            .collect()
+    }
+
+    const MD5_ABC: &str = "900150983cd24fb0d6963f7d28e17f72";
+    const NTLM_PASSWORD: &str = "8846f7eaee8fb117ad06bdd830b7586c";
+
+    #[test]
+    fn imports_user_lists_with_labels() -> Result<(), HashassinError> {
+        let text = format!("# dumped\nalice:{MD5_ABC}\n\n:{MD5_ABC}:extra\n");
+        let file = import("import-user", &text, "md5", HashFormat::User, None)?;
+        assert!(file.header.labeled);
+        assert_eq!(labels(&file), [Some("alice"), None]);
+        assert!(
+            file.entries
+                .iter()
+                .all(|entry| entry.password_len.is_none())
+        );
+        assert_eq!(hex::encode(&file.entries[0].hash), MD5_ABC);
+        Ok(())
+    }
+
+    #[test]
+    fn imports_john_and_pwdump_lines() -> Result<(), HashassinError> {
+        let text = format!(
+            "admin:500:aad3b435b51404eeaad3b435b51404ee:{NTLM_PASSWORD}:::\n$NT${NTLM_PASSWORD}\n"
+        );
+        let file = import("import-john", &text, "ntlm", HashFormat::John, None)?;
+        assert_eq!(labels(&file), [Some("admin"), None]);
+        assert!(
+            file.entries
+                .iter()
+                .all(|entry| hex::encode(&entry.hash) == NTLM_PASSWORD)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn imports_salted_hashcat_lines() -> Result<(), HashassinError> {
+        let text = format!("{MD5_ABC}:pepper\n{MD5_ABC}:$HEX[00ff]\n");
+        let placement = Some(SaltPlacement::Suffix);
+        let file = import(
+            "import-hashcat",
+            &text,
+            "md5",
+            HashFormat::Hashcat,
+            placement,
+        )?;
+        assert_eq!(file.header.placement, placement);
+        assert_eq!(file.entries[0].salt, b"pepper");
+        assert_eq!(file.entries[1].salt, [0x00, 0xff]);
+
+        assert!(
+            import(
+                "import-unsalted",
+                MD5_ABC,
+                "md5",
+                HashFormat::Hashcat,
+                placement
+            )
+            .is_err()
+        );
+        assert!(
+            import(
+                "import-user-salt",
+                MD5_ABC,
+                "md5",
+                HashFormat::User,
+                placement
+            )
+            .is_err()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_invalid_lists() {
+        for (name, text) in [
+            ("import-short", "900150983cd24fb0"),
+            ("import-not-hex", "zz0150983cd24fb0d6963f7d28e17f72"),
+            ("import-empty", "# nothing\n\n"),
+        ] {
+            assert!(
+                import(name, text, "md5", HashFormat::Hex, None).is_err(),
+                "{name}"
+            );
+        }
+    }
+
+    #[test]
+    fn imports_shadow_files_by_algorithm() -> Result<(), HashassinError> {
+        let md5crypt = hash::lookup("md5crypt")?.hash(b"hunter2")?;
+        let sha512crypt = hash::lookup("sha512crypt:rounds=1000")?.hash(b"hunter2")?;
+        let shadow = TempFile::new(
+            "import-shadow",
+            format!(
+                "root:{}:19000:0:99999:7:::\nbin:*:19000::::::\nnobody:!:19000::::::\nalice:{}:19000::::::\n",
+                String::from_utf8_lossy(&md5crypt),
+                String::from_utf8_lossy(&sha512crypt)
+            ),
+        )?;
+        let out = TempFile::new("import-shadow-out", "")?;
+        assert!(import_shadow(shadow.path(), out.path(), None).is_err());
+
+        import_shadow(shadow.path(), out.path(), Some(hash::lookup("md5crypt")?))?;
+        let file = HashFile::read(out.path())?;
+        assert_eq!(file.header.algorithm.name(), "md5crypt");
+        assert_eq!(labels(&file), [Some("root")]);
+        assert_eq!(file.entries[0].hash, md5crypt);
+        Ok(())
+    }
+}
@@ -0,0 +1,160 @@
+use std::str::FromStr;
+
+use rand::{Rng, distr::Distribution, distr::weighted::WeightedIndex};
+
+use crate::HashassinError;
+
+/// A probability distribution over password lengths.
+///
+/// Built either from an inclusive range where every length is equally likely,
+/// or from user supplied `length:weight` pairs such as `8:50,10:30,12:20`.
+#[derive(Debug, Clone)]
+pub struct LengthDistribution {
+    lengths: Vec<u8>,
+    weights: Vec<u64>,
+    index: WeightedIndex<u64>,
+}
+
+impl LengthDistribution {
+    /// Every length in `min..=max` is equally likely.
+    ///
+    /// # Errors
+    ///
+    /// Returns `HashassinError::InvalidInput` if `min` is greater than `max`.
+    pub fn uniform(min: u8, max: u8) -> Result<Self, HashassinError> {
+        if min > max {
+            return Err(HashassinError::InvalidInput(format!(
+                "Minimum length {min} is greater than maximum length {max}"
+            )));
+        }
+        Self::weighted((min..=max).map(|len| (len, 1)).collect())
+    }
+
+    /// Parses a comma separated list of `length:weight` pairs.
+    ///
+    /// Weights are relative, so `8:1,12:3` draws length 12 three times as often
+    /// as length 8.
+    ///
+    /// # Errors
+    ///
+    /// Returns `HashassinError::InvalidInput` if an entry is malformed or all
+    /// weights are zero.
+    pub fn parse(spec: &str) -> Result<Self, HashassinError> {
+        let pairs = spec
+            .split(',')
+            .map(|entry| {
+                let (len, weight) = entry.split_once(':').ok_or_else(|| {
+                    HashassinError::InvalidInput(format!(
+                        "Length weight {entry:?} must be written as length:weight"
+                    ))
+                })?;
+                let len = len.trim().parse::<u8>().map_err(|e| {
+                    HashassinError::InvalidInput(format!("Invalid length {len:?}: {e}"))
+                })?;
+                let weight = weight.trim().parse::<u64>().map_err(|e| {
+                    HashassinError::InvalidInput(format!("Invalid weight {weight:?}: {e}"))
+                })?;
+                Ok((len, weight))
+            })
+            .collect::<Result<Vec<_>, HashassinError>>()?;
+        Self::weighted(pairs)
+    }
+
+    fn weighted(pairs: Vec<(u8, u64)>) -> Result<Self, HashassinError> {
+        let (lengths, weights): (Vec<u8>, Vec<u64>) = pairs.into_iter().unzip();
+        let index = WeightedIndex::new(&weights)
+            .map_err(|e| HashassinError::InvalidInput(format!("Invalid length weights: {e}")))?;
+        Ok(LengthDistribution {
+            lengths,
+            weights,
+            index,
+        })
+    }
+
+    /// The longest length that can be drawn.
+    pub fn max(&self) -> u8 {
+        self.drawable().max().unwrap_or(0)
+    }
+
+    /// Draws a password length.
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> u8 {
+        self.lengths[self.index.sample(rng)]
+    }
+
+    /// The distribution conditioned on the lengths `keep` accepts, which keep their relative weights.
+    ///
+    /// # Errors
+    ///
+    /// Returns `HashassinError::InvalidInput` if `keep` rejects every length that can be drawn.
+    pub(crate) fn filter(&self, keep: impl Fn(u8) -> bool) -> Result<Self, HashassinError> {
+        Self::weighted(
+            self.lengths
+                .iter()
+                .zip(&self.weights)
+                .map(|(&len, &weight)| (len, if keep(len) { weight } else { 0 }))
+                .collect(),
+        )
+    }
+
+    /// The lengths with a nonzero weight.
+    fn drawable(&self) -> impl Iterator<Item = u8> + '_ {
+        self.lengths
+            .iter()
+            .zip(&self.weights)
+            .filter(|&(_, &weight)| weight > 0)
+            .map(|(&len, _)| len)
+    }
+}
+
+impl FromStr for LengthDistribution {
+    type Err = HashassinError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        LengthDistribution::parse(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    #[test]
+    fn uniform_draws_every_length_of_the_range() -> Result<(), HashassinError> {
+        let lengths = LengthDistribution::uniform(4, 6)?;
+        let mut rng = ChaCha20Rng::seed_from_u64(7);
+        let mut seen = [false; 3];
+        for _ in 0..300 {
+            let length = lengths.sample(&mut rng);
+            assert!((4..=6).contains(&length));
+            seen[length as usize - 4] = true;
+        }
+        assert_eq!(seen, [true; 3]);
+        assert_eq!(lengths.max(), 6);
+        assert!(LengthDistribution::uniform(6, 4).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn weights_are_relative() -> Result<(), HashassinError> {
+        let lengths: LengthDistribution = "8:1, 12:3, 20:0".parse()?;
+        let mut rng = ChaCha20Rng::seed_from_u64(7);
+        let twelves = (0..4000)
+            .map(|_| lengths.sample(&mut rng))
+            .inspect(|&length| assert!(length == 8 || length == 12))
+            .filter(|&length| length == 12)
+            .count();
+        assert!((2800..3200).contains(&twelves), "{twelves}");
+        // Lengths that are never drawn do not count
+        assert_eq!(lengths.max(), 12);
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_malformed_specs() {
+        for spec in ["", "8", "8:", "8:-1", "256:1", "x:1", "8:0,9:0"] {
+            assert!(LengthDistribution::parse(spec).is_err(), "{spec:?}");
+        }
+    }
+}
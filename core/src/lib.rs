@@ -1,19 +1,40 @@
 #![deny(clippy::unwrap_used, clippy::expect_used)]
 // Exposing generate_passsword
 mod algorithms;
+pub mod benchmark;
+pub mod charset;
+pub mod compose;
 pub mod crack;
+pub mod dictionary;
 pub mod dump_hashes;
 pub mod dump_rainbow_table;
 pub mod generate_hashes;
 pub mod generate_passwords;
 pub mod generate_rainbow_table;
 pub mod hash;
+pub mod hash_file;
+pub mod identify;
+pub mod import;
+pub mod lengths;
+pub mod markov;
+pub mod mask;
+pub mod output;
+pub mod passphrase;
+pub mod policy;
 mod radix_type;
 pub mod reduction;
+pub mod rules;
+pub mod salt;
+pub mod strength;
 pub mod table;
 pub mod utils;
 use thiserror::Error;
 
+/// Value of the PASSWORD LENGTH header byte of a hash file whose passwords have
+/// different lengths. Every entry of such a file starts with one byte holding the
+/// length of its password, followed by the hash.
+pub const VARIABLE_PASSWORD_LENGTH: u8 = 0;
+
 #[derive(Debug, Error)]
 pub enum MyError {
     #[error("file open error")]
@@ -41,6 +62,7 @@ pub enum MyError {
 /// - `SendError`: Channel communication failure.
 /// - `InvalidThreadCount`, `InvalidInput`, `InvalidHashLength`: Parameter validation errors.
 /// - `UnknownAlgorithm`: Unsupported or unrecognized hash algorithm.
+/// - `HashError`: A hash function rejected its input or parameters.
 /// - `CustomError`: General-purpose error for custom messages.
 #[derive(Debug, Error)]
 pub enum HashassinError {
@@ -82,4 +104,7 @@ pub enum HashassinError {
 
     #[error("Invalid hash length: {0}")]
     InvalidHashLength(String),
+
+    #[error("Hashing failed: {0}")]
+    HashError(String),
 }
@@ -0,0 +1,449 @@
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, BinaryHeap},
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Read, Write},
+};
+
+use rand::{Rng, distr::Distribution, distr::weighted::WeightedIndex};
+
+use crate::{HashassinError, policy::ByteClass, utils};
+
+/// Magic word at the start of every model file.
+const MAGIC: &[u8] = b"markovmodel";
+/// Model file format version.
+const VERSION: u8 = 1;
+/// Stand-in for the previous character at position 0. Not a printable character, so it never collides.
+const START: u8 = 0;
+
+/// Next-character statistics for one context.
+#[derive(Debug, Clone)]
+struct Transition {
+    chars: Vec<u8>,
+    counts: Vec<u64>,
+    weights: WeightedIndex<u64>,
+}
+
+impl Transition {
+    fn new(counts: &BTreeMap<u8, u64>) -> Option<Self> {
+        let (chars, counts): (Vec<u8>, Vec<u64>) = counts.iter().map(|(&c, &n)| (c, n)).unzip();
+        let weights = WeightedIndex::new(counts.iter().copied()).ok()?;
+        Some(Transition {
+            chars,
+            counts,
+            weights,
+        })
+    }
+
+    fn sample<R: Rng>(&self, rng: &mut R) -> u8 {
+        self.chars[self.weights.sample(rng)]
+    }
+
+    fn total(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+}
+
+/// A first-order, position-aware Markov model of passwords.
+///
+/// For every position the model records how often each character followed each previous character, plus
+/// how often each password length occurred. Sampling draws a length, then every character given its position
+/// and predecessor, so generated candidates follow the structure of the training corpus.
+#[derive(Debug, Clone)]
+pub struct MarkovModel {
+    /// Number of training passwords of every length, indexed by length.
+    lengths: Vec<u64>,
+    length_weights: WeightedIndex<u64>,
+    /// Statistics keyed by (position, previous character or `START`).
+    transitions: BTreeMap<(u8, u8), Transition>,
+    /// Character statistics of every position regardless of the previous character.
+    positions: Vec<Option<Transition>>,
+}
+
+impl MarkovModel {
+    /// Trains a model on a password list, one password per line.
+    ///
+    /// Passwords containing characters outside printable ASCII or longer than 255 characters are skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or holds no usable password.
+    pub fn train(in_file: &str) -> Result<Self, HashassinError> {
+        let reader = BufReader::new(utils::open_file(in_file)?);
+        let mut lengths = vec![0u64; u8::MAX as usize + 1];
+        let mut counts: BTreeMap<(u8, u8), BTreeMap<u8, u64>> = BTreeMap::new();
+        let mut skipped = 0;
+
+        for line in reader.lines() {
+            let password = line.map_err(|e| HashassinError::FileRead(e.to_string()))?;
+            if password.is_empty() {
+                continue;
+            }
+            if password.len() > u8::MAX as usize
+                || !password.bytes().all(|b| (32..=126).contains(&b))
+            {
+                skipped += 1;
+                continue;
+            }
+            lengths[password.len()] += 1;
+            let mut prev = START;
+            for (pos, c) in password.bytes().enumerate() {
+                *counts
+                    .entry((pos as u8, prev))
+                    .or_default()
+                    .entry(c)
+                    .or_default() += 1;
+                prev = c;
+            }
+        }
+        if skipped > 0 {
+            eprintln!("Skipped {skipped} passwords that are not printable ASCII or are too long");
+        }
+
+        Self::from_counts(lengths, counts)
+    }
+
+    /// Builds the sampling tables from raw counts.
+    fn from_counts(
+        mut lengths: Vec<u64>,
+        counts: BTreeMap<(u8, u8), BTreeMap<u8, u64>>,
+    ) -> Result<Self, HashassinError> {
+        // Trailing zero counts carry no information
+        while lengths.last() == Some(&0) {
+            lengths.pop();
+        }
+        let length_weights = WeightedIndex::new(lengths.iter().copied()).map_err(|_| {
+            HashassinError::InvalidInput("Markov model has no training passwords".to_string())
+        })?;
+
+        let mut marginals: Vec<BTreeMap<u8, u64>> = vec![BTreeMap::new(); lengths.len()];
+        for (&(pos, _), next) in &counts {
+            if let Some(marginal) = marginals.get_mut(pos as usize) {
+                for (&c, &n) in next {
+                    *marginal.entry(c).or_default() += n;
+                }
+            }
+        }
+
+        Ok(MarkovModel {
+            lengths,
+            length_weights,
+            transitions: counts
+                .iter()
+                .filter_map(|(&key, next)| Some((key, Transition::new(next)?)))
+                .collect(),
+            positions: marginals.iter().map(Transition::new).collect(),
+        })
+    }
+
+    /// Loads a model written by `MarkovModel::save`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or is not a valid model file.
+    pub fn load(path: &str) -> Result<Self, HashassinError> {
+        let mut reader = BufReader::new(utils::open_file(path)?);
+        let read_err = |e: std::io::Error| HashassinError::FileRead(e.to_string());
+
+        let mut magic = vec![0u8; MAGIC.len()];
+        reader.read_exact(&mut magic).map_err(read_err)?;
+        if magic != MAGIC {
+            return Err(HashassinError::InvalidFormat(
+                "missing markov model magic word".to_string(),
+            ));
+        }
+        let mut byte = [0u8; 1];
+        reader.read_exact(&mut byte).map_err(read_err)?;
+        if byte[0] != VERSION {
+            return Err(HashassinError::InvalidFormat(format!(
+                "unsupported markov model version {}",
+                byte[0]
+            )));
+        }
+
+        let mut u64_buf = [0u8; 8];
+        reader.read_exact(&mut byte).map_err(read_err)?;
+        let mut lengths = Vec::with_capacity(byte[0] as usize + 1);
+        for _ in 0..=byte[0] {
+            reader.read_exact(&mut u64_buf).map_err(read_err)?;
+            lengths.push(u64::from_be_bytes(u64_buf));
+        }
+
+        let mut u32_buf = [0u8; 4];
+        reader.read_exact(&mut u32_buf).map_err(read_err)?;
+        let mut counts: BTreeMap<(u8, u8), BTreeMap<u8, u64>> = BTreeMap::new();
+        for _ in 0..u32::from_be_bytes(u32_buf) {
+            let mut entry = [0u8; 3];
+            reader.read_exact(&mut entry).map_err(read_err)?;
+            reader.read_exact(&mut u64_buf).map_err(read_err)?;
+            counts
+                .entry((entry[0], entry[1]))
+                .or_default()
+                .insert(entry[2], u64::from_be_bytes(u64_buf));
+        }
+
+        Self::from_counts(lengths, counts)
+    }
+
+    /// Writes the model to a file.
+    ///
+    /// The format is the magic word `markovmodel`, a version byte, the longest password length `L` (1 byte),
+    /// `L + 1` big-endian u64 length counts, a big-endian u32 number of transitions and then every transition
+    /// as position (1 byte), previous character (1 byte, 0 at the start), next character (1 byte) and count
+    /// (big-endian u64).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be created or written.
+    pub fn save(&self, path: &str) -> Result<(), HashassinError> {
+        let file = File::create(path)
+            .map_err(|e| HashassinError::CreateFile(format!("Error creating model file: {e:?}")))?;
+        let mut writer = BufWriter::new(file);
+
+        let mut data = Vec::new();
+        data.extend_from_slice(MAGIC);
+        data.push(VERSION);
+        data.push((self.lengths.len() - 1) as u8);
+        for count in &self.lengths {
+            data.extend_from_slice(&count.to_be_bytes());
+        }
+        let num_transitions: usize = self.transitions.values().map(|t| t.chars.len()).sum();
+        data.extend_from_slice(&(num_transitions as u32).to_be_bytes());
+        for (&(pos, prev), transition) in &self.transitions {
+            for (&c, &count) in transition.chars.iter().zip(&transition.counts) {
+                data.extend_from_slice(&[pos, prev, c]);
+                data.extend_from_slice(&count.to_be_bytes());
+            }
+        }
+
+        writer
+            .write_all(&data)
+            .and_then(|_| writer.flush())
+            .map_err(|e| HashassinError::WriteError(e.to_string()))
+    }
+
+    /// Draws one password.
+    ///
+    /// Characters whose predecessor never appeared at that position in the corpus fall back to the character
+    /// statistics of the position alone.
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> String {
+        let length = self.length_weights.sample(rng);
+        let mut password = String::with_capacity(length);
+        let mut prev = START;
+        for pos in 0..length {
+            let transition = self
+                .transitions
+                .get(&(pos as u8, prev))
+                .or_else(|| self.positions.get(pos).and_then(Option::as_ref));
+            // Every position below an observed length has been seen at least once
+            let c = match transition {
+                Some(transition) => transition.sample(rng),
+                None => rng.random_range(32..=126),
+            };
+            password.push(c as char);
+            prev = c;
+        }
+        password
+    }
+
+    /// Most characters of `class` one password can hold without using an `excluded` character, or `None` if
+    /// every length the model draws needs a position where only excluded characters were seen.
+    ///
+    /// This is an upper bound: it counts the characters seen at each position regardless of the predecessor.
+    pub(crate) fn max_count(&self, class: ByteClass, excluded: &[u8]) -> Option<usize> {
+        let usable = |pos: usize| -> Vec<u8> {
+            match self.positions.get(pos).and_then(Option::as_ref) {
+                Some(transition) => transition
+                    .chars
+                    .iter()
+                    .copied()
+                    .filter(|c| !excluded.contains(c))
+                    .collect(),
+                None => (32..=126).filter(|c| !excluded.contains(c)).collect(),
+            }
+        };
+        self.lengths
+            .iter()
+            .enumerate()
+            .filter(|&(_, &count)| count > 0)
+            .filter_map(|(length, _)| {
+                let positions: Vec<Vec<u8>> = (0..length).map(usable).collect();
+                if positions.iter().any(Vec::is_empty) {
+                    return None;
+                }
+                Some(
+                    positions
+                        .iter()
+                        .filter(|chars| chars.iter().any(class))
+                        .count(),
+                )
+            })
+            .max()
+    }
+
+    /// Enumerates passwords in decreasing order of probability.
+    ///
+    /// Only transitions seen in the corpus are followed, so the enumeration is finite. It is a best-first
+    /// search over prefixes; since extending a prefix can only lower its probability, complete passwords
+    /// come out in exact probability order.
+    pub fn enumerate(&self) -> impl Iterator<Item = String> + '_ {
+        let total = self.lengths.iter().sum::<u64>() as f64;
+        let frontier = self
+            .lengths
+            .iter()
+            .enumerate()
+            .filter(|&(_, &count)| count > 0)
+            .map(|(length, &count)| Candidate {
+                log_prob: (count as f64 / total).ln(),
+                length,
+                prefix: Vec::new(),
+            })
+            .collect();
+        MarkovEnumerator {
+            model: self,
+            frontier,
+        }
+    }
+}
+
+/// A password prefix waiting to be extended, ordered by probability.
+#[derive(Debug)]
+struct Candidate {
+    log_prob: f64,
+    length: usize,
+    prefix: Vec<u8>,
+}
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Ties go to the lexicographically smaller prefix so the order is deterministic
+        self.log_prob
+            .total_cmp(&other.log_prob)
+            .then_with(|| other.prefix.cmp(&self.prefix))
+            .then_with(|| other.length.cmp(&self.length))
+    }
+}
+
+struct MarkovEnumerator<'a> {
+    model: &'a MarkovModel,
+    frontier: BinaryHeap<Candidate>,
+}
+
+impl Iterator for MarkovEnumerator<'_> {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        while let Some(candidate) = self.frontier.pop() {
+            let pos = candidate.prefix.len();
+            if pos == candidate.length {
+                return Some(candidate.prefix.iter().map(|&b| b as char).collect());
+            }
+            let prev = candidate.prefix.last().copied().unwrap_or(START);
+            let Some(transition) = self.model.transitions.get(&(pos as u8, prev)) else {
+                continue;
+            };
+            let total = transition.total() as f64;
+            for (&c, &count) in transition.chars.iter().zip(&transition.counts) {
+                let mut prefix = candidate.prefix.clone();
+                prefix.push(c);
+                self.frontier.push(Candidate {
+                    log_prob: candidate.log_prob + (count as f64 / total).ln(),
+                    length: candidate.length,
+                    prefix,
+                });
+            }
+        }
+        None
+    }
+}
+
+/// Trains a Markov model on a password list and writes it to a file.
+///
+/// # Arguments
+///
+/// * `in_file` - The path to the training passwords, one per line.
+/// * `out_file` - The path the model is written to.
+///
+/// # Errors
+///
+/// Returns an error if the corpus cannot be read, holds no usable password, or the model cannot be written.
+pub fn train_markov(in_file: &str, out_file: &str) -> Result<(), HashassinError> {
+    let model = MarkovModel::train(in_file)?;
+    model.save(out_file)?;
+    println!(
+        "Trained markov model on {} passwords with {} transitions",
+        model.lengths.iter().sum::<u64>(),
+        model
+            .transitions
+            .values()
+            .map(|t| t.chars.len())
+            .sum::<usize>()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn model() -> Result<MarkovModel, HashassinError> {
+        MarkovModel::train(utils::TempFile::new("markov-corpus", "abc\nabd\nabc\nxy\n")?.path())
+    }
+
+    #[test]
+    fn enumerates_the_corpus_by_probability() -> Result<(), HashassinError> {
+        let passwords: Vec<String> = model()?.enumerate().collect();
+        assert_eq!(passwords[0], "abc");
+        assert_eq!(passwords[3], "xy");
+        let mut sorted = passwords.clone();
+        sorted.sort();
+        assert_eq!(sorted, ["ab", "abc", "abd", "xy"]);
+        Ok(())
+    }
+
+    #[test]
+    fn saved_models_load_unchanged() -> Result<(), HashassinError> {
+        let model = model()?;
+        let file = utils::TempFile::new("markov-model", "")?;
+        let path = file.path();
+        model.save(path)?;
+        let loaded = MarkovModel::load(path)?;
+        assert_eq!(loaded.lengths, model.lengths);
+        assert_eq!(
+            loaded.enumerate().collect::<Vec<_>>(),
+            model.enumerate().collect::<Vec<_>>()
+        );
+
+        let corrupted = utils::TempFile::new("markov-corrupted", "markovmodeX\u{1}")?;
+        assert!(matches!(
+            MarkovModel::load(corrupted.path()),
+            Err(HashassinError::InvalidFormat(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn counts_characters_usable_without_exclusions() -> Result<(), HashassinError> {
+        let model = model()?;
+        assert_eq!(model.max_count(|_| true, b""), Some(3));
+        assert_eq!(model.max_count(|c| *c == b'x', b""), Some(1));
+        // `x` still starts a password when `a` is excluded
+        assert_eq!(model.max_count(|_| true, b"a"), Some(3));
+        assert_eq!(model.max_count(|_| true, b"ax"), None);
+        Ok(())
+    }
+}
@@ -0,0 +1,146 @@
+use std::str::FromStr;
+
+use crate::{HashassinError, charset::Charset, radix_type::Radix};
+
+/// A password template assigning a charset to every position.
+///
+/// Masks use the hashcat placeholder syntax: `?l` lowercase, `?u` uppercase,
+/// `?d` digits, `?s` symbols, `?h` lowercase hex, `?a` all printable ASCII and
+/// `??` for a literal question mark. Any other printable character stands for
+/// itself, so `Pass?d?d?d` yields `Pass000` through `Pass999`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mask(Vec<Charset>);
+
+impl Mask {
+    /// Parses a mask such as `?u?l?l?l?d?d`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `HashassinError::InvalidInput` if the mask is empty, ends with a
+    /// lone `?`, uses an unknown placeholder or contains a non printable character.
+    pub fn parse(mask: &str) -> Result<Self, HashassinError> {
+        let mut positions = Vec::new();
+        let mut chars = mask.chars();
+        while let Some(c) = chars.next() {
+            let charset = match c {
+                '?' => match chars.next() {
+                    Some('l') => Charset::parse("lower")?,
+                    Some('u') => Charset::parse("upper")?,
+                    Some('d') => Charset::parse("digit")?,
+                    Some('s') => Charset::parse("symbol,space")?,
+                    Some('h') => Charset::parse("hex")?,
+                    Some('a') => Charset::default(),
+                    Some('?') => Charset::from_bytes(vec![b'?'])?,
+                    Some(other) => {
+                        return Err(HashassinError::InvalidInput(format!(
+                            "Unknown mask placeholder ?{other}"
+                        )));
+                    }
+                    None => {
+                        return Err(HashassinError::InvalidInput(
+                            "Mask ends with an incomplete placeholder".to_string(),
+                        ));
+                    }
+                },
+                ' '..='~' => Charset::from_bytes(vec![c as u8])?,
+                _ => {
+                    return Err(HashassinError::InvalidInput(format!(
+                        "Mask contains non printable ASCII character {c:?}"
+                    )));
+                }
+            };
+            positions.push(charset);
+        }
+        if positions.is_empty() {
+            return Err(HashassinError::InvalidInput(
+                "Mask cannot be empty".to_string(),
+            ));
+        }
+        Ok(Mask(positions))
+    }
+
+    /// Builds a mask of `length` positions that all use `charset`.
+    pub fn uniform(length: u8, charset: &Charset) -> Self {
+        Mask(vec![charset.clone(); length as usize])
+    }
+
+    /// Number of characters in passwords produced by this mask.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns `true` if the mask produces empty passwords.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The charset of every position, in order.
+    pub fn positions(&self) -> &[Charset] {
+        &self.0
+    }
+
+    /// Returns the mask with the characters in `excluded` removed from every position.
+    ///
+    /// # Errors
+    ///
+    /// Returns `HashassinError::InvalidInput` if a position is left without characters.
+    pub fn without(&self, excluded: &[u8]) -> Result<Self, HashassinError> {
+        self.0
+            .iter()
+            .map(|charset| charset.without(excluded))
+            .collect::<Result<Vec<_>, _>>()
+            .map(Mask)
+    }
+
+    /// The radix of every position, i.e. the size of its charset.
+    pub(crate) fn radixes(&self) -> Vec<Radix> {
+        self.0
+            .iter()
+            .map(|charset| Radix::new(charset.len() as u128))
+            .collect()
+    }
+}
+
+impl FromStr for Mask {
+    type Err = HashassinError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Mask::parse(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_placeholders_and_literals() -> Result<(), HashassinError> {
+        let mask = Mask::parse("P?d?l??")?;
+        let sizes: Vec<usize> = mask.positions().iter().map(Charset::len).collect();
+        assert_eq!(sizes, [1, 10, 26, 1]);
+        assert_eq!(mask.positions()[0].as_bytes(), b"P");
+        assert_eq!(mask.positions()[3].as_bytes(), b"?");
+        assert_eq!(Mask::parse("?s")?.positions()[0].len(), 33);
+        assert_eq!(Mask::parse("?h")?.positions()[0].len(), 16);
+        assert_eq!(Mask::parse("?a")?.positions()[0].len(), 95);
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_malformed_masks() {
+        for mask in ["", "?", "ab?", "?x", "é"] {
+            assert!(
+                matches!(Mask::parse(mask), Err(HashassinError::InvalidInput(_))),
+                "{mask:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn removes_excluded_characters() -> Result<(), HashassinError> {
+        let mask = Mask::parse("a?h")?.without(b"0123456789")?;
+        assert_eq!(mask.positions()[1].as_bytes(), b"abcdef");
+        assert!(mask.without(b"a").is_err());
+        Ok(())
+    }
+}
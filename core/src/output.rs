@@ -0,0 +1,86 @@
+use std::{
+    fmt::Display,
+    io::{self, Write},
+    str::FromStr,
+};
+
+use serde_json::Value;
+
+use crate::HashassinError;
+
+/// How dump commands print a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    /// The human readable header lines followed by one tab separated line per entry.
+    #[default]
+    Text,
+    /// One JSON object holding the header fields and an array of entries.
+    Json,
+    /// A CSV table with a header row, one row per entry, repeating the header fields in every row.
+    Csv,
+}
+
+impl FromStr for OutputFormat {
+    type Err = HashassinError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            "csv" => Ok(OutputFormat::Csv),
+            _ => Err(HashassinError::InvalidInput(format!(
+                "Unknown output format {s:?}, expected text, json or csv"
+            ))),
+        }
+    }
+}
+
+impl Display for OutputFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OutputFormat::Text => write!(f, "text"),
+            OutputFormat::Json => write!(f, "json"),
+            OutputFormat::Csv => write!(f, "csv"),
+        }
+    }
+}
+
+/// Joins fields into a CSV row (RFC 4180), quoting fields that hold a comma, a quote or a line break.
+pub(crate) fn csv_row<S: AsRef<str>>(fields: &[S]) -> String {
+    fields
+        .iter()
+        .map(|field| {
+            let field = field.as_ref();
+            if field.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Writes a JSON object holding the `fields` followed by the array `name`, serializing one element at a time so
+/// the elements never have to be in memory together.
+pub(crate) fn write_json<W: Write>(
+    mut out: W,
+    fields: &[(&str, Value)],
+    name: &str,
+    elements: impl Iterator<Item = Value>,
+) -> io::Result<()> {
+    writeln!(out, "{{")?;
+    for (key, value) in fields {
+        write!(out, "  {}: ", Value::from(*key))?;
+        serde_json::to_writer(&mut out, value)?;
+        writeln!(out, ",")?;
+    }
+    write!(out, "  {}: [", Value::from(name))?;
+    for (i, element) in elements.enumerate() {
+        out.write_all(if i == 0 { b"\n    " } else { b",\n    " })?;
+        serde_json::to_writer(&mut out, &element)?;
+    }
+    writeln!(out, "\n  ]")?;
+    writeln!(out, "}}")?;
+    out.flush()
+}
@@ -0,0 +1,279 @@
+use std::{
+    collections::HashSet,
+    io::{BufRead, BufReader},
+    str::FromStr,
+};
+
+use rand::Rng;
+
+use crate::{HashassinError, policy::ByteClass, utils};
+
+/// How the words of a passphrase are capitalized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Capitalization {
+    /// Words are used as they appear in the wordlist.
+    #[default]
+    None,
+    /// The first letter of every word is uppercase.
+    First,
+    /// Every letter is uppercase.
+    All,
+    /// The first letter of every word is uppercase with probability one half.
+    Random,
+}
+
+impl FromStr for Capitalization {
+    type Err = HashassinError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Capitalization::None),
+            "first" => Ok(Capitalization::First),
+            "all" => Ok(Capitalization::All),
+            "random" => Ok(Capitalization::Random),
+            _ => Err(HashassinError::InvalidInput(format!(
+                "Unknown capitalization {s:?}, expected none, first, all or random"
+            ))),
+        }
+    }
+}
+
+/// Diceware-style passphrases: words drawn uniformly from a wordlist and joined by a separator.
+///
+/// Optionally every word is capitalized and a run of random digits is inserted as an extra element at a
+/// random position between the words.
+#[derive(Debug, Clone)]
+pub struct Passphrase {
+    words: Vec<String>,
+    num_words: usize,
+    separator: String,
+    capitalization: Capitalization,
+    digits: usize,
+}
+
+impl Passphrase {
+    /// Loads the wordlist and sets up the passphrase layout.
+    ///
+    /// The wordlist has one word per line. Lines in the diceware `11111 word` format are accepted, only the
+    /// last field is used. Duplicate words are dropped so every word counts once towards the entropy; when
+    /// words are capitalized they are lowercased first for the same reason.
+    ///
+    /// # Arguments
+    ///
+    /// * `wordlist` - The path to the wordlist.
+    /// * `num_words` - The number of words in every passphrase.
+    /// * `separator` - The string placed between words.
+    /// * `capitalization` - How the words are capitalized.
+    /// * `digits` - The number of random digits inserted, 0 for none.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the wordlist cannot be read, holds fewer than two distinct words, or `num_words` is 0.
+    pub fn new(
+        wordlist: &str,
+        num_words: usize,
+        separator: String,
+        capitalization: Capitalization,
+        digits: usize,
+    ) -> Result<Self, HashassinError> {
+        if num_words == 0 {
+            return Err(HashassinError::InvalidInput(
+                "A passphrase needs at least one word".to_string(),
+            ));
+        }
+
+        let reader = BufReader::new(utils::open_file(wordlist)?);
+        let mut seen = HashSet::new();
+        let mut words = Vec::new();
+        for line in reader.lines() {
+            let line = line.map_err(|e| HashassinError::FileRead(e.to_string()))?;
+            let Some(word) = line.split_whitespace().last() else {
+                continue;
+            };
+            let word = match capitalization {
+                Capitalization::None => word.to_string(),
+                _ => word.to_lowercase(),
+            };
+            if seen.insert(word.clone()) {
+                words.push(word);
+            }
+        }
+        if words.len() < 2 {
+            return Err(HashassinError::InvalidInput(format!(
+                "Wordlist {wordlist} holds {} distinct words, at least 2 are needed",
+                words.len()
+            )));
+        }
+
+        Ok(Passphrase {
+            words,
+            num_words,
+            separator,
+            capitalization,
+            digits,
+        })
+    }
+
+    /// Number of distinct words in the wordlist.
+    pub fn wordlist_len(&self) -> usize {
+        self.words.len()
+    }
+
+    /// Entropy of a single passphrase in bits, assuming the attacker knows the wordlist and every setting.
+    ///
+    /// Every word contributes `log2(wordlist size)` bits. Random capitalization adds one bit for each word
+    /// that starts with a letter, averaged over the wordlist, and digits add `log2(10)` bits each plus the
+    /// choice of one of the `num_words + 1` insertion points.
+    pub fn entropy_bits(&self) -> f64 {
+        let mut per_word = (self.words.len() as f64).log2();
+        if self.capitalization == Capitalization::Random {
+            let cased = self
+                .words
+                .iter()
+                .filter(|w| w.chars().next().is_some_and(char::is_lowercase))
+                .count();
+            per_word += cased as f64 / self.words.len() as f64;
+        }
+        let mut bits = per_word * self.num_words as f64;
+        if self.digits > 0 {
+            bits += self.digits as f64 * 10f64.log2() + ((self.num_words + 1) as f64).log2();
+        }
+        bits
+    }
+
+    /// Most characters of `class` one passphrase can hold without using an `excluded` character, or `None` if
+    /// every passphrase contains one.
+    pub(crate) fn max_count(&self, class: ByteClass, excluded: &[u8]) -> Option<usize> {
+        let allowed = |s: &str| !s.bytes().any(|b| excluded.contains(&b));
+        let count = |s: &str| s.bytes().filter(class).count();
+        let best_word = self
+            .words
+            .iter()
+            .flat_map(|word| match self.capitalization {
+                Capitalization::None => vec![word.clone()],
+                Capitalization::First => vec![capitalize(word)],
+                Capitalization::All => vec![word.to_uppercase()],
+                Capitalization::Random => vec![word.clone(), capitalize(word)],
+            })
+            .filter(|word| allowed(word))
+            .map(|word| count(&word))
+            .max()?;
+
+        let parts = self.num_words + usize::from(self.digits > 0);
+        if parts > 1 && !allowed(&self.separator) {
+            return None;
+        }
+        let mut total = best_word * self.num_words + count(&self.separator) * (parts - 1);
+        if self.digits > 0 {
+            let digits: Vec<u8> = (b'0'..=b'9').filter(|d| !excluded.contains(d)).collect();
+            if digits.is_empty() {
+                return None;
+            }
+            if digits.iter().any(class) {
+                total += self.digits;
+            }
+        }
+        Some(total)
+    }
+
+    /// Draws one passphrase.
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> String {
+        let mut parts: Vec<String> = (0..self.num_words)
+            .map(|_| {
+                let word = &self.words[rng.random_range(0..self.words.len())];
+                match self.capitalization {
+                    Capitalization::None => word.clone(),
+                    Capitalization::First => capitalize(word),
+                    Capitalization::All => word.to_uppercase(),
+                    Capitalization::Random => {
+                        if rng.random_bool(0.5) {
+                            capitalize(word)
+                        } else {
+                            word.clone()
+                        }
+                    }
+                }
+            })
+            .collect();
+        if self.digits > 0 {
+            let digits = (0..self.digits)
+                .map(|_| char::from(b'0' + rng.random_range(0..10)))
+                .collect();
+            parts.insert(rng.random_range(0..=parts.len()), digits);
+        }
+        parts.join(&self.separator)
+    }
+}
+
+/// Uppercases the first character of a word.
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::TempFile;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    /// Diceware-style lines, with a duplicate differing only in case.
+    const WORDLIST: &str = "11111 apple\n11112 Banana\n11113 cherry\n\n11114 banana\n";
+
+    #[test]
+    fn reads_diceware_wordlists() -> Result<(), HashassinError> {
+        let wordlist = TempFile::new("passphrase-diceware", WORDLIST)?;
+        let passphrase = |capitalization| {
+            Passphrase::new(wordlist.path(), 4, " ".to_string(), capitalization, 0)
+        };
+        // Lowercasing makes banana a duplicate when words are capitalized
+        assert_eq!(passphrase(Capitalization::None)?.wordlist_len(), 4);
+        assert_eq!(passphrase(Capitalization::First)?.wordlist_len(), 3);
+        assert!(
+            (passphrase(Capitalization::First)?.entropy_bits() - 4.0 * 3f64.log2()).abs() < 1e-9
+        );
+
+        let single = TempFile::new("passphrase-single", "apple\napple\n")?;
+        assert!(
+            Passphrase::new(single.path(), 4, " ".to_string(), Capitalization::None, 0).is_err()
+        );
+        assert!(
+            Passphrase::new(wordlist.path(), 0, " ".to_string(), Capitalization::None, 0).is_err()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn samples_follow_the_layout() -> Result<(), HashassinError> {
+        let wordlist = TempFile::new("passphrase-layout", WORDLIST)?;
+        let passphrase = Passphrase::new(
+            wordlist.path(),
+            3,
+            "-".to_string(),
+            Capitalization::First,
+            2,
+        )?;
+        let mut rng = ChaCha20Rng::seed_from_u64(7);
+        for _ in 0..50 {
+            let sample = passphrase.sample(&mut rng);
+            let parts: Vec<&str> = sample.split('-').collect();
+            assert_eq!(parts.len(), 4, "{sample}");
+            let (digits, words): (Vec<&str>, Vec<&str>) = parts
+                .into_iter()
+                .partition(|part| part.bytes().all(|b| b.is_ascii_digit()));
+            assert_eq!(digits.len(), 1, "{sample}");
+            assert_eq!(digits[0].len(), 2, "{sample}");
+            assert!(
+                words
+                    .iter()
+                    .all(|word| ["Apple", "Banana", "Cherry"].contains(word)),
+                "{sample}"
+            );
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,278 @@
+use crate::{HashassinError, generate_passwords::PasswordSource};
+
+/// Characters that are easily confused with each other when read or typed.
+pub const AMBIGUOUS_CHARS: &str = "0O1lI|";
+
+/// Number of candidates drawn for a single password before the policy is considered unsatisfiable.
+pub(crate) const MAX_ATTEMPTS: usize = 1_000_000;
+
+/// Predicate selecting one class of characters, such as `u8::is_ascii_digit`.
+pub(crate) type ByteClass = fn(&u8) -> bool;
+
+/// Requirements every generated password must meet.
+///
+/// Passwords are drawn from their source as usual and redrawn until they comply, so compliant passwords keep
+/// the relative probabilities they had under the source; a uniform source stays uniform over the compliant
+/// passwords. Excluded characters are removed from mask and charset sources up front, which yields the same
+/// distribution without the wasted draws.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Policy {
+    /// Minimum number of uppercase letters.
+    pub min_upper: usize,
+    /// Minimum number of lowercase letters.
+    pub min_lower: usize,
+    /// Minimum number of digits.
+    pub min_digit: usize,
+    /// Minimum number of ASCII punctuation characters.
+    pub min_symbol: usize,
+    /// Characters that must not appear.
+    pub excluded: Vec<u8>,
+    /// Longest allowed run of one repeated character, e.g. 2 allows `aa` but not `aaa`.
+    pub max_run: Option<usize>,
+}
+
+impl Policy {
+    /// Returns `true` if `password` meets every requirement.
+    pub fn allows(&self, password: &str) -> bool {
+        let count = |class: ByteClass| password.bytes().filter(class).count();
+        count(u8::is_ascii_uppercase) >= self.min_upper
+            && count(u8::is_ascii_lowercase) >= self.min_lower
+            && count(u8::is_ascii_digit) >= self.min_digit
+            && count(u8::is_ascii_punctuation) >= self.min_symbol
+            && !password.bytes().any(|b| self.excluded.contains(&b))
+            && self
+                .max_run
+                .is_none_or(|max_run| longest_run(password.as_bytes()) <= max_run)
+    }
+
+    /// Removes excluded characters from the source where possible, drops the lengths too short for the
+    /// policy from length distributions, and rejects policies the source can never satisfy.
+    ///
+    /// # Errors
+    ///
+    /// Returns `HashassinError::InvalidInput` if exclusions leave a position without characters, or the
+    /// source cannot produce enough characters of a required class. Markov and passphrase sources are checked
+    /// against an upper bound of what they can produce, so a policy that passes may still find no password
+    /// within `MAX_ATTEMPTS` draws; generation then fails.
+    pub(crate) fn restrict(
+        &self,
+        source: PasswordSource,
+    ) -> Result<PasswordSource, HashassinError> {
+        if self.max_run == Some(0) {
+            return Err(HashassinError::InvalidInput(
+                "Maximum run length must be at least 1".to_string(),
+            ));
+        }
+        let source = match source {
+            PasswordSource::Mask(mask) => {
+                let mask = mask.without(&self.excluded)?;
+                self.check_feasible(mask.len(), |class| {
+                    mask.positions()
+                        .iter()
+                        .filter(|charset| charset.as_bytes().iter().any(class))
+                        .count()
+                })?;
+                PasswordSource::Mask(mask)
+            }
+            PasswordSource::Lengths { lengths, charset } => {
+                let charset = charset.without(&self.excluded)?;
+                // Every position of a password can hold any class of the charset
+                let feasible = |length: usize| {
+                    self.check_feasible(length, |class| {
+                        if charset.as_bytes().iter().any(class) {
+                            length
+                        } else {
+                            0
+                        }
+                    })
+                };
+                feasible(lengths.max() as usize)?;
+                // Passwords drawn at lengths too short for the minimums would all be redrawn
+                let lengths = lengths.filter(|length| feasible(length as usize).is_ok())?;
+                PasswordSource::Lengths { lengths, charset }
+            }
+            PasswordSource::Markov(model) => {
+                let longest = model.max_count(|_| true, &self.excluded).ok_or_else(|| {
+                    HashassinError::InvalidInput(
+                        "Every password of the Markov model contains an excluded character"
+                            .to_string(),
+                    )
+                })?;
+                self.check_feasible(longest, |class| {
+                    model.max_count(class, &self.excluded).unwrap_or(0)
+                })?;
+                PasswordSource::Markov(model)
+            }
+            PasswordSource::Passphrase(passphrase) => {
+                let longest = passphrase
+                    .max_count(|_| true, &self.excluded)
+                    .ok_or_else(|| {
+                        HashassinError::InvalidInput(
+                            "Every passphrase contains an excluded character".to_string(),
+                        )
+                    })?;
+                self.check_feasible(longest, |class| {
+                    passphrase.max_count(class, &self.excluded).unwrap_or(0)
+                })?;
+                PasswordSource::Passphrase(passphrase)
+            }
+        };
+        Ok(source)
+    }
+
+    /// Checks that `length` characters can hold every minimum, given how many positions can produce each class.
+    fn check_feasible(
+        &self,
+        length: usize,
+        positions: impl Fn(ByteClass) -> usize,
+    ) -> Result<(), HashassinError> {
+        let required = self.min_upper + self.min_lower + self.min_digit + self.min_symbol;
+        if required > length {
+            return Err(HashassinError::InvalidInput(format!(
+                "Policy requires {required} characters but passwords are at most {length} long"
+            )));
+        }
+        let classes: [(&str, usize, ByteClass); 4] = [
+            ("uppercase letters", self.min_upper, u8::is_ascii_uppercase),
+            ("lowercase letters", self.min_lower, u8::is_ascii_lowercase),
+            ("digits", self.min_digit, u8::is_ascii_digit),
+            ("symbols", self.min_symbol, u8::is_ascii_punctuation),
+        ];
+        for (name, min, class) in classes {
+            let available = positions(class);
+            if min > available {
+                return Err(HashassinError::InvalidInput(format!(
+                    "Policy requires {min} {name} but only {available} positions can hold one"
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Length of the longest run of one repeated byte.
+fn longest_run(bytes: &[u8]) -> usize {
+    bytes
+        .chunk_by(|a, b| a == b)
+        .map(<[u8]>::len)
+        .max()
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        charset::Charset,
+        lengths::LengthDistribution,
+        markov::MarkovModel,
+        mask::Mask,
+        passphrase::{Capitalization, Passphrase},
+        utils,
+    };
+
+    fn policy() -> Policy {
+        Policy {
+            min_upper: 1,
+            min_digit: 2,
+            excluded: b"0".to_vec(),
+            max_run: Some(2),
+            ..Policy::default()
+        }
+    }
+
+    fn mask(mask: &str) -> Result<PasswordSource, HashassinError> {
+        Ok(PasswordSource::Mask(Mask::parse(mask)?))
+    }
+
+    #[test]
+    fn allows_only_compliant_passwords() {
+        let policy = policy();
+        assert!(policy.allows("Ab12"));
+        assert!(policy.allows("Abb12"));
+        assert!(!policy.allows("ab12"));
+        assert!(!policy.allows("Ab1"));
+        assert!(!policy.allows("Ab10"));
+        assert!(!policy.allows("Abbb12"));
+        assert!(Policy::default().allows(""));
+    }
+
+    #[test]
+    fn restricts_masks_and_charsets() -> Result<(), HashassinError> {
+        let policy = policy();
+        let PasswordSource::Mask(restricted) = policy.restrict(mask("?u?d?d")?)? else {
+            panic!("a mask stays a mask");
+        };
+        assert_eq!(restricted.positions()[1].as_bytes(), b"123456789");
+        assert!(policy.restrict(mask("?u?d?l")?).is_err());
+        assert!(policy.restrict(mask("?u?d0")?).is_err());
+
+        let lengths = PasswordSource::Lengths {
+            lengths: LengthDistribution::uniform(1, 2)?,
+            charset: Charset::parse("upper,digit")?,
+        };
+        assert!(policy.restrict(lengths).is_err());
+        let lengths = PasswordSource::Lengths {
+            lengths: LengthDistribution::uniform(1, 3)?,
+            charset: Charset::parse("upper,digit")?,
+        };
+        assert!(policy.restrict(lengths).is_ok());
+        // Only the lengths that can hold every minimum are drawn
+        let lengths = PasswordSource::Lengths {
+            lengths: LengthDistribution::parse("1:1000,2:1000,5:1")?,
+            charset: Charset::parse("upper,digit")?,
+        };
+        let PasswordSource::Lengths { lengths, .. } = policy.restrict(lengths)? else {
+            panic!("a length distribution stays a length distribution");
+        };
+        let mut rng = rand::rng();
+        assert!((0..100).all(|_| lengths.sample(&mut rng) == 5));
+
+        let no_runs = Policy {
+            max_run: Some(0),
+            ..Policy::default()
+        };
+        assert!(no_runs.restrict(mask("?a")?).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn restricts_markov_models_and_passphrases() -> Result<(), HashassinError> {
+        let model =
+            MarkovModel::train(utils::TempFile::new("policy-corpus", "Ab12\nxyz\n")?.path())?;
+        let markov = || PasswordSource::Markov(Arc::new(model.clone()));
+        assert!(policy().restrict(markov()).is_ok());
+        let symbols = Policy {
+            min_symbol: 1,
+            ..Policy::default()
+        };
+        assert!(symbols.restrict(markov()).is_err());
+        let excluded = Policy {
+            excluded: b"Ax".to_vec(),
+            ..Policy::default()
+        };
+        assert!(excluded.restrict(markov()).is_err());
+
+        let wordlist = utils::TempFile::new("policy-words", "apple\nbanana\n")?;
+        let passphrase = |digits| -> Result<PasswordSource, HashassinError> {
+            Ok(PasswordSource::Passphrase(Arc::new(Passphrase::new(
+                wordlist.path(),
+                2,
+                "-".to_string(),
+                Capitalization::First,
+                digits,
+            )?)))
+        };
+        assert!(policy().restrict(passphrase(2)?).is_ok());
+        assert!(policy().restrict(passphrase(0)?).is_err());
+        assert!(symbols.restrict(passphrase(0)?).is_ok());
+        let no_separator = Policy {
+            excluded: b"-".to_vec(),
+            ..Policy::default()
+        };
+        assert!(no_separator.restrict(passphrase(0)?).is_err());
+        Ok(())
+    }
+}
@@ -8,7 +8,7 @@ use std::cell::Cell;
 ///
 /// # Examples
 ///
-/// ```rust
+/// ```rust,ignore
 /// let radix = Radix::new(95); // Printable ASCII
 /// assert_eq!(radix.get(), 95);
 /// ```
@@ -24,7 +24,7 @@ pub(crate) struct Radix(Cell<u128>);
 ///
 /// # Example
 ///
-/// ```rust
+/// ```rust,ignore
 /// let r = Radix::new(36);
 /// ```
 impl Radix {
@@ -0,0 +1,370 @@
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    str::FromStr,
+};
+
+use crate::{HashassinError, utils};
+
+/// A single rule function, using hashcat's rule syntax.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum RuleOp {
+    /// `:` leave the word unchanged.
+    Noop,
+    /// `l` lowercase every letter.
+    Lowercase,
+    /// `u` uppercase every letter.
+    Uppercase,
+    /// `c` uppercase the first letter and lowercase the rest.
+    Capitalize,
+    /// `C` lowercase the first letter and uppercase the rest.
+    InvertCapitalize,
+    /// `t` toggle the case of every letter.
+    ToggleAll,
+    /// `TN` toggle the case of the letter at position N.
+    ToggleAt(usize),
+    /// `r` reverse the word.
+    Reverse,
+    /// `d` duplicate the word.
+    Duplicate,
+    /// `f` append the reversed word.
+    Reflect,
+    /// `$X` append character X.
+    Append(char),
+    /// `^X` prepend character X.
+    Prepend(char),
+    /// `sXY` replace every X with Y, e.g. `sa@` for leetspeak.
+    Substitute(char, char),
+    /// `@X` remove every X.
+    Purge(char),
+    /// `[` delete the first character.
+    DeleteFirst,
+    /// `]` delete the last character.
+    DeleteLast,
+    /// `DN` delete the character at position N.
+    DeleteAt(usize),
+    /// `'N` truncate the word to N characters.
+    Truncate(usize),
+}
+
+/// A sequence of rule functions applied left to right to a word, such as `c$1$!`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rule(Vec<RuleOp>);
+
+impl Rule {
+    /// Parses one rule line.
+    ///
+    /// Supported functions are `:`, `l`, `u`, `c`, `C`, `t`, `TN`, `r`, `d`, `f`, `$X`, `^X`, `sXY`, `@X`,
+    /// `[`, `]`, `DN` and `'N`, where positions `N` are written `0`-`9` then `A`-`Z` for 10-35. Spaces
+    /// between functions are ignored.
+    ///
+    /// # Errors
+    ///
+    /// Returns `HashassinError::InvalidInput` if the rule uses an unsupported function or is missing an argument.
+    pub fn parse(rule: &str) -> Result<Self, HashassinError> {
+        let mut ops = Vec::new();
+        let mut chars = rule.chars();
+        while let Some(c) = chars.next() {
+            let mut arg = || {
+                chars.next().ok_or_else(|| {
+                    HashassinError::InvalidInput(format!(
+                        "Rule {rule:?}: {c} is missing an argument"
+                    ))
+                })
+            };
+            let op = match c {
+                ' ' => continue,
+                ':' => RuleOp::Noop,
+                'l' => RuleOp::Lowercase,
+                'u' => RuleOp::Uppercase,
+                'c' => RuleOp::Capitalize,
+                'C' => RuleOp::InvertCapitalize,
+                't' => RuleOp::ToggleAll,
+                'T' => RuleOp::ToggleAt(position(rule, arg()?)?),
+                'r' => RuleOp::Reverse,
+                'd' => RuleOp::Duplicate,
+                'f' => RuleOp::Reflect,
+                '$' => RuleOp::Append(arg()?),
+                '^' => RuleOp::Prepend(arg()?),
+                's' => {
+                    let from = arg()?;
+                    RuleOp::Substitute(from, arg()?)
+                }
+                '@' => RuleOp::Purge(arg()?),
+                '[' => RuleOp::DeleteFirst,
+                ']' => RuleOp::DeleteLast,
+                'D' => RuleOp::DeleteAt(position(rule, arg()?)?),
+                '\'' => RuleOp::Truncate(position(rule, arg()?)?),
+                _ => {
+                    return Err(HashassinError::InvalidInput(format!(
+                        "Rule {rule:?}: unsupported function {c:?}"
+                    )));
+                }
+            };
+            ops.push(op);
+        }
+        Ok(Rule(ops))
+    }
+
+    /// Applies the rule to a word.
+    pub fn apply(&self, word: &str) -> String {
+        let mut word: Vec<char> = word.chars().collect();
+        for op in &self.0 {
+            match *op {
+                RuleOp::Noop => {}
+                RuleOp::Lowercase => word.iter_mut().for_each(|c| c.make_ascii_lowercase()),
+                RuleOp::Uppercase => word.iter_mut().for_each(|c| c.make_ascii_uppercase()),
+                RuleOp::Capitalize | RuleOp::InvertCapitalize => {
+                    let first_upper = *op == RuleOp::Capitalize;
+                    for (i, c) in word.iter_mut().enumerate() {
+                        if (i == 0) == first_upper {
+                            c.make_ascii_uppercase();
+                        } else {
+                            c.make_ascii_lowercase();
+                        }
+                    }
+                }
+                RuleOp::ToggleAll => word.iter_mut().for_each(toggle_case),
+                RuleOp::ToggleAt(n) => {
+                    if let Some(c) = word.get_mut(n) {
+                        toggle_case(c);
+                    }
+                }
+                RuleOp::Reverse => word.reverse(),
+                RuleOp::Duplicate => word.extend_from_within(..),
+                RuleOp::Reflect => {
+                    let reversed: Vec<char> = word.iter().rev().copied().collect();
+                    word.extend(reversed);
+                }
+                RuleOp::Append(x) => word.push(x),
+                RuleOp::Prepend(x) => word.insert(0, x),
+                RuleOp::Substitute(from, to) => word
+                    .iter_mut()
+                    .filter(|c| **c == from)
+                    .for_each(|c| *c = to),
+                RuleOp::Purge(x) => word.retain(|&c| c != x),
+                RuleOp::DeleteFirst => {
+                    if !word.is_empty() {
+                        word.remove(0);
+                    }
+                }
+                RuleOp::DeleteLast => {
+                    word.pop();
+                }
+                RuleOp::DeleteAt(n) => {
+                    if n < word.len() {
+                        word.remove(n);
+                    }
+                }
+                RuleOp::Truncate(n) => word.truncate(n),
+            }
+        }
+        word.into_iter().collect()
+    }
+}
+
+impl FromStr for Rule {
+    type Err = HashassinError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Rule::parse(s)
+    }
+}
+
+/// An ordered list of rules. Every word of a wordlist is expanded into one candidate per rule.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RuleSet(Vec<Rule>);
+
+impl RuleSet {
+    /// Builds a rule set from already parsed rules.
+    pub fn new(rules: Vec<Rule>) -> Self {
+        RuleSet(rules)
+    }
+
+    /// Loads a rules file with one rule per line. Empty lines and lines starting with `#` are skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or a rule cannot be parsed.
+    pub fn load(path: &str) -> Result<Self, HashassinError> {
+        let reader = BufReader::new(utils::open_file(path)?);
+        let mut rules = Vec::new();
+        for line in reader.lines() {
+            let line = line.map_err(|e| HashassinError::FileRead(e.to_string()))?;
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            rules.push(Rule::parse(&line)?);
+        }
+        Ok(RuleSet(rules))
+    }
+
+    /// Appends rules to the end of the set.
+    pub fn extend(&mut self, rules: impl IntoIterator<Item = Rule>) {
+        self.0.extend(rules);
+    }
+
+    /// Number of rules in the set.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns `true` if the set holds no rules.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Expands a word into the candidate produced by every rule, in rule order.
+    pub fn expand<'a>(&'a self, word: &'a str) -> impl Iterator<Item = String> + 'a {
+        self.0.iter().map(move |rule| rule.apply(word))
+    }
+}
+
+/// Applies an optional rule set to the lines of a wordlist.
+///
+/// Every successfully read line is replaced by the candidates of all rules, while read errors are passed
+/// through untouched so callers keep reporting them as before. Without rules the lines are returned as is.
+pub(crate) fn expand_lines<'a, I>(
+    lines: I,
+    rules: Option<&'a RuleSet>,
+) -> impl Iterator<Item = io::Result<String>> + 'a
+where
+    I: Iterator<Item = io::Result<String>> + 'a,
+{
+    lines.flat_map(move |line| match (line, rules) {
+        (Ok(word), Some(rules)) => rules.expand(&word).map(Ok).collect(),
+        (line, _) => vec![line],
+    })
+}
+
+/// Expands every line of a wordlist with a rule set and writes the candidates to standard output or a file.
+///
+/// # Arguments
+///
+/// * `in_file` - The path to the wordlist, one word per line.
+/// * `out_file` - The path to the output file. If set to "std", candidates are printed to standard output.
+/// * `rules` - The rules applied to every word.
+///
+/// # Errors
+///
+/// Returns an error if the wordlist cannot be read or the output cannot be written.
+pub fn mangle_wordlist(
+    in_file: &str,
+    out_file: &str,
+    rules: &RuleSet,
+) -> Result<(), HashassinError> {
+    let reader = BufReader::new(utils::open_file(in_file)?);
+    let mut writer: BufWriter<Box<dyn Write>> = match out_file {
+        "std" => BufWriter::new(Box::new(std::io::stdout())),
+        path => BufWriter::new(Box::new(File::create(path).map_err(|e| {
+            HashassinError::CreateFile(format!("Error creating output file: {e:?}"))
+        })?)),
+    };
+
+    for line in reader.lines() {
+        let word = line.map_err(|e| HashassinError::FileRead(e.to_string()))?;
+        for candidate in rules.expand(&word) {
+            writeln!(writer, "{candidate}")
+                .map_err(|e| HashassinError::WriteError(e.to_string()))?;
+        }
+    }
+    writer
+        .flush()
+        .map_err(|e| HashassinError::WriteError(e.to_string()))
+}
+
+/// Decodes a hashcat rule position: `0`-`9` then `A`-`Z` for 10-35.
+fn position(rule: &str, c: char) -> Result<usize, HashassinError> {
+    match c {
+        '0'..='9' => Ok(c as usize - '0' as usize),
+        'A'..='Z' => Ok(c as usize - 'A' as usize + 10),
+        _ => Err(HashassinError::InvalidInput(format!(
+            "Rule {rule:?}: invalid position {c:?}"
+        ))),
+    }
+}
+
+fn toggle_case(c: &mut char) {
+    if c.is_ascii_lowercase() {
+        c.make_ascii_uppercase();
+    } else {
+        c.make_ascii_lowercase();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(rule: &str, word: &str) -> Result<String, HashassinError> {
+        Ok(Rule::parse(rule)?.apply(word))
+    }
+
+    #[test]
+    fn applies_every_function() -> Result<(), HashassinError> {
+        let cases = [
+            (":", "Pass", "Pass"),
+            ("l", "PaSs", "pass"),
+            ("u", "PaSs", "PASS"),
+            ("c", "pASS", "Pass"),
+            ("C", "Pass", "pASS"),
+            ("t", "PaSs", "pAsS"),
+            ("T1", "pass", "pAss"),
+            ("r", "pass", "ssap"),
+            ("d", "ab", "abab"),
+            ("f", "ab", "abba"),
+            ("$1$!", "pass", "pass1!"),
+            ("^2^1", "pass", "12pass"),
+            ("sa@ss$", "pass", "p@$$"),
+            ("@s", "pass", "pa"),
+            ("[", "pass", "ass"),
+            ("]", "pass", "pas"),
+            ("D1", "pass", "pss"),
+            ("'2", "pass", "pa"),
+            ("TA", "pass", "pass"),
+            ("c $2 $0 $2 $4", "summer", "Summer2024"),
+        ];
+        for (rule, word, expected) in cases {
+            assert_eq!(apply(rule, word)?, expected, "{rule}");
+        }
+        // Positions past the end and empty words leave the word alone
+        assert_eq!(apply("D9[]", "")?, "");
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_malformed_rules() {
+        for rule in ["x", "$", "s1", "T", "T!", "'a"] {
+            assert!(
+                matches!(Rule::parse(rule), Err(HashassinError::InvalidInput(_))),
+                "{rule}"
+            );
+        }
+    }
+
+    #[test]
+    fn expands_words_in_rule_order() -> Result<(), HashassinError> {
+        let rules = RuleSet::new(vec![Rule::parse(":")?, Rule::parse("u")?]);
+        assert_eq!(rules.expand("ab").collect::<Vec<_>>(), ["ab", "AB"]);
+
+        let lines = vec![
+            Ok("x".to_string()),
+            Err(io::Error::other("bad line")),
+            Ok("y".to_string()),
+        ];
+        let expanded: Vec<_> = expand_lines(lines.into_iter(), Some(&rules))
+            .map(|line| line.map_err(|e| e.to_string()))
+            .collect();
+        assert_eq!(
+            expanded,
+            [
+                Ok("x".to_string()),
+                Ok("X".to_string()),
+                Err("bad line".to_string()),
+                Ok("y".to_string()),
+                Ok("Y".to_string()),
+            ]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,253 @@
+use std::{fmt::Display, str::FromStr};
+
+use rand::RngCore;
+
+use crate::{HashassinError, hash::HashAlgorithm};
+
+/// Where the salt goes when a password is hashed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SaltPlacement {
+    /// The salt is hashed in front of the password, `H(salt || password)`.
+    Prefix,
+    /// The salt is hashed after the password, `H(password || salt)`.
+    #[default]
+    Suffix,
+    /// The salt is the HMAC key and the password the message, `HMAC-H(salt, password)`.
+    Hmac,
+    /// The algorithm places the salt itself, as the expression `md5(p+salt)` does.
+    Expression,
+}
+
+impl FromStr for SaltPlacement {
+    type Err = HashassinError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "prefix" => Ok(SaltPlacement::Prefix),
+            "suffix" => Ok(SaltPlacement::Suffix),
+            "hmac" => Ok(SaltPlacement::Hmac),
+            "expression" => Ok(SaltPlacement::Expression),
+            _ => Err(HashassinError::InvalidInput(format!(
+                "Unknown salt placement {s:?}, expected prefix, suffix, hmac or expression"
+            ))),
+        }
+    }
+}
+
+impl Display for SaltPlacement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SaltPlacement::Prefix => write!(f, "prefix"),
+            SaltPlacement::Suffix => write!(f, "suffix"),
+            SaltPlacement::Hmac => write!(f, "hmac"),
+            SaltPlacement::Expression => write!(f, "expression"),
+        }
+    }
+}
+
+impl SaltPlacement {
+    /// Value of the SALT PLACEMENT header byte.
+    pub fn to_byte(self) -> u8 {
+        match self {
+            SaltPlacement::Prefix => 0,
+            SaltPlacement::Suffix => 1,
+            SaltPlacement::Hmac => 2,
+            SaltPlacement::Expression => 3,
+        }
+    }
+
+    /// Decodes a SALT PLACEMENT header byte.
+    ///
+    /// # Errors
+    ///
+    /// Returns `HashassinError::InvalidFormat` for unknown values.
+    pub fn from_byte(byte: u8) -> Result<Self, HashassinError> {
+        match byte {
+            0 => Ok(SaltPlacement::Prefix),
+            1 => Ok(SaltPlacement::Suffix),
+            2 => Ok(SaltPlacement::Hmac),
+            3 => Ok(SaltPlacement::Expression),
+            _ => Err(HashassinError::InvalidFormat(format!(
+                "unknown salt placement {byte}"
+            ))),
+        }
+    }
+
+    /// Digest length of salted hashes of `algorithm`, checking that the algorithm can be salted this way.
+    ///
+    /// # Errors
+    ///
+    /// Returns `HashassinError::UnknownAlgorithm` if the algorithm salts itself, has variable-length output,
+    /// or is used as an HMAC without having a block length. The `Expression` placement instead requires an
+    /// algorithm that places a salt with a fixed-length output.
+    pub fn digest_len(self, algorithm: &dyn HashAlgorithm) -> Result<usize, HashassinError> {
+        if self == SaltPlacement::Expression {
+            return match algorithm.digest_len() {
+                Some(digest_len) if algorithm.salted() => Ok(digest_len),
+                _ => Err(HashassinError::UnknownAlgorithm(format!(
+                    "{} does not place a salt itself",
+                    algorithm.name()
+                ))),
+            };
+        }
+        let digest_len = algorithm.fixed_digest_len()?;
+        if self == SaltPlacement::Hmac && algorithm.block_len().is_none() {
+            return Err(HashassinError::UnknownAlgorithm(format!(
+                "{} cannot be used as an HMAC",
+                algorithm.name()
+            )));
+        }
+        Ok(digest_len)
+    }
+
+    /// Hashes a password with a salt.
+    ///
+    /// # Errors
+    ///
+    /// Returns `HashassinError::UnknownAlgorithm` if the placement is `Hmac` and the algorithm has no block
+    /// length, and `HashassinError::HashError` if the algorithm fails.
+    pub fn hash(
+        self,
+        algorithm: &dyn HashAlgorithm,
+        salt: &[u8],
+        password: &[u8],
+    ) -> Result<Vec<u8>, HashassinError> {
+        match self {
+            SaltPlacement::Prefix => algorithm.hash(&[salt, password].concat()),
+            SaltPlacement::Suffix => algorithm.hash(&[password, salt].concat()),
+            SaltPlacement::Hmac => {
+                let block_len = algorithm.block_len().ok_or_else(|| {
+                    HashassinError::UnknownAlgorithm(format!(
+                        "{} cannot be used as an HMAC",
+                        algorithm.name()
+                    ))
+                })?;
+                hmac(algorithm, block_len, salt, password)
+            }
+            SaltPlacement::Expression => algorithm.hash_with_salt(password, salt),
+        }
+    }
+}
+
+/// HMAC (RFC 2104) over any algorithm with a block length.
+fn hmac(
+    algorithm: &dyn HashAlgorithm,
+    block_len: usize,
+    key: &[u8],
+    message: &[u8],
+) -> Result<Vec<u8>, HashassinError> {
+    let mut key = if key.len() > block_len {
+        algorithm.hash(key)?
+    } else {
+        key.to_vec()
+    };
+    key.resize(block_len, 0);
+
+    let mut inner: Vec<u8> = key.iter().map(|b| b ^ 0x36).collect();
+    inner.extend_from_slice(message);
+    let mut outer: Vec<u8> = key.iter().map(|b| b ^ 0x5c).collect();
+    outer.extend_from_slice(&algorithm.hash(&inner)?);
+    algorithm.hash(&outer)
+}
+
+/// How `generate_hashes` salts every password.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Salting {
+    /// Number of random salt bytes drawn for every password.
+    pub salt_len: u8,
+    pub placement: SaltPlacement,
+}
+
+/// `len` random salt bytes.
+pub fn random_salt(len: u8) -> Vec<u8> {
+    let mut salt = vec![0u8; len as usize];
+    rand::rng().fill_bytes(&mut salt);
+    salt
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash;
+
+    fn salted(
+        placement: &str,
+        algorithm: &str,
+        salt: &[u8],
+        password: &[u8],
+    ) -> Result<String, HashassinError> {
+        let algorithm = hash::lookup(algorithm)?;
+        Ok(hex::encode(placement.parse::<SaltPlacement>()?.hash(
+            algorithm.as_ref(),
+            salt,
+            password,
+        )?))
+    }
+
+    #[test]
+    fn places_the_salt() -> Result<(), HashassinError> {
+        // md5("saltpassword") and md5("passwordsalt")
+        assert_eq!(
+            salted("prefix", "md5", b"salt", b"password")?,
+            "67a1e09bb1f83f5007dc119c14d663aa"
+        );
+        assert_eq!(
+            salted("suffix", "md5", b"salt", b"password")?,
+            "b305cadbb3bce54f3aa59c64fec00dea"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn hmac_matches_rfc_test_vectors() -> Result<(), HashassinError> {
+        let message = b"what do ya want for nothing?";
+        assert_eq!(
+            salted("hmac", "md5", b"Jefe", message)?,
+            "750c783e6ab0b503eaa86e310a5db738"
+        );
+        assert_eq!(
+            salted("hmac", "sha256", b"Jefe", message)?,
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        // Keys longer than a block are hashed first
+        assert_eq!(
+            salted(
+                "hmac",
+                "sha256",
+                &[0xaa; 131],
+                b"Test Using Larger Than Block-Size Key - Hash Key First"
+            )?,
+            "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn placement_bytes_round_trip() -> Result<(), HashassinError> {
+        for placement in ["prefix", "suffix", "hmac", "expression"] {
+            let placement: SaltPlacement = placement.parse()?;
+            assert_eq!(SaltPlacement::from_byte(placement.to_byte())?, placement);
+        }
+        assert!(SaltPlacement::from_byte(4).is_err());
+        assert!("middle".parse::<SaltPlacement>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn checks_the_algorithm_can_be_salted() -> Result<(), HashassinError> {
+        let md5 = hash::lookup("md5")?;
+        assert_eq!(SaltPlacement::Suffix.digest_len(md5.as_ref())?, 16);
+        assert!(SaltPlacement::Expression.digest_len(md5.as_ref()).is_err());
+        assert_eq!(
+            SaltPlacement::Expression.digest_len(hash::lookup("md5(p+salt)")?.as_ref())?,
+            16
+        );
+        assert!(
+            SaltPlacement::Prefix
+                .digest_len(hash::lookup("bcrypt")?.as_ref())
+                .is_err()
+        );
+        assert_eq!(random_salt(12).len(), 12);
+        Ok(())
+    }
+}
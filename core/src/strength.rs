@@ -0,0 +1,603 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+    io::{BufRead, BufReader},
+};
+
+use crate::{HashassinError, utils};
+
+/// Common passwords and password words that are always part of the dictionary.
+const COMMON_WORDS: &[&str] = &[
+    "password", "passwd", "pass", "qwerty", "letmein", "welcome", "admin", "login", "secret",
+    "master", "dragon", "monkey", "shadow", "sunshine", "princess", "iloveyou", "love", "hello",
+    "freedom", "whatever", "football", "baseball", "soccer", "hockey", "summer", "winter",
+    "spring", "autumn", "michael", "jordan", "superman", "batman", "trustno", "access", "flower",
+    "cheese", "computer", "internet", "starwars", "pokemon", "charlie", "killer", "abc", "test",
+    "guest", "root", "user",
+];
+
+/// Shortest substring reported as a dictionary word.
+const MIN_WORD_LEN: usize = 3;
+/// Longest unit a repeat is looked for with, such as the `abc` of `abcabc`.
+const MAX_REPEAT_UNIT: usize = 32;
+/// Shortest run of adjacent keys reported as a keyboard walk.
+const MIN_WALK_LEN: usize = 4;
+/// Number of distinct keys a keyboard walk can start on.
+const WALK_START_KEYS: f64 = 47.0;
+/// Average number of neighbours a walk can continue to.
+const WALK_NEIGHBOURS: f64 = 4.0;
+/// Number of distinct dates an attacker tries: every day of 200 years.
+const DATE_GUESSES: f64 = 365.0 * 200.0;
+/// Number of distinct years an attacker tries.
+const YEAR_GUESSES: f64 = 200.0;
+
+/// The US QWERTY layout, unshifted and shifted, one string per row.
+const KEYBOARD_ROWS: [(&str, &str); 4] = [
+    ("`1234567890-=", "~!@#$%^&*()_+"),
+    ("qwertyuiop[]\\", "QWERTYUIOP{}|"),
+    ("asdfghjkl;'", "ASDFGHJKL:\""),
+    ("zxcvbnm,./", "ZXCVBNM<>?"),
+];
+
+/// A predictable pattern found in a password.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Weakness {
+    /// A dictionary word, possibly capitalized or written in leetspeak.
+    Dictionary(String),
+    /// A run of neighbouring keys such as `qwerty` or `1qaz`.
+    KeyboardWalk(String),
+    /// A repeated character or substring such as `aaaa` or `abcabc`.
+    Repeat(String),
+    /// A calendar date or year such as `1987` or `12/05/1999`.
+    Date(String),
+}
+
+impl Weakness {
+    /// Short name of the kind of pattern.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Weakness::Dictionary(_) => "dictionary",
+            Weakness::KeyboardWalk(_) => "keyboard walk",
+            Weakness::Repeat(_) => "repeat",
+            Weakness::Date(_) => "date",
+        }
+    }
+}
+
+impl Display for Weakness {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (Weakness::Dictionary(s)
+        | Weakness::KeyboardWalk(s)
+        | Weakness::Repeat(s)
+        | Weakness::Date(s)) = self;
+        write!(f, "{} {s:?}", self.kind())
+    }
+}
+
+/// The estimated strength of one password.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Strength {
+    /// Estimated entropy in bits after accounting for every pattern found.
+    pub entropy_bits: f64,
+    /// Score from 0 (very weak) to 4 (very strong).
+    pub score: u8,
+    /// Patterns that lowered the estimate, in password order.
+    pub weaknesses: Vec<Weakness>,
+}
+
+impl Strength {
+    /// Human readable name of a score.
+    pub fn label(score: u8) -> &'static str {
+        match score {
+            0 => "very weak",
+            1 => "weak",
+            2 => "fair",
+            3 => "strong",
+            _ => "very strong",
+        }
+    }
+}
+
+/// A pattern match covering `start..end` of a password that an attacker can guess in `2^bits` tries.
+struct Match {
+    start: usize,
+    end: usize,
+    bits: f64,
+    weakness: Weakness,
+}
+
+/// Estimates password strength.
+///
+/// The baseline is the charset entropy: every character costs `log2` of the combined size of the character
+/// classes the password uses. Dictionary words, keyboard walks, repeats and dates are then priced at the
+/// number of guesses an attacker enumerating that pattern needs, and the cheapest non-overlapping set of
+/// patterns replaces the characters it covers.
+#[derive(Debug, Clone)]
+pub struct StrengthEstimator {
+    dictionary: HashSet<String>,
+    /// Length in characters of the longest dictionary word, beyond which no substring can match.
+    longest_word: usize,
+    keyboard: HashMap<char, (usize, usize)>,
+}
+
+impl Default for StrengthEstimator {
+    fn default() -> Self {
+        let keyboard = KEYBOARD_ROWS
+            .iter()
+            .enumerate()
+            .flat_map(|(row, (plain, shifted))| {
+                plain
+                    .chars()
+                    .enumerate()
+                    .chain(shifted.chars().enumerate())
+                    .map(move |(col, c)| (c, (row, col)))
+            })
+            .collect();
+        StrengthEstimator {
+            dictionary: COMMON_WORDS.iter().map(|w| w.to_string()).collect(),
+            longest_word: COMMON_WORDS.iter().map(|w| w.len()).max().unwrap_or(0),
+            keyboard,
+        }
+    }
+}
+
+impl StrengthEstimator {
+    /// An estimator using the built-in list of common passwords as its dictionary.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds every word of a wordlist, one per line, to the dictionary. Words are matched case-insensitively.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the wordlist cannot be read.
+    pub fn load_dictionary(&mut self, path: &str) -> Result<(), HashassinError> {
+        let reader = BufReader::new(utils::open_file(path)?);
+        for line in reader.lines() {
+            let word = line.map_err(|e| HashassinError::FileRead(e.to_string()))?;
+            let word = word.trim().to_lowercase();
+            let len = word.chars().count();
+            if len >= MIN_WORD_LEN {
+                self.longest_word = self.longest_word.max(len);
+                self.dictionary.insert(word);
+            }
+        }
+        Ok(())
+    }
+
+    /// Estimates the strength of a password.
+    pub fn estimate(&self, password: &str) -> Strength {
+        let chars: Vec<char> = password.chars().collect();
+        let char_bits = charset_size(&chars).log2();
+
+        let mut matches = self.dictionary_matches(&chars);
+        matches.extend(self.keyboard_walks(&chars));
+        matches.extend(self.repeats(&chars));
+        matches.extend(dates(&chars));
+
+        // Greedily keep the patterns that save the most bits without overlapping
+        let savings = |m: &Match| (m.end - m.start) as f64 * char_bits - m.bits;
+        matches.retain(|m| savings(m) > 0.0);
+        matches.sort_by(|a, b| savings(b).total_cmp(&savings(a)));
+        let mut covered = vec![false; chars.len()];
+        let mut kept: Vec<Match> = Vec::new();
+        for m in matches {
+            if covered[m.start..m.end].iter().all(|&c| !c) {
+                covered[m.start..m.end].iter_mut().for_each(|c| *c = true);
+                kept.push(m);
+            }
+        }
+        kept.sort_by_key(|m| m.start);
+
+        let uncovered = covered.iter().filter(|&&c| !c).count();
+        let entropy_bits = uncovered as f64 * char_bits + kept.iter().map(|m| m.bits).sum::<f64>();
+        Strength {
+            entropy_bits,
+            score: score(entropy_bits),
+            weaknesses: kept.into_iter().map(|m| m.weakness).collect(),
+        }
+    }
+
+    /// Substrings found in the dictionary, as written or after undoing common leetspeak substitutions.
+    fn dictionary_matches(&self, chars: &[char]) -> Vec<Match> {
+        let lower: Vec<char> = chars.iter().map(|c| c.to_ascii_lowercase()).collect();
+        let unleet: Vec<char> = lower.iter().map(|&c| unleet(c)).collect();
+        let word_bits = (self.dictionary.len() as f64).log2();
+
+        let mut matches = Vec::new();
+        for start in 0..chars.len() {
+            for end in start + MIN_WORD_LEN..=(start + self.longest_word).min(chars.len()) {
+                let plain: String = lower[start..end].iter().collect();
+                let decoded: String = unleet[start..end].iter().collect();
+                let leet = if self.dictionary.contains(&plain) {
+                    false
+                } else if self.dictionary.contains(&decoded) {
+                    true
+                } else {
+                    continue;
+                };
+                // One extra bit each for capitalization and leetspeak variants
+                let cased = chars[start..end].iter().any(char::is_ascii_uppercase);
+                matches.push(Match {
+                    start,
+                    end,
+                    bits: word_bits + f64::from(u8::from(cased)) + f64::from(u8::from(leet)),
+                    weakness: Weakness::Dictionary(chars[start..end].iter().collect()),
+                });
+            }
+        }
+        matches
+    }
+
+    /// Maximal runs of characters where every key neighbours the previous one.
+    fn keyboard_walks(&self, chars: &[char]) -> Vec<Match> {
+        let mut matches = Vec::new();
+        let mut start = 0;
+        while start < chars.len() {
+            let mut end = start + 1;
+            while end < chars.len() && self.adjacent(chars[end - 1], chars[end]) {
+                end += 1;
+            }
+            if end - start >= MIN_WALK_LEN {
+                matches.push(Match {
+                    start,
+                    end,
+                    bits: WALK_START_KEYS.log2()
+                        + (end - start - 1) as f64 * WALK_NEIGHBOURS.log2(),
+                    weakness: Weakness::KeyboardWalk(chars[start..end].iter().collect()),
+                });
+            }
+            start = end;
+        }
+        matches
+    }
+
+    /// Returns `true` if two keys touch on a QWERTY keyboard.
+    ///
+    /// Every row is shifted right by about half a key relative to the one above, so a key touches the keys at
+    /// the same and the next column of the row above, and the same and the previous column of the row below.
+    fn adjacent(&self, a: char, b: char) -> bool {
+        let (Some(&(ra, ca)), Some(&(rb, cb))) = (self.keyboard.get(&a), self.keyboard.get(&b))
+        else {
+            return false;
+        };
+        let dc = cb as isize - ca as isize;
+        match rb as isize - ra as isize {
+            0 => dc.abs() == 1,
+            -1 => dc == 0 || dc == 1,
+            1 => dc == 0 || dc == -1,
+            _ => false,
+        }
+    }
+
+    /// Substrings made of a shorter unit repeated at least twice, such as `aaa` or `abab`.
+    ///
+    /// Every stretch of a password that repeats with a period of `unit` characters is reported once with the
+    /// repeats aligned to its start and once aligned to its end, rather than once per start position.
+    fn repeats(&self, chars: &[char]) -> Vec<Match> {
+        let mut matches = Vec::new();
+        for unit in 1..=MAX_REPEAT_UNIT.min(chars.len() / 2) {
+            // How many characters from each position on equal the character `unit` positions later
+            let mut same = vec![0; chars.len() - unit + 1];
+            for i in (0..chars.len() - unit).rev() {
+                if chars[i] == chars[i + unit] {
+                    same[i] = same[i + 1] + 1;
+                }
+            }
+            for start in 0..chars.len() - unit {
+                if same[start] < unit || (start > 0 && same[start - 1] > 0) {
+                    continue;
+                }
+                let stretch_end = start + same[start] + unit;
+                let count = (stretch_end - start) / unit;
+                if count * unit < 3 {
+                    continue;
+                }
+                let mut starts = vec![start, stretch_end - count * unit];
+                starts.dedup();
+                for start in starts {
+                    let end = start + count * unit;
+                    // Guessing a repeat costs guessing the unit, as a word or by brute force, and the number of
+                    // repetitions
+                    let pattern = &chars[start..start + unit];
+                    let word: String = pattern.iter().map(char::to_ascii_lowercase).collect();
+                    let unit_bits = if self.dictionary.contains(&word) {
+                        (self.dictionary.len() as f64).log2()
+                    } else {
+                        unit as f64 * charset_size(pattern).log2()
+                    };
+                    matches.push(Match {
+                        start,
+                        end,
+                        bits: unit_bits + (count as f64).log2(),
+                        weakness: Weakness::Repeat(chars[start..end].iter().collect()),
+                    });
+                }
+            }
+        }
+        matches
+    }
+}
+
+/// Dates written as digits with optional `-`, `/` or `.` separators, and four digit years.
+fn dates(chars: &[char]) -> Vec<Match> {
+    let mut matches = Vec::new();
+    for start in 0..chars.len() {
+        for end in start + 4..=(start + 10).min(chars.len()) {
+            // Dates are ASCII only, which also keeps the byte slicing in `is_date` on char boundaries
+            if !chars[start..end]
+                .iter()
+                .all(|c| c.is_ascii_digit() || matches!(c, '-' | '/' | '.'))
+            {
+                break;
+            }
+            let s: String = chars[start..end].iter().collect();
+            let bits = if is_year(&s) {
+                YEAR_GUESSES.log2()
+            } else if is_date(&s) {
+                DATE_GUESSES.log2() + if s.len() > 8 { 1.0 } else { 0.0 }
+            } else {
+                continue;
+            };
+            matches.push(Match {
+                start,
+                end,
+                bits,
+                weakness: Weakness::Date(s),
+            });
+        }
+    }
+    matches
+}
+
+/// A four digit year between 1900 and 2099.
+fn is_year(s: &str) -> bool {
+    s.len() == 4 && s.parse::<u32>().is_ok_and(|y| (1900..=2099).contains(&y))
+}
+
+/// Day, month and year in any common order, with two or four digit years.
+fn is_date(s: &str) -> bool {
+    let parts: Vec<&str> = match s.find(['-', '/', '.']) {
+        Some(i) => s.split(&s[i..=i]).collect(),
+        None if s.len() == 6 => vec![&s[0..2], &s[2..4], &s[4..6]],
+        None if s.len() == 8 => {
+            // Either the year leads or it trails
+            return is_date(&format!("{}-{}-{}", &s[0..4], &s[4..6], &s[6..8]))
+                || is_date(&format!("{}-{}-{}", &s[0..2], &s[2..4], &s[4..8]));
+        }
+        None => return false,
+    };
+    let [a, b, c] = parts[..] else {
+        return false;
+    };
+    if [a, b, c]
+        .iter()
+        .any(|p| p.is_empty() || !p.bytes().all(|b| b.is_ascii_digit()))
+    {
+        return false;
+    }
+    let short = |p: &str| p.len() <= 2;
+    let day_month = |a: &str, b: &str| {
+        let (Ok(a), Ok(b)) = (a.parse::<u32>(), b.parse::<u32>()) else {
+            return false;
+        };
+        let (day, month) = (1..=31, 1..=12);
+        (day.contains(&a) && month.contains(&b)) || (month.contains(&a) && day.contains(&b))
+    };
+    // Day and month followed by the year, or a four digit year followed by month and day
+    (short(a) && short(b) && (c.len() == 2 || is_year(c)) && day_month(a, b))
+        || (is_year(a) && short(b) && short(c) && day_month(b, c))
+}
+
+/// Combined size of the character classes used by a password.
+fn charset_size(chars: &[char]) -> f64 {
+    let mut size = 0.0;
+    if chars.iter().any(char::is_ascii_lowercase) {
+        size += 26.0;
+    }
+    if chars.iter().any(char::is_ascii_uppercase) {
+        size += 26.0;
+    }
+    if chars.iter().any(char::is_ascii_digit) {
+        size += 10.0;
+    }
+    if chars.iter().any(|c| c.is_ascii_punctuation() || *c == ' ') {
+        size += 33.0;
+    }
+    if chars.iter().any(|c| !c.is_ascii()) {
+        size += 100.0;
+    }
+    f64::max(size, 1.0)
+}
+
+/// Undoes the most common leetspeak substitutions.
+fn unleet(c: char) -> char {
+    match c {
+        '@' | '4' => 'a',
+        '3' => 'e',
+        '1' | '!' => 'i',
+        '0' => 'o',
+        '$' | '5' => 's',
+        '7' => 't',
+        _ => c,
+    }
+}
+
+/// Maps an entropy estimate to a score from 0 to 4.
+fn score(entropy_bits: f64) -> u8 {
+    match entropy_bits {
+        b if b < 28.0 => 0,
+        b if b < 36.0 => 1,
+        b if b < 60.0 => 2,
+        b if b < 80.0 => 3,
+        _ => 4,
+    }
+}
+
+/// Scores every password of a file and prints one line per password followed by aggregate statistics.
+///
+/// # Arguments
+///
+/// * `in_file` - The path to the passwords, one per line.
+/// * `dictionary` - Optional wordlist added to the built-in dictionary of common passwords.
+///
+/// # Errors
+///
+/// Returns an error if the password file or the dictionary cannot be read.
+pub fn audit_passwords(in_file: &str, dictionary: Option<&str>) -> Result<(), HashassinError> {
+    let mut estimator = StrengthEstimator::new();
+    if let Some(path) = dictionary {
+        estimator.load_dictionary(path)?;
+    }
+    let reader = BufReader::new(utils::open_file(in_file)?);
+
+    let mut total = 0usize;
+    let mut entropy_sum = 0.0;
+    let mut scores = [0usize; 5];
+    let mut kinds: HashMap<&'static str, usize> = HashMap::new();
+    let mut seen = HashSet::new();
+    let mut duplicates = 0usize;
+
+    println!("SCORE\tENTROPY\tPASSWORD\tWEAKNESSES");
+    for line in reader.lines() {
+        let password = line.map_err(|e| HashassinError::FileRead(e.to_string()))?;
+        if password.is_empty() {
+            continue;
+        }
+        let strength = estimator.estimate(&password);
+        let weaknesses: Vec<String> = strength.weaknesses.iter().map(|w| w.to_string()).collect();
+        println!(
+            "{}\t{:.1}\t{}\t{}",
+            strength.score,
+            strength.entropy_bits,
+            password,
+            weaknesses.join(", ")
+        );
+
+        total += 1;
+        entropy_sum += strength.entropy_bits;
+        scores[strength.score as usize] += 1;
+        let found: HashSet<&'static str> = strength.weaknesses.iter().map(Weakness::kind).collect();
+        for kind in found {
+            *kinds.entry(kind).or_default() += 1;
+        }
+        if !seen.insert(password) {
+            duplicates += 1;
+        }
+    }
+
+    if total == 0 {
+        println!("No passwords to audit");
+        return Ok(());
+    }
+    let percent = |n: usize| n as f64 * 100.0 / total as f64;
+    println!();
+    println!("PASSWORDS AUDITED: {}", total);
+    println!("AVERAGE ENTROPY: {:.1} bits", entropy_sum / total as f64);
+    for (score, &count) in scores.iter().enumerate() {
+        println!(
+            "SCORE {} ({}): {} ({:.1}%)",
+            score,
+            Strength::label(score as u8),
+            count,
+            percent(count)
+        );
+    }
+    for kind in ["dictionary", "keyboard walk", "repeat", "date"] {
+        let count = kinds.get(kind).copied().unwrap_or(0);
+        println!(
+            "CONTAINS {}: {} ({:.1}%)",
+            kind.to_uppercase(),
+            count,
+            percent(count)
+        );
+    }
+    println!("DUPLICATES: {} ({:.1}%)", duplicates, percent(duplicates));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn weaknesses(password: &str) -> Vec<Weakness> {
+        StrengthEstimator::new().estimate(password).weaknesses
+    }
+
+    #[test]
+    fn finds_every_kind_of_weakness() {
+        assert_eq!(
+            weaknesses("P@ssw0rd"),
+            [Weakness::Dictionary("P@ssw0rd".to_string())]
+        );
+        assert_eq!(
+            weaknesses("zxcvbnm"),
+            [Weakness::KeyboardWalk("zxcvbnm".to_string())]
+        );
+        assert_eq!(
+            weaknesses("kzkzkzkz"),
+            [Weakness::Repeat("kzkzkzkz".to_string())]
+        );
+        assert_eq!(
+            weaknesses("x12/05/1999"),
+            [Weakness::Date("12/05/1999".to_string())]
+        );
+        assert_eq!(weaknesses("jq1987"), [Weakness::Date("1987".to_string())]);
+    }
+
+    #[test]
+    fn scores_by_entropy() {
+        let estimator = StrengthEstimator::new();
+        assert_eq!(estimator.estimate("password").score, 0);
+        assert_eq!(estimator.estimate("Password1987").score, 0);
+        assert_eq!(estimator.estimate("kT9#vQ2!mZ7@pL4$").score, 4);
+        assert_eq!(Strength::label(4), "very strong");
+    }
+
+    #[test]
+    fn recognizes_dates() {
+        for date in ["12051999", "19990512", "1999-05-12", "31/12/99", "5.6.2001"] {
+            assert!(is_date(date), "{date}");
+        }
+        for other in ["13/13/99", "123456", "1999", "12/05", "1-2-3-4"] {
+            assert!(!is_date(other), "{other}");
+        }
+    }
+
+    #[test]
+    fn handles_non_ascii_passwords() {
+        let estimator = StrengthEstimator::new();
+        for password in ["aé123", "é12/05/1999é", "１２３４５６", "ü1987"] {
+            estimator.estimate(password);
+        }
+        assert_eq!(weaknesses("ü1987"), [Weakness::Date("1987".to_string())]);
+    }
+
+    #[test]
+    fn handles_long_repetitive_passwords() {
+        let estimator = StrengthEstimator::new();
+        for password in ["a".repeat(256), "ab".repeat(200), "password".repeat(500)] {
+            let strength = estimator.estimate(&password);
+            assert_eq!(strength.score, 0, "{password}");
+        }
+        assert_eq!(
+            weaknesses("xabababy"),
+            [Weakness::Repeat("ababab".to_string())]
+        );
+    }
+
+    #[test]
+    fn loads_dictionaries() -> Result<(), HashassinError> {
+        let mut estimator = StrengthEstimator::new();
+        assert!(estimator.estimate("Correcthorse").weaknesses.is_empty());
+        estimator.load_dictionary(
+            utils::TempFile::new("strength-words", "correcthorse\nab\n")?.path(),
+        )?;
+        assert_eq!(
+            estimator.estimate("Correcthorse").weaknesses,
+            [Weakness::Dictionary("Correcthorse".to_string())]
+        );
+        Ok(())
+    }
+}
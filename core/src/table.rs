@@ -1 +1,667 @@
+#![deny(clippy::unwrap_used, clippy::expect_used)]
+use std::{
+    fs::File,
+    io::{self, Read, Write},
+    str,
+    sync::Arc,
+};
 
+use memmap2::Mmap;
+use rayon::prelude::*;
+
+#[cfg(feature = "async")]
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::{
+    HashassinError, VARIABLE_PASSWORD_LENGTH,
+    hash::{self, HashAlgorithm},
+    salt::SaltPlacement,
+};
+
+/// MAGIC WORD opening every rainbow table.
+pub const RAINBOW_TABLE_MAGIC: &[u8; 12] = b"rainbowtable";
+
+/// VERSION byte of rainbow tables.
+pub const RAINBOW_TABLE_VERSION: u8 = 1;
+
+/// CHARACTER SET SIZE of the printable ASCII characters, from the space to the tilde.
+const PRINTABLE_CHARSET_SIZE: u8 = 95;
+
+/// ASCII OFFSET of the printable ASCII characters.
+const PRINTABLE_ASCII_OFFSET: u8 = 32;
+
+/// Length of the PASSWORD LENGTH, CHARACTER SET SIZE, NUMBER OF LINKS and ASCII OFFSET fields.
+#[cfg(feature = "async")]
+const RAINBOW_TABLE_TAIL_LEN: usize = 1 + 16 + 16 + 1;
+
+/// VERSION byte of the hash files written by every command.
+///
+/// This is the second generation of the version 1 layout, replacing both it and the salted layout; it is
+/// numbered 3 because `SALTED_HASH_FILE_VERSION` had already taken 2. Both older versions are still read.
+///
+/// The ALGORITHM is followed by a FLAGS byte, the SALT PLACEMENT byte of salted files, the entries, the
+/// ENTRY COUNT (4 bytes, big endian) and a CRC-32 CHECKSUM of everything before it (4 bytes, big endian).
+/// Every entry is one PASSWORD LENGTH byte, one SALT LENGTH byte and the salt (salted files only), one LABEL
+/// LENGTH byte and the label (labeled files only), then the hash. Encoded hashes, such as PHC strings, are
+/// preceded by one HASH LENGTH byte; digests have the length of the algorithm.
+pub const HASH_FILE_VERSION: u8 = 3;
+
+/// VERSION byte of the salted hash files written before `HASH_FILE_VERSION`.
+///
+/// The ALGORITHM is followed by the PASSWORD LENGTH, as in version 1, and the SALT PLACEMENT byte. Every entry
+/// is the password length byte (only when PASSWORD LENGTH is `VARIABLE_PASSWORD_LENGTH`), one SALT LENGTH
+/// byte, the salt and the digest.
+pub const SALTED_HASH_FILE_VERSION: u8 = 2;
+
+/// FLAGS bit of hash files whose entries carry a salt.
+const SALTED: u8 = 0b01;
+/// FLAGS bit of hash files whose entries carry a label.
+const LABELED: u8 = 0b10;
+
+/// Everything in a rainbow table but its chains.
+///
+/// The MAGIC WORD and VERSION are followed by the ALGORITHM LENGTH (1 byte), the ALGORITHM, the PASSWORD
+/// LENGTH (1 byte), the CHARACTER SET SIZE and NUMBER OF LINKS (16 bytes each, big endian) and the ASCII
+/// OFFSET (1 byte). Every chain is its start and end password, PASSWORD LENGTH bytes each.
+#[derive(Debug, Clone)]
+pub struct RainbowTableHeader {
+    /// Unsalted algorithm with a fixed digest length.
+    pub algorithm: Arc<dyn HashAlgorithm>,
+    pub password_len: u8,
+    /// Number of characters passwords are made of, consecutive from `ascii_offset`.
+    pub charset_size: u8,
+    pub num_links: u32,
+    pub ascii_offset: u8,
+}
+
+impl RainbowTableHeader {
+    /// Header of a new table of printable ASCII passwords.
+    ///
+    /// # Errors
+    ///
+    /// Returns `HashassinError::UnknownAlgorithm` if the algorithm is salted or has variable-length output,
+    /// and `HashassinError::InvalidInput` if the password length or number of links is zero or does not fit
+    /// the table.
+    pub fn new(
+        algorithm: Arc<dyn HashAlgorithm>,
+        password_len: usize,
+        num_links: usize,
+    ) -> Result<Self, HashassinError> {
+        let header = RainbowTableHeader {
+            algorithm,
+            password_len: u8::try_from(password_len).map_err(|_| {
+                HashassinError::InvalidInput(format!(
+                    "Passwords of {password_len} characters do not fit a rainbow table"
+                ))
+            })?,
+            charset_size: PRINTABLE_CHARSET_SIZE,
+            num_links: u32::try_from(num_links).map_err(|_| {
+                HashassinError::InvalidInput(format!(
+                    "{num_links} links do not fit a rainbow table"
+                ))
+            })?,
+            ascii_offset: PRINTABLE_ASCII_OFFSET,
+        };
+        header.algorithm.fixed_digest_len()?;
+        header.check().map_err(HashassinError::InvalidInput)?;
+        Ok(header)
+    }
+
+    /// Length of every chain: its start and end password.
+    pub fn chain_len(&self) -> usize {
+        2 * self.password_len as usize
+    }
+
+    /// The characters passwords are made of.
+    pub fn charset(&self) -> Vec<u8> {
+        (self.ascii_offset..self.ascii_offset + self.charset_size).collect()
+    }
+
+    /// Reads and validates the header, leaving the reader at the first chain.
+    ///
+    /// # Errors
+    ///
+    /// Returns `HashassinError::InvalidFormat` if the header is truncated, lacks the magic word, has another
+    /// version, a password length or number of links of zero, or a character set outside ASCII,
+    /// `HashassinError::UnknownAlgorithm` if the algorithm does not exist or cannot be used for rainbow
+    /// tables, and `HashassinError::FileRead` if the reader fails.
+    pub fn read_from<R: Read>(reader: &mut R) -> Result<Self, HashassinError> {
+        let mut magic = [0u8; RAINBOW_TABLE_MAGIC.len()];
+        read_exact(reader, &mut magic)?;
+        check_magic(&magic)?;
+        check_rainbow_table_version(read_u8(reader)?)?;
+
+        let algo_len = read_u8(reader)?;
+        let algorithm = lookup(&read_vec(reader, algo_len.into())?)?;
+        algorithm.fixed_digest_len()?;
+        let password_len = read_u8(reader)?;
+        let charset_size = read_u128(reader)?;
+        let num_links = read_u128(reader)?;
+        let ascii_offset = read_u8(reader)?;
+
+        let header = RainbowTableHeader {
+            algorithm,
+            password_len,
+            charset_size: u8::try_from(charset_size).map_err(|_| {
+                invalid(format!(
+                    "character set of {charset_size} characters is not ASCII"
+                ))
+            })?,
+            num_links: u32::try_from(num_links)
+                .map_err(|_| invalid(format!("{num_links} links are too many")))?,
+            ascii_offset,
+        };
+        header.check().map_err(invalid)?;
+        Ok(header)
+    }
+
+    /// Reads and validates the header from an asynchronous reader, such as a client connection; see
+    /// `RainbowTableHeader::read_from`.
+    ///
+    /// # Errors
+    ///
+    /// Same as `RainbowTableHeader::read_from`.
+    #[cfg(feature = "async")]
+    pub async fn read_from_async<R: AsyncRead + Unpin>(
+        reader: &mut R,
+    ) -> Result<Self, HashassinError> {
+        // MAGIC WORD, VERSION and ALGORITHM LENGTH, then everything else at once
+        let mut data = vec![0u8; RAINBOW_TABLE_MAGIC.len() + 2];
+        read_exact_async(reader, &mut data).await?;
+        let (magic, rest) = data.split_at(RAINBOW_TABLE_MAGIC.len());
+        check_magic(magic)?;
+        check_rainbow_table_version(rest[0])?;
+
+        let known = data.len();
+        data.resize(known + rest[1] as usize + RAINBOW_TABLE_TAIL_LEN, 0);
+        read_exact_async(reader, &mut data[known..]).await?;
+        RainbowTableHeader::read_from(&mut data.as_slice())
+    }
+
+    /// Writes the header.
+    ///
+    /// # Errors
+    ///
+    /// Returns `HashassinError::WriteError` if the writer fails.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> Result<(), HashassinError> {
+        writer.write_all(&self.to_bytes()).map_err(write_error)
+    }
+
+    /// Writes the header to an asynchronous writer.
+    ///
+    /// # Errors
+    ///
+    /// Returns `HashassinError::WriteError` if the writer fails.
+    #[cfg(feature = "async")]
+    pub async fn write_to_async<W: AsyncWrite + Unpin>(
+        &self,
+        writer: &mut W,
+    ) -> Result<(), HashassinError> {
+        writer
+            .write_all(&self.to_bytes())
+            .await
+            .map_err(write_error)
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let name = self.algorithm.name();
+        let mut data = RAINBOW_TABLE_MAGIC.to_vec();
+        data.push(RAINBOW_TABLE_VERSION);
+        data.push(name.len() as u8);
+        data.extend_from_slice(name.as_bytes());
+        data.push(self.password_len);
+        data.extend_from_slice(&u128::from(self.charset_size).to_be_bytes());
+        data.extend_from_slice(&u128::from(self.num_links).to_be_bytes());
+        data.push(self.ascii_offset);
+        data
+    }
+
+    /// Checks the fields the algorithm does not cover.
+    fn check(&self) -> Result<(), String> {
+        if self.password_len == 0 {
+            return Err("password length of 0".to_string());
+        }
+        if self.num_links == 0 {
+            return Err("number of links of 0".to_string());
+        }
+        if self.charset_size == 0 || self.ascii_offset as usize + self.charset_size as usize > 128 {
+            return Err(format!(
+                "character set of {} characters from {} is not ASCII",
+                self.charset_size, self.ascii_offset
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// One chain of a mapped rainbow table, borrowed from the mapping.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Chain<'a> {
+    pub start: &'a [u8],
+    pub end: &'a [u8],
+}
+
+/// A rainbow table file mapped into memory.
+///
+/// Only the header is parsed when the file is opened; chains are handed out as slices of the mapping, so
+/// tables larger than the available memory can be searched and pages are loaded as they are visited.
+#[derive(Debug)]
+pub struct MappedRainbowTable {
+    pub header: RainbowTableHeader,
+    map: Mmap,
+    /// Offset of the first chain, the length of the header.
+    chains_start: usize,
+}
+
+impl MappedRainbowTable {
+    /// Maps a rainbow table and validates its header and size.
+    ///
+    /// The file must not be modified while it is mapped.
+    ///
+    /// # Errors
+    ///
+    /// Returns `HashassinError::FileOpen` if the file cannot be opened or mapped, the errors of
+    /// `RainbowTableHeader::read_from` for malformed headers, and `HashassinError::InvalidFormat` if the
+    /// chains do not fill whole chain lengths.
+    pub fn open(path: &str) -> Result<Self, HashassinError> {
+        let file = File::open(path).map_err(|e| {
+            HashassinError::FileOpen(format!("Error opening rainbow table {path}: {e}"))
+        })?;
+        // SAFETY: the mapping is read-only and the file is not modified by Hashassin while it is mapped;
+        // concurrent modification by another process is documented as unsupported.
+        let map = unsafe { Mmap::map(&file) }.map_err(|e| {
+            HashassinError::FileOpen(format!("Error mapping rainbow table {path}: {e}"))
+        })?;
+
+        let mut rest = &map[..];
+        let header = RainbowTableHeader::read_from(&mut rest)?;
+        let chains_start = map.len() - rest.len();
+        if !rest.len().is_multiple_of(header.chain_len()) {
+            return Err(invalid(format!(
+                "{path}: chains of {} bytes do not fill the {} bytes after the header",
+                header.chain_len(),
+                rest.len()
+            )));
+        }
+        Ok(MappedRainbowTable {
+            header,
+            map,
+            chains_start,
+        })
+    }
+
+    /// Number of chains.
+    pub fn len(&self) -> usize {
+        self.chain_data().len() / self.header.chain_len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chain_data().is_empty()
+    }
+
+    /// The chains in file order.
+    pub fn chains(&self) -> impl ExactSizeIterator<Item = Chain<'_>> {
+        self.chain_data()
+            .chunks_exact(self.header.chain_len())
+            .map(|chunk| self.split(chunk))
+    }
+
+    /// The chains as a rayon parallel iterator.
+    pub fn par_chains(&self) -> impl IndexedParallelIterator<Item = Chain<'_>> {
+        self.chain_data()
+            .par_chunks_exact(self.header.chain_len())
+            .map(|chunk| self.split(chunk))
+    }
+
+    fn chain_data(&self) -> &[u8] {
+        &self.map[self.chains_start..]
+    }
+
+    fn split<'a>(&self, chunk: &'a [u8]) -> Chain<'a> {
+        let (start, end) = chunk.split_at(self.header.password_len as usize);
+        Chain { start, end }
+    }
+}
+
+/// Everything in a hash file but its entries.
+///
+/// Version 1 files have no FLAGS; their ALGORITHM is followed by the PASSWORD LENGTH of every entry, or
+/// `VARIABLE_PASSWORD_LENGTH` if every entry starts with its own. See `SALTED_HASH_FILE_VERSION` for version
+/// 2 and `HASH_FILE_VERSION` for version 3.
+#[derive(Debug, Clone)]
+pub struct HashFileHeader {
+    /// VERSION byte the file was read with. Files are always written as `HASH_FILE_VERSION`.
+    pub version: u8,
+    pub algorithm: Arc<dyn HashAlgorithm>,
+    /// PASSWORD LENGTH shared by every entry of a version 1 or 2 file. `None` for version 3 files and older
+    /// files of variable length, whose entries record their own.
+    pub password_len: Option<u8>,
+    /// Salt placement of salted files.
+    pub placement: Option<SaltPlacement>,
+    /// Whether entries carry a label, such as the username of an imported hash.
+    pub labeled: bool,
+}
+
+impl HashFileHeader {
+    /// Header of a new hash file.
+    pub fn new(
+        algorithm: Arc<dyn HashAlgorithm>,
+        placement: Option<SaltPlacement>,
+        labeled: bool,
+    ) -> Self {
+        HashFileHeader {
+            version: HASH_FILE_VERSION,
+            algorithm,
+            password_len: None,
+            placement,
+            labeled,
+        }
+    }
+
+    /// Length of every hash, or `None` for encoded hashes that carry their own length.
+    ///
+    /// # Errors
+    ///
+    /// Returns `HashassinError::UnknownAlgorithm` if the algorithm cannot be stored with this salt placement.
+    pub fn hash_len(&self) -> Result<Option<usize>, HashassinError> {
+        match self.placement {
+            Some(placement) => placement.digest_len(self.algorithm.as_ref()).map(Some),
+            None if self.algorithm.digest_len().is_none() => Ok(None),
+            None => self.algorithm.fixed_digest_len().map(Some),
+        }
+    }
+
+    /// Reads and validates a version 1, 2 or 3 header, leaving the reader at the first entry.
+    ///
+    /// # Errors
+    ///
+    /// Returns `HashassinError::InvalidFormat` if the header is truncated or has an unknown version, flags or
+    /// salt placement, `HashassinError::UnknownAlgorithm` for algorithms that do not exist or cannot be
+    /// stored this way, and `HashassinError::FileRead` if the reader fails.
+    pub fn read_from<R: Read>(reader: &mut R) -> Result<Self, HashassinError> {
+        let version = read_u8(reader)?;
+        check_hash_file_version(version)?;
+        let algo_len = read_u8(reader)?;
+        let algorithm = lookup(&read_vec(reader, algo_len.into())?)?;
+
+        let header = match version {
+            1 | SALTED_HASH_FILE_VERSION => HashFileHeader {
+                version,
+                algorithm,
+                password_len: Some(read_u8(reader)?).filter(|&len| len != VARIABLE_PASSWORD_LENGTH),
+                placement: match version {
+                    SALTED_HASH_FILE_VERSION => Some(SaltPlacement::from_byte(read_u8(reader)?)?),
+                    _ => None,
+                },
+                labeled: false,
+            },
+            _ => {
+                let flags = read_u8(reader)?;
+                if flags & !(SALTED | LABELED) != 0 {
+                    return Err(invalid(format!("unknown flags {flags:#04x}")));
+                }
+                let placement = match flags & SALTED {
+                    0 => None,
+                    _ => Some(SaltPlacement::from_byte(read_u8(reader)?)?),
+                };
+                HashFileHeader {
+                    version,
+                    algorithm,
+                    password_len: None,
+                    placement,
+                    labeled: flags & LABELED != 0,
+                }
+            }
+        };
+        header.hash_len()?;
+        Ok(header)
+    }
+
+    /// Reads and validates a header from an asynchronous reader; see `HashFileHeader::read_from`.
+    ///
+    /// # Errors
+    ///
+    /// Same as `HashFileHeader::read_from`.
+    #[cfg(feature = "async")]
+    pub async fn read_from_async<R: AsyncRead + Unpin>(
+        reader: &mut R,
+    ) -> Result<Self, HashassinError> {
+        // VERSION and ALGORITHM LENGTH, then the ALGORITHM and the PASSWORD LENGTH or FLAGS byte
+        let mut data = vec![0u8; 2];
+        read_exact_async(reader, &mut data).await?;
+        check_hash_file_version(data[0])?;
+        data.resize(3 + data[1] as usize, 0);
+        read_exact_async(reader, &mut data[2..]).await?;
+        let salted = match data[0] {
+            SALTED_HASH_FILE_VERSION => true,
+            HASH_FILE_VERSION => data[data.len() - 1] & SALTED != 0,
+            _ => false,
+        };
+        if salted {
+            let mut placement = [0u8; 1];
+            read_exact_async(reader, &mut placement).await?;
+            data.push(placement[0]);
+        }
+        HashFileHeader::read_from(&mut data.as_slice())
+    }
+
+    /// Writes the header in version 3 layout, whatever version it was read with.
+    ///
+    /// # Errors
+    ///
+    /// Returns `HashassinError::UnknownAlgorithm` if the algorithm cannot be stored with the salt placement,
+    /// and `HashassinError::WriteError` if the writer fails.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> Result<(), HashassinError> {
+        self.hash_len()?;
+        writer.write_all(&self.to_bytes()).map_err(write_error)
+    }
+
+    /// Writes the header in version 3 layout to an asynchronous writer.
+    ///
+    /// # Errors
+    ///
+    /// Same as `HashFileHeader::write_to`.
+    #[cfg(feature = "async")]
+    pub async fn write_to_async<W: AsyncWrite + Unpin>(
+        &self,
+        writer: &mut W,
+    ) -> Result<(), HashassinError> {
+        self.hash_len()?;
+        writer
+            .write_all(&self.to_bytes())
+            .await
+            .map_err(write_error)
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let name = self.algorithm.name();
+        let mut data = vec![HASH_FILE_VERSION, name.len() as u8];
+        data.extend_from_slice(name.as_bytes());
+        let mut flags = 0;
+        if self.placement.is_some() {
+            flags |= SALTED;
+        }
+        if self.labeled {
+            flags |= LABELED;
+        }
+        data.push(flags);
+        if let Some(placement) = self.placement {
+            data.push(placement.to_byte());
+        }
+        data
+    }
+}
+
+pub(crate) fn invalid(msg: String) -> HashassinError {
+    HashassinError::InvalidFormat(msg)
+}
+
+pub(crate) fn truncated() -> HashassinError {
+    invalid("truncated file".to_string())
+}
+
+fn check_magic(magic: &[u8]) -> Result<(), HashassinError> {
+    match magic == RAINBOW_TABLE_MAGIC {
+        true => Ok(()),
+        false => Err(invalid("missing the rainbowtable magic word".to_string())),
+    }
+}
+
+fn check_rainbow_table_version(version: u8) -> Result<(), HashassinError> {
+    match version {
+        RAINBOW_TABLE_VERSION => Ok(()),
+        _ => Err(invalid(format!(
+            "unsupported rainbow table version {version}"
+        ))),
+    }
+}
+
+fn check_hash_file_version(version: u8) -> Result<(), HashassinError> {
+    match version {
+        1 | SALTED_HASH_FILE_VERSION | HASH_FILE_VERSION => Ok(()),
+        _ => Err(invalid(format!("unsupported hash file version {version}"))),
+    }
+}
+
+fn lookup(name: &[u8]) -> Result<Arc<dyn HashAlgorithm>, HashassinError> {
+    let name =
+        str::from_utf8(name).map_err(|_| invalid("algorithm name is not UTF-8".to_string()))?;
+    hash::lookup(name)
+}
+
+/// Running out of input means the file is truncated.
+fn read_error(e: io::Error) -> HashassinError {
+    match e.kind() {
+        io::ErrorKind::UnexpectedEof => truncated(),
+        _ => HashassinError::FileRead(e.to_string()),
+    }
+}
+
+fn write_error(e: io::Error) -> HashassinError {
+    HashassinError::WriteError(e.to_string())
+}
+
+fn read_exact<R: Read>(reader: &mut R, buf: &mut [u8]) -> Result<(), HashassinError> {
+    reader.read_exact(buf).map_err(read_error)
+}
+
+#[cfg(feature = "async")]
+async fn read_exact_async<R: AsyncRead + Unpin>(
+    reader: &mut R,
+    buf: &mut [u8],
+) -> Result<(), HashassinError> {
+    reader.read_exact(buf).await.map(drop).map_err(read_error)
+}
+
+pub(crate) fn read_u8<R: Read>(reader: &mut R) -> Result<u8, HashassinError> {
+    let mut byte = [0u8; 1];
+    read_exact(reader, &mut byte)?;
+    Ok(byte[0])
+}
+
+fn read_u128<R: Read>(reader: &mut R) -> Result<u128, HashassinError> {
+    let mut bytes = [0u8; 16];
+    read_exact(reader, &mut bytes)?;
+    Ok(u128::from_be_bytes(bytes))
+}
+
+pub(crate) fn read_vec<R: Read>(reader: &mut R, len: usize) -> Result<Vec<u8>, HashassinError> {
+    let mut data = vec![0u8; len];
+    read_exact(reader, &mut data)?;
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Message of the `InvalidFormat` error a rainbow table header is rejected with.
+    fn format_error(data: &[u8]) -> String {
+        match RainbowTableHeader::read_from(&mut &data[..]) {
+            Err(HashassinError::InvalidFormat(msg)) => msg,
+            other => panic!("expected an invalid format, got {other:?}"),
+        }
+    }
+
+    fn rainbow_table_header() -> Result<Vec<u8>, HashassinError> {
+        let header = RainbowTableHeader::new(hash::lookup("md5")?, 4, 1000)?;
+        let mut data = Vec::new();
+        header.write_to(&mut data)?;
+        Ok(data)
+    }
+
+    #[test]
+    fn round_trips_rainbow_table_header() -> Result<(), HashassinError> {
+        let mut data = rainbow_table_header()?;
+        assert_eq!(
+            data.len(),
+            RAINBOW_TABLE_MAGIC.len() + 2 + 3 + 1 + 16 + 16 + 1
+        );
+        data.extend_from_slice(b"aaaabbbb");
+
+        let mut reader = data.as_slice();
+        let header = RainbowTableHeader::read_from(&mut reader)?;
+        assert_eq!(header.algorithm.name(), "md5");
+        assert_eq!(header.password_len, 4);
+        assert_eq!(header.num_links, 1000);
+        assert_eq!(header.charset(), (b' '..=b'~').collect::<Vec<_>>());
+        assert_eq!(header.chain_len(), 8);
+        assert_eq!(reader, b"aaaabbbb");
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_bad_rainbow_table_headers() -> Result<(), HashassinError> {
+        let data = rainbow_table_header()?;
+        let version_at = RAINBOW_TABLE_MAGIC.len();
+
+        let mut magic = data.clone();
+        magic[0] = b'R';
+        assert_eq!(format_error(&magic), "missing the rainbowtable magic word");
+
+        let mut version = data.clone();
+        version[version_at] = 2;
+        assert_eq!(
+            format_error(&version),
+            "unsupported rainbow table version 2"
+        );
+
+        // The NUMBER OF LINKS is the 16 bytes before the ASCII OFFSET
+        let mut links = data.clone();
+        links[data.len() - 17..data.len() - 1].fill(0);
+        assert_eq!(format_error(&links), "number of links of 0");
+
+        for len in 0..data.len() {
+            assert_eq!(format_error(&data[..len]), "truncated file", "{len} bytes");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn rainbow_tables_need_unsalted_fixed_length_digests() -> Result<(), HashassinError> {
+        assert!(RainbowTableHeader::new(hash::lookup("scrypt")?, 4, 10).is_err());
+        assert!(RainbowTableHeader::new(hash::lookup("md5")?, 0, 10).is_err());
+        assert!(RainbowTableHeader::new(hash::lookup("md5")?, 4, 0).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn writes_hash_file_headers_as_version_3() -> Result<(), HashassinError> {
+        let header = HashFileHeader::new(hash::lookup("sha1")?, Some(SaltPlacement::Hmac), true);
+        let mut data = Vec::new();
+        header.write_to(&mut data)?;
+        assert_eq!(data[..2], [HASH_FILE_VERSION, 4]);
+        assert_eq!(data[6..], [SALTED | LABELED, SaltPlacement::Hmac.to_byte()]);
+
+        let read = HashFileHeader::read_from(&mut data.as_slice())?;
+        assert_eq!(read.placement, Some(SaltPlacement::Hmac));
+        assert!(read.labeled);
+
+        data[6] |= 0b100;
+        assert!(matches!(
+            HashFileHeader::read_from(&mut data.as_slice()),
+            Err(HashassinError::InvalidFormat(msg)) if msg == "unknown flags 0x07"
+        ));
+        Ok(())
+    }
+}
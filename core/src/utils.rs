@@ -71,3 +71,28 @@ pub(crate) fn create_print_to_file_thread(
 
     Ok(handle)
 }
+
+/// A file in the temporary directory, unique to this test process, that is deleted when dropped.
+#[cfg(test)]
+pub(crate) struct TempFile(String);
+
+#[cfg(test)]
+impl TempFile {
+    /// Writes `contents` to the file `name`.
+    pub(crate) fn new(name: &str, contents: impl AsRef<[u8]>) -> Result<Self, HashassinError> {
+        let path = std::env::temp_dir().join(format!("hashassin-{}-{name}", std::process::id()));
+        std::fs::write(&path, contents).map_err(|e| HashassinError::WriteError(e.to_string()))?;
+        Ok(TempFile(path.to_string_lossy().into_owned()))
+    }
+
+    pub(crate) fn path(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
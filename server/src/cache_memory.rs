@@ -154,6 +154,23 @@ impl Cache {
         Ok(chain_map)
     }
 
+    /// Returns the password lengths with uploaded chains for a given algorithm.
+    ///
+    /// # Arguments
+    /// * `algorithm` - The hashing algorithm to look up.
+    pub(crate) fn password_lengths(&self, algorithm: &str) -> Vec<u8> {
+        self.algorithms
+            .get(algorithm)
+            .map(|algo_cache| {
+                algo_cache
+                    .password_len
+                    .iter()
+                    .map(|entry| *entry.key() as u8)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
     /// Inserts a cracked password into the cache if it doesn't already exist.
     ///
     /// # Arguments
@@ -20,7 +20,7 @@ impl CrackLimiter {
     /// * `max` - The maximum number of concurrent operations allowed.
     ///
     /// # Example
-    /// ```
+    /// ```ignore
     /// let limiter = CrackLimiter::new(4);
     /// ```
     pub(crate) fn new(max: usize) -> Self {
@@ -36,12 +36,12 @@ impl CrackLimiter {
     /// If the limit is already reached, this function will block until a permit becomes available.
     ///
     /// # Example
-    /// ```
+    /// ```ignore
     /// limiter.acquire();
     /// // perform cracking work
     /// limiter.release();
     /// ```
-    pub(crate) fn acquire(&self) -> Result<(), PoisonError<std::sync::MutexGuard<usize>>> {
+    pub(crate) fn acquire(&self) -> Result<(), PoisonError<std::sync::MutexGuard<'_, usize>>> {
         let mut count = self.counter.lock()?;
         while *count >= self.max {
             count = self.cvar.wait(count)?;
@@ -54,10 +54,10 @@ impl CrackLimiter {
     /// This will unblock one waiting thread (if any) by notifying the condition variable.
     ///
     /// # Example
-    /// ```
+    /// ```ignore
     /// limiter.release();
     /// ```
-    pub(crate) fn release(&self) -> Result<(), PoisonError<std::sync::MutexGuard<usize>>> {
+    pub(crate) fn release(&self) -> Result<(), PoisonError<std::sync::MutexGuard<'_, usize>>> {
         let mut count = self.counter.lock()?;
         *count -= 1;
         self.cvar.notify_one();
@@ -1,11 +1,12 @@
 use crate::ServerError;
 use crate::cache_memory::{Cache, Chain, CrackedPassword};
-use hashassin_core::hash::{HashAlgorithm, hash_with_algorithm};
+use hashassin_core::HashassinError;
+use hashassin_core::hash::HashAlgorithm;
+use hashassin_core::hash_file::HashFile;
 use hashassin_core::reduction::reduce;
 use hex::encode as hex_encode;
 use std::collections::HashMap;
 use std::result;
-use std::str;
 use std::sync::Arc;
 use tokio::io::AsyncReadExt;
 use tokio::net::TcpStream;
@@ -27,53 +28,24 @@ pub(crate) async fn crack(
     stream: &mut TcpStream,
     cache: Arc<Mutex<Cache>>,
 ) -> result::Result<HashMap<String, String>, ServerError> {
-    let mut hash_version = [0u8; 1];
-    stream
-        .read_exact(&mut hash_version)
-        .await
-        .map_err(ServerError::IoError)?;
-
-    let mut algo_len = [0u8; 1];
-    stream
-        .read_exact(&mut algo_len)
-        .await
-        .map_err(ServerError::IoError)?;
-
-    let mut algorithm = vec![0u8; algo_len[0] as usize];
-    stream
-        .read_exact(&mut algorithm)
-        .await
-        .map_err(ServerError::IoError)?;
-    let algorithm_str = str::from_utf8(&algorithm)
-        .map_err(ServerError::Utf8Error)?
-        .to_lowercase();
-
-    let algorithm = match algorithm_str.as_str() {
-        "md5" => HashAlgorithm::Md5,
-        "sha256" => HashAlgorithm::Sha256,
-        "sha3_512" => HashAlgorithm::Sha3_512,
-        _ => return Err(ServerError::InvalidAlgorithm),
-    };
-
-    let mut password_len = [0u8; 1];
-    stream
-        .read_exact(&mut password_len)
-        .await
-        .map_err(ServerError::IoError)?;
-
-    let hash_len = match algorithm {
-        HashAlgorithm::Md5 => 16,
-        HashAlgorithm::Sha256 => 32,
-        HashAlgorithm::Sha3_512 | HashAlgorithm::Scrypt => 64,
-    };
-
     let mut buffer = Vec::new();
     stream
         .read_to_end(&mut buffer)
         .await
         .map_err(ServerError::IoError)?;
+    let UploadedHashes {
+        algorithm,
+        mut hashes_by_len,
+        unknown_len,
+    } = read_hashes(&buffer)?;
+    // Tables and cached passwords are stored under the canonical name
+    let algorithm_str = algorithm.name().to_string();
 
-    let hashes: Vec<String> = buffer.chunks_exact(hash_len).map(hex_encode).collect();
+    let hashes: Vec<&String> = hashes_by_len
+        .values()
+        .flatten()
+        .chain(&unknown_len)
+        .collect();
 
     let cracked_password: Option<HashMap<String, String>> = {
         let cache_guard = cache.lock().await;
@@ -81,7 +53,7 @@ pub(crate) async fn crack(
         let mut result = HashMap::new();
         for hash in &hashes {
             if let Ok(cracked) = cache_guard.get_cracked_password(&algorithm_str, hash) {
-                result.insert(hash.clone(), cracked.password);
+                result.insert(hash.to_string(), cracked.password);
             }
         }
 
@@ -95,21 +67,50 @@ pub(crate) async fn crack(
     match cracked_password {
         Some(cracked) => Ok(cracked),
         None => {
-            let chains = {
-                let cache_guard = cache.lock().await;
-                cache_guard.get_all_chains(&algorithm_str, password_len[0])
-            }?;
-
             let charset: Vec<u8> = (32..=126).collect();
 
-            let cracked_passwords = crack_passwords(
-                chains,
-                hashes,
-                algorithm.clone(),
-                password_len[0],
-                charset,
-                0,
-            )?;
+            // Hashes of unknown password length, such as imported ones, are tried with the chains of every length
+            if !unknown_len.is_empty() {
+                let lengths = cache.lock().await.password_lengths(&algorithm_str);
+                for password_len in lengths {
+                    hashes_by_len
+                        .entry(password_len)
+                        .or_default()
+                        .extend(unknown_len.iter().cloned());
+                }
+            }
+
+            // Every password length is cracked with the chains uploaded for that length
+            let mut found_table = false;
+            let mut cracked_passwords = HashMap::new();
+            for (password_len, hashes) in hashes_by_len {
+                let chains = {
+                    let cache_guard = cache.lock().await;
+                    cache_guard.get_all_chains(&algorithm_str, password_len)
+                };
+                let Ok(chains) = chains else {
+                    continue;
+                };
+                found_table = true;
+
+                if let Ok(found) = crack_passwords(
+                    chains,
+                    hashes,
+                    algorithm.as_ref(),
+                    password_len,
+                    charset.clone(),
+                    0,
+                ) {
+                    cracked_passwords.extend(found);
+                }
+            }
+
+            if !found_table {
+                return Err(ServerError::NoRainbowTableFound);
+            }
+            if cracked_passwords.is_empty() {
+                return Err(ServerError::NoPasswordsFound);
+            }
 
             let cache_guard = cache.lock().await;
             for (hash, password) in cracked_passwords.iter() {
@@ -122,6 +123,52 @@ pub(crate) async fn crack(
     }
 }
 
+/// The hashes of an uploaded hash file, in hex, grouped by the length of their password.
+struct UploadedHashes {
+    algorithm: Arc<dyn HashAlgorithm>,
+    hashes_by_len: HashMap<u8, Vec<String>>,
+    /// Hashes whose password length is unknown, such as imported ones.
+    unknown_len: Vec<String>,
+}
+
+/// Parses an uploaded hash file of any version.
+///
+/// # Returns
+///
+/// The hashes to crack, or `ServerError::InvalidHashFile` for malformed files, `ServerError::SaltedHashFile`
+/// for salted ones and `ServerError::InvalidAlgorithm` for algorithms rainbow tables cannot be built for.
+fn read_hashes(buffer: &[u8]) -> Result<UploadedHashes, ServerError> {
+    let hash_file = HashFile::parse(buffer).map_err(|e| match e {
+        HashassinError::UnknownAlgorithm(_) => ServerError::InvalidAlgorithm,
+        e => ServerError::InvalidHashFile(e),
+    })?;
+    if hash_file.header.placement.is_some() {
+        return Err(ServerError::SaltedHashFile);
+    }
+    let algorithm = hash_file.header.algorithm;
+    algorithm
+        .fixed_digest_len()
+        .map_err(|_| ServerError::InvalidAlgorithm)?;
+
+    // Every hash is cracked with the chains uploaded for the length of its password
+    let mut hashes_by_len: HashMap<u8, Vec<String>> = HashMap::new();
+    let mut unknown_len = Vec::new();
+    for entry in &hash_file.entries {
+        match entry.password_len {
+            Some(password_len) => hashes_by_len
+                .entry(password_len)
+                .or_default()
+                .push(hex_encode(&entry.hash)),
+            None => unknown_len.push(hex_encode(&entry.hash)),
+        }
+    }
+    Ok(UploadedHashes {
+        algorithm,
+        hashes_by_len,
+        unknown_len,
+    })
+}
+
 /// Cracks hashes using provided rainbow table chains by simulating forward and backward reductions.
 ///
 /// # Arguments
@@ -139,7 +186,7 @@ pub(crate) async fn crack(
 pub(crate) fn crack_passwords(
     rainbow_table_chains: HashMap<u32, Vec<Chain>>,
     hashes_to_crack: Vec<String>,
-    algorithm: HashAlgorithm,
+    algorithm: &dyn HashAlgorithm,
     password_len: u8,
     charset: Vec<u8>,
     ascii_offset: u8,
@@ -152,7 +199,9 @@ pub(crate) fn crack_passwords(
             // Reverse simulation from end of chain
             let mut pwd = chain.end_chain.clone();
             for _ in (0..num_links).rev() {
-                let hashed = hash_with_algorithm(&pwd, &algorithm);
+                let hashed = algorithm
+                    .hash(pwd.as_bytes())
+                    .map_err(ServerError::HashError)?;
                 pwd = reduce(
                     &hex_encode(&hashed),
                     password_len as usize,
@@ -164,7 +213,9 @@ pub(crate) fn crack_passwords(
             // Forward simulation from start of chain
             let mut candidate = chain.start_chain.clone();
             for _ in 0..num_links {
-                let hashed = hash_with_algorithm(&candidate, &algorithm);
+                let hashed = algorithm
+                    .hash(candidate.as_bytes())
+                    .map_err(ServerError::HashError)?;
                 let hash_hex = hex_encode(&hashed);
 
                 if hash_set.contains(&hash_hex) {
@@ -183,3 +234,65 @@ pub(crate) fn crack_passwords(
         Ok(found)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hashassin_core::hash;
+    use hashassin_core::hash_file::{HashEntry, HashFileWriter};
+    use hashassin_core::salt::SaltPlacement;
+    use hashassin_core::table::HashFileHeader;
+
+    #[test]
+    fn reads_version_1_files() -> Result<(), ServerError> {
+        let uploaded = read_hashes(include_bytes!("../../sample_outputs/100-sha256.hashes"))?;
+        assert_eq!(uploaded.algorithm.name(), "sha256");
+        assert_eq!(uploaded.hashes_by_len.len(), 1);
+        assert_eq!(uploaded.hashes_by_len.get(&4).map(Vec::len), Some(100));
+        assert!(uploaded.unknown_len.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn reads_version_3_files() -> Result<(), ServerError> {
+        let header = HashFileHeader::new(
+            hash::lookup("md5").map_err(ServerError::HashError)?,
+            None,
+            true,
+        );
+        let mut writer = HashFileWriter::new(Vec::new(), header).map_err(ServerError::HashError)?;
+        for (password_len, label) in [(Some(3), None), (None, Some("alice".to_string()))] {
+            let hash = hash::lookup("md5")
+                .and_then(|md5| md5.hash(b"abc"))
+                .map_err(ServerError::HashError)?;
+            let entry = HashEntry {
+                password_len,
+                salt: Vec::new(),
+                label,
+                hash,
+            };
+            writer.write_entry(&entry).map_err(ServerError::HashError)?;
+        }
+        let data = writer.finish().map_err(ServerError::HashError)?;
+
+        let uploaded = read_hashes(&data)?;
+        let md5 = "900150983cd24fb0d6963f7d28e17f72".to_string();
+        assert_eq!(uploaded.hashes_by_len.get(&3), Some(&vec![md5.clone()]));
+        assert_eq!(uploaded.unknown_len, vec![md5]);
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_salted_version_2_files() {
+        // VERSION 2, md5, PASSWORD LENGTH 3, suffix salts, then one entry
+        let mut data = vec![2, 3];
+        data.extend_from_slice(b"md5");
+        data.extend_from_slice(&[3, SaltPlacement::Suffix.to_byte(), 1, 0x42]);
+        data.extend_from_slice(&[0x11; 16]);
+        assert!(HashFile::parse(&data).is_ok());
+        assert!(matches!(
+            read_hashes(&data),
+            Err(ServerError::SaltedHashFile)
+        ));
+    }
+}
@@ -19,6 +19,10 @@ pub enum ServerError {
     CacheError,
     CachePoisonedError,
     InvalidAlgorithm,
+    SaltedHashFile,
+    InvalidHashFile(hashassin_core::HashassinError),
+    InvalidRainbowTable(hashassin_core::HashassinError),
+    HashError(hashassin_core::HashassinError),
     MutexError,
     UnableUnwrapArc,
     ChainError(io::Error),
@@ -37,6 +41,12 @@ impl std::fmt::Display for ServerError {
             ServerError::MetadataError => write!(f, "Metadata error"),
             ServerError::CacheError => write!(f, "Cache error"),
             ServerError::InvalidAlgorithm => write!(f, "Invalid algorithm"),
+            ServerError::InvalidHashFile(err) => write!(f, "Invalid hash file: {}", err),
+            ServerError::InvalidRainbowTable(err) => write!(f, "Invalid rainbow table: {}", err),
+            ServerError::HashError(err) => write!(f, "{}", err),
+            ServerError::SaltedHashFile => {
+                write!(f, "Salted hashes cannot be cracked with a rainbow table")
+            }
             ServerError::ChainError(error) => {
                 write!(f, "Chain error: {}", error)
             }
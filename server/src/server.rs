@@ -97,10 +97,7 @@ async fn handle_client(
     extract_metadata(&mut stream, magic_word_str).await?;
 
     if magic_word_str == "upload" {
-        let map_err = upload(&mut stream, Arc::clone(&cache))
-            .await
-            .map_err(|_| ServerError::CacheError);
-        match map_err {
+        match upload(&mut stream, Arc::clone(&cache)).await {
             Ok(response) => {
                 stream
                     .write_all(response.as_bytes())